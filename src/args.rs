@@ -1,15 +1,242 @@
-use clap::Parser;
-use std::fmt;
+use bedrock_up::backup::Retention;
+use bedrock_up::http::HttpSettings;
+use bedrock_up::version::{Change, Version};
+use bedrock_up::{DownloadType, SourceConfig};
+use bedrock_up::{health, mirror, supervisor};
+use clap::{Args, Parser, Subcommand};
 
 /// Manages Minecraft Bedrock Edition server updates.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    arg_required_else_help = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+
+    /// Running without a subcommand updates the server, as `update` does.
+    #[command(flatten)]
+    pub(crate) update: Option<UpdateArgs>,
+
+    /// Path to the bedrock-up config file.
+    #[arg(
+        long,
+        global = true,
+        env = "BEDROCK_UP_CONFIG",
+        default_value = "~/.bedrock-up/config.json"
+    )]
+    pub(crate) config: String,
+
+    #[command(flatten)]
+    pub(crate) http: HttpSettings,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Updates a server to the latest version (the default).
+    Update(UpdateArgs),
+    /// Archives the worlds and config files of each server.
+    Backup(BackupArgs),
+    /// Restores a server's worlds and config files from a backup.
+    Restore(RestoreArgs),
+    /// Downloads and extracts the next update ahead of time, to be applied
+    /// later with `apply`.
+    Stage(UpdateArgs),
+    /// Switches a server to another download type, e.g. from Windows to Linux
+    /// or to the preview channel, keeping its worlds, packs and config.
+    Migrate(UpdateArgs),
+    /// Copies a stable server's worlds, packs and config into a preview
+    /// server, and installs the latest preview release there.
+    Preview(PreviewArgs),
+    /// Applies the update prepared by `stage`.
+    Apply(ApplyArgs),
+    /// Checks the server's files against the installed release.
+    Verify(VerifyArgs),
+    /// Restores modified and missing files from the installed release.
+    Repair(RepairArgs),
+    /// Runs the server, restarting it when it crashes and applying updates
+    /// between restarts.
+    Run(RunArgs),
+    /// Attaches to the console of a server started with `run`, or sends it
+    /// one command.
+    #[command(visible_alias = "console")]
+    Attach(AttachArgs),
+    /// Shows the installed and running version, last update and disk usage of
+    /// each server.
+    Status(StatusArgs),
+    /// Mirrors the official releases for other bedrock-up instances.
+    #[command(subcommand)]
+    Mirror(MirrorCommand),
+    /// Installs and manages add-on packs.
+    #[command(subcommand)]
+    Pack(PackCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PackCommand {
+    /// Installs the packs in a .mcpack or .mcaddon and enables them in a world.
+    Install(PackInstallArgs),
+    /// Replaces installed packs with the newer versions in a .mcpack or .mcaddon.
+    Upgrade(PackUpgradeArgs),
+    /// Lists the packs installed with `pack install`.
+    List(PackListArgs),
+    /// Removes an installed pack from the server and its worlds.
+    Remove(PackRemoveArgs),
+    /// Reports the custom packs of the server and its worlds that a version is
+    /// likely to break.
+    Check(PackCheckArgs),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MirrorCommand {
+    /// Downloads the latest releases into the mirror directory once.
+    Sync(MirrorArgs),
+    /// Serves the mirror directory over HTTP, syncing it periodically.
+    Serve(MirrorServeArgs),
+    /// Creates the key pair that signs the mirror's release hashes.
+    Keygen(KeygenArgs),
+    /// Signs the hashes of the zips in the mirror directory.
+    Sign(SignArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct PackInstallArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    /// The .mcpack or .mcaddon to install.
+    pub(crate) file: String,
+
+    /// World to enable the packs in. Defaults to the server's level-name.
+    #[arg(short, long)]
+    pub(crate) world: Option<String>,
+
+    /// Replace a pack even when the same or a newer version is installed.
+    #[arg(short, long, default_value_t = false)]
+    pub(crate) force: bool,
+
+    /// Owner to give the pack files, as user[:group] names or ids. Unix only.
+    #[arg(long, env = "BEDROCK_UP_OWNER")]
+    pub(crate) owner: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PackUpgradeArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    /// The .mcpack or .mcaddon with the new versions.
+    pub(crate) file: String,
+
+    /// Replace a pack even when the same or a newer version is installed.
+    #[arg(short, long, default_value_t = false)]
+    pub(crate) force: bool,
+
+    /// Owner to give the pack files, as user[:group] names or ids. Unix only.
+    #[arg(long, env = "BEDROCK_UP_OWNER")]
+    pub(crate) owner: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PackListArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+}
+
+#[derive(Args, Debug)]
+pub struct PackRemoveArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    /// UUID or name of the pack to remove.
+    pub(crate) pack: String,
+}
+
+#[derive(Args, Debug)]
+pub struct PackCheckArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    /// The version to check the packs against, like 1.21.100.6. Defaults to the
+    /// installed version.
+    #[arg(long)]
+    pub(crate) against: Option<Version>,
+}
+
+#[derive(Args, Debug)]
+pub struct MirrorArgs {
+    /// Directory the zips and links.json are kept in.
+    #[arg(long, default_value = "~/.bedrock-up/mirror")]
+    pub(crate) dir: String,
+
+    #[command(flatten)]
+    pub(crate) links: LinksArgs,
+
+    /// Sign the release hashes with this secret key after each sync.
+    #[arg(long, env = "BEDROCK_UP_MIRROR_SECRET_KEY")]
+    pub(crate) secret_key: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct KeygenArgs {
+    /// Where to write the secret key. The public key is written next to it
+    /// with a `.pub` extension.
+    #[arg(long, default_value = "~/.bedrock-up/mirror.key")]
+    pub(crate) secret_key: String,
+}
+
+#[derive(Args, Debug)]
+pub struct SignArgs {
+    /// Directory the zips and links.json are kept in.
+    #[arg(long, default_value = "~/.bedrock-up/mirror")]
+    pub(crate) dir: String,
+
+    /// Secret key written by `mirror keygen`.
+    #[arg(
+        long,
+        env = "BEDROCK_UP_MIRROR_SECRET_KEY",
+        default_value = "~/.bedrock-up/mirror.key"
+    )]
+    pub(crate) secret_key: String,
+}
+
+#[derive(Args, Debug)]
+pub struct MirrorServeArgs {
+    #[command(flatten)]
+    pub(crate) mirror: MirrorArgs,
+
+    /// Address to listen on.
+    #[arg(long, default_value = "0.0.0.0:8080")]
+    pub(crate) listen: String,
+
+    /// Seconds between syncs.
+    #[arg(long, default_value_t = mirror::DEFAULT_SYNC_INTERVAL_SECS)]
+    pub(crate) sync_interval: u64,
+
+    /// Serve the directory as it is, without syncing, to hold the fleet on
+    /// the releases already mirrored.
+    #[arg(long, default_value_t = false)]
+    pub(crate) frozen: bool,
+}
+
+#[derive(Args, Debug)]
 pub struct UpdateArgs {
-    /// Which version of minecraft to download
+    /// Which version of minecraft to download. Detected from the installed
+    /// server when omitted.
     #[arg(short, long)]
-    pub(crate) download_type: DownloadType,
+    pub(crate) download_type: Option<DownloadType>,
 
-    /// Whether to force the update even if the version is the same.
+    /// Whether to force the update even if the version is the same, older
+    /// than a world was last opened with, or likely to break a custom pack.
     #[arg(short, long, default_value_t = false)]
     pub(crate) force: bool,
 
@@ -20,6 +247,11 @@ pub struct UpdateArgs {
     #[arg(short, long, default_value = "~/.bedrock-up/links.json")]
     pub(crate) cache_path: String,
 
+    /// Server binary hashes of the releases seen so far, to identify servers
+    /// installed without bedrock-up.
+    #[arg(long, default_value = "~/.bedrock-up/known-versions.json")]
+    pub(crate) known_versions: String,
+
     /// Excluded files to not update if they already exist.
     #[arg(
         short,
@@ -31,29 +263,271 @@ pub struct UpdateArgs {
         "allowlist.json"]
     )]
     pub(crate) exclude: Vec<String>,
-}
 
-use clap::ValueEnum;
+    // Repeats `LinksArgs`: clap never fills in a flattened `Option<UpdateArgs>`
+    // that flattens anything itself.
+    /// Links API endpoints to query, in order, before the built-in default.
+    #[arg(long, env = "BEDROCK_UP_LINKS_URL", value_delimiter = ',')]
+    pub(crate) links_url: Vec<String>,
+
+    /// Public download page to scrape when every links endpoint fails.
+    #[arg(long, env = "BEDROCK_UP_DOWNLOAD_PAGE_URL")]
+    pub(crate) download_page_url: Option<String>,
+
+    /// Only query the given endpoints, never the built-in defaults.
+    #[arg(long, default_value_t = false)]
+    pub(crate) no_fallback: bool,
+
+    /// Owner to give extracted files, as user[:group] names or ids. Unix only.
+    #[arg(long, env = "BEDROCK_UP_OWNER")]
+    pub(crate) owner: Option<String>,
+
+    /// Where releases come from: official, dir:<path>, index:<url> or mirror:<url>.
+    /// Defaults to the server's source in the config file.
+    #[arg(long, env = "BEDROCK_UP_SOURCE")]
+    pub(crate) source: Option<SourceConfig>,
 
-#[derive(Debug, Clone, ValueEnum)]
-pub enum DownloadType {
-    Windows,
-    Linux,
-    PreviewWindows,
-    PreviewLinux,
-    ServerJar,
+    /// Public key a mirror source's releases must be signed with. Defaults to
+    /// the source's publicKey in the config file.
+    #[arg(long, env = "BEDROCK_UP_MIRROR_KEY")]
+    pub(crate) mirror_key: Option<String>,
+
+    /// Start the server after updating and roll back if it doesn't come up.
+    #[arg(long, default_value_t = false)]
+    pub(crate) health_check: bool,
+
+    /// Seconds the health check waits for the server to start.
+    #[arg(long, default_value_t = health::DEFAULT_TIMEOUT_SECS)]
+    pub(crate) health_timeout: u64,
+
+    /// Wait until no players are online before applying the update.
+    #[arg(long, default_value_t = false)]
+    pub(crate) defer_until_empty: bool,
+
+    /// Longest the update waits for players to leave, in seconds. Defaults to an hour.
+    #[arg(long)]
+    pub(crate) max_deferral: Option<u64>,
+
+    /// Largest version change applied without --approve. Defaults to the
+    /// server's policy in the config file, or any change.
+    #[arg(long)]
+    pub(crate) auto_apply: Option<Change>,
+
+    /// Apply an update the update policy holds back.
+    #[arg(long, default_value_t = false)]
+    pub(crate) approve: bool,
 }
 
-impl fmt::Display for DownloadType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DownloadType::Windows => write!(f, "serverBedrockWindows"),
-            DownloadType::Linux => write!(f, "serverBedrockLinux"),
-            DownloadType::PreviewWindows => {
-                write!(f, "serverBedrockPreviewWindows")
-            }
-            DownloadType::PreviewLinux => write!(f, "serverBedrockPreviewLinux"),
-            DownloadType::ServerJar => write!(f, "serverJar"),
+impl UpdateArgs {
+    pub(crate) fn links(&self) -> LinksArgs {
+        LinksArgs {
+            links_url: self.links_url.clone(),
+            download_page_url: self.download_page_url.clone(),
+            no_fallback: self.no_fallback,
         }
     }
 }
+
+/// Where the official download links are read from.
+#[derive(Args, Debug, Default)]
+pub struct LinksArgs {
+    /// Links API endpoints to query, in order, before the built-in default.
+    #[arg(long, env = "BEDROCK_UP_LINKS_URL", value_delimiter = ',')]
+    pub(crate) links_url: Vec<String>,
+
+    /// Public download page to scrape when every links endpoint fails.
+    #[arg(long, env = "BEDROCK_UP_DOWNLOAD_PAGE_URL")]
+    pub(crate) download_page_url: Option<String>,
+
+    /// Only query the given endpoints, never the built-in defaults.
+    #[arg(long, default_value_t = false)]
+    pub(crate) no_fallback: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    #[arg(short, long, default_value = "~/.bedrock-up/links.json")]
+    pub(crate) cache_path: String,
+
+    /// Excluded files to not update if they already exist.
+    #[arg(
+        short,
+        long,
+        value_parser,
+        value_delimiter = ' ',
+        default_values = ["server.properties",
+        "permissions.json",
+        "allowlist.json"]
+    )]
+    pub(crate) exclude: Vec<String>,
+
+    /// Owner to give the updated files, as user[:group] names or ids. Unix only.
+    #[arg(long, env = "BEDROCK_UP_OWNER")]
+    pub(crate) owner: Option<String>,
+
+    /// Start the server after updating and roll back if it doesn't come up.
+    #[arg(long, default_value_t = false)]
+    pub(crate) health_check: bool,
+
+    /// Seconds the health check waits for the server to start.
+    #[arg(long, default_value_t = health::DEFAULT_TIMEOUT_SECS)]
+    pub(crate) health_timeout: u64,
+
+    /// Apply the staged release even if it is older than the installed one or
+    /// than a world was last opened with, or likely to break a custom pack.
+    #[arg(short, long, default_value_t = false)]
+    pub(crate) force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Minecraft server path. Should be the directory where the server files are located.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    /// Files the admin may change, which are not checked.
+    #[arg(
+        short,
+        long,
+        value_parser,
+        value_delimiter = ' ',
+        default_values = ["server.properties",
+        "permissions.json",
+        "allowlist.json"]
+    )]
+    pub(crate) exclude: Vec<String>,
+
+    /// Zip of the installed release. Defaults to the hashes recorded when it was
+    /// installed, or downloading it again.
+    #[arg(long)]
+    pub(crate) zip: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RepairArgs {
+    #[command(flatten)]
+    pub(crate) verify: VerifyArgs,
+
+    /// Owner to give restored files, as user[:group] names or ids. Unix only.
+    #[arg(long, env = "BEDROCK_UP_OWNER")]
+    pub(crate) owner: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PreviewArgs {
+    /// Stable server to copy, by its name in the config file or its path.
+    #[arg(long)]
+    pub(crate) from: String,
+
+    // The preview server is --server-path, created when missing.
+    #[command(flatten)]
+    pub(crate) update: UpdateArgs,
+
+    /// IPv4 port of the preview server, the IPv6 port being the next one.
+    /// Defaults to two above the stable server's.
+    #[arg(long)]
+    pub(crate) port: Option<u16>,
+
+    /// Level name of the preview server. Defaults to the stable server's with
+    /// " Preview" appended.
+    #[arg(long)]
+    pub(crate) level_name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub(crate) update: UpdateArgs,
+
+    /// Seconds between update checks while the server runs. 0 only checks at startup.
+    #[arg(long, default_value_t = 3600)]
+    pub(crate) update_interval: u64,
+
+    /// Seconds to wait before restarting a crashed server. Doubles with each crash in a row.
+    #[arg(long, default_value_t = 5)]
+    pub(crate) restart_delay: u64,
+
+    /// Longest wait between restarts, in seconds.
+    #[arg(long, default_value_t = 300)]
+    pub(crate) max_restart_delay: u64,
+
+    /// Seconds the server gets to shut down after `stop` before it is killed.
+    #[arg(long, default_value_t = supervisor::DEFAULT_STOP_TIMEOUT_SECS)]
+    pub(crate) stop_timeout: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct AttachArgs {
+    /// Minecraft server path of the server started with `run`.
+    #[arg(short, long)]
+    pub(crate) server_path: String,
+
+    /// Command to send instead of attaching, e.g. `say Restarting soon`.
+    #[arg(trailing_var_arg = true)]
+    pub(crate) command: Vec<String>,
+}
+
+/// Selects the servers a command acts on: the one given on the command line,
+/// or every server in the config file.
+#[derive(Args, Debug)]
+pub struct ServerArgs {
+    /// Minecraft server path. Defaults to every server in the config file.
+    #[arg(short, long)]
+    pub(crate) server_path: Option<String>,
+
+    /// Name of the server given by --server-path. Defaults to the directory name.
+    #[arg(short, long, requires = "server_path")]
+    pub(crate) name: Option<String>,
+
+    /// Config files of the server given by --server-path. Defaults to the update excludes.
+    #[arg(short, long, value_delimiter = ' ', requires = "server_path")]
+    pub(crate) exclude: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct BackupArgs {
+    #[command(flatten)]
+    pub(crate) server: ServerArgs,
+
+    #[command(flatten)]
+    pub(crate) retention: Retention,
+
+    /// Directory backups are written to, one subdirectory per server.
+    #[arg(short, long)]
+    pub(crate) backup_dir: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    #[command(flatten)]
+    pub(crate) server: ServerArgs,
+
+    /// Directory backups are read from, one subdirectory per server.
+    #[arg(short, long)]
+    pub(crate) backup_dir: Option<String>,
+
+    /// Backup archive to restore. Defaults to the server's latest backup.
+    pub(crate) archive: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    #[command(flatten)]
+    pub(crate) server: ServerArgs,
+
+    /// Download type to look up in the cache for servers without a manifest.
+    #[arg(short, long)]
+    pub(crate) download_type: Option<DownloadType>,
+
+    #[arg(short, long, default_value = "~/.bedrock-up/links.json")]
+    pub(crate) cache_path: String,
+
+    /// Server binary hashes of known releases, to identify servers without a
+    /// manifest.
+    #[arg(long, default_value = "~/.bedrock-up/known-versions.json")]
+    pub(crate) known_versions: String,
+}
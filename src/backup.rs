@@ -0,0 +1,692 @@
+use crate::config::ServerConfig;
+use chrono::{Datelike, NaiveDateTime};
+use clap::Args;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_BACKUP_DIR: &str = "~/.bedrock-up/backups";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+const WORLDS_DIR: &str = "worlds";
+/// Where a restore unpacks the backup, inside the server directory so its
+/// files can be renamed into place.
+const RESTORE_DIR: &str = ".bedrock-up-restore";
+
+/// How many backups to keep. Backups are only pruned when one is set.
+#[derive(Args, Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Retention {
+    /// Keep the most recent N backups
+    #[arg(long)]
+    pub keep_last: Option<usize>,
+
+    /// Keep the latest backup of each of the last N days
+    #[arg(long)]
+    pub keep_daily: Option<usize>,
+
+    /// Keep the latest backup of each of the last N weeks
+    #[arg(long)]
+    pub keep_weekly: Option<usize>,
+}
+
+impl Retention {
+    pub fn merged_with(&self, fallback: &Retention) -> Retention {
+        Retention {
+            keep_last: self.keep_last.or(fallback.keep_last),
+            keep_daily: self.keep_daily.or(fallback.keep_daily),
+            keep_weekly: self.keep_weekly.or(fallback.keep_weekly),
+        }
+    }
+
+    /// Whether any limit is set, i.e. whether backups should be pruned at all.
+    pub fn is_set(&self) -> bool {
+        self.keep_last.is_some() || self.keep_daily.is_some() || self.keep_weekly.is_some()
+    }
+}
+
+/// Archives the server's `worlds` directory and its config files into a
+/// timestamped zip in `backup_dir`. A backup taken in the same second as an
+/// earlier one gets a `-2`, `-3`... suffix instead of replacing it.
+pub fn create_backup(
+    server: &ServerConfig,
+    backup_dir: &Path,
+    now: NaiveDateTime,
+) -> std::io::Result<PathBuf> {
+    let server_path = PathBuf::from(shellexpand::tilde(&server.server_path).to_string());
+    if !server_path.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} is not a directory", server_path.display()),
+        ));
+    }
+
+    std::fs::create_dir_all(backup_dir)?;
+    let (archive, partial, file) = claim_archive(backup_dir, &server.name(), now)?;
+
+    let mut files = Vec::new();
+    collect_files(&server_path, Path::new(WORLDS_DIR), &mut files)?;
+    for file in server.exclude() {
+        if server_path.join(&file).is_file() {
+            files.push(PathBuf::from(file));
+        }
+    }
+
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+    for file in files {
+        let name = file
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        writer.start_file(name, options)?;
+        std::io::copy(
+            &mut std::fs::File::open(server_path.join(&file))?,
+            &mut writer,
+        )?;
+    }
+    writer.finish()?.flush()?;
+
+    std::fs::rename(&partial, &archive)?;
+    Ok(archive)
+}
+
+/// Picks the first free name for a backup of `name` taken at `now` and
+/// creates its partial file, which the backup is written to so a failed one
+/// is never listed. Creating the partial file claims the name, so concurrent
+/// backups can't pick the same one.
+fn claim_archive(
+    backup_dir: &Path,
+    name: &str,
+    now: NaiveDateTime,
+) -> std::io::Result<(PathBuf, PathBuf, std::fs::File)> {
+    let timestamp = now.format(TIMESTAMP_FORMAT);
+    let mut suffix = 1;
+    loop {
+        let archive = match suffix {
+            1 => backup_dir.join(format!("{}-{}.zip", name, timestamp)),
+            _ => backup_dir.join(format!("{}-{}-{}.zip", name, timestamp, suffix)),
+        };
+        suffix += 1;
+        if archive.exists() {
+            continue;
+        }
+        let partial = archive.with_extension("zip.partial");
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&partial)
+        {
+            Ok(file) => return Ok((archive, partial, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Adds every file under `root/relative` to `files`, as paths relative to `root`.
+fn collect_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let dir = root.join(relative);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let relative = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &relative, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// The server's backups in `backup_dir`, newest first.
+pub fn list_backups(
+    backup_dir: &Path,
+    name: &str,
+) -> std::io::Result<Vec<(NaiveDateTime, PathBuf)>> {
+    let entries = match std::fs::read_dir(backup_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let prefix = format!("{}-", name);
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let taken = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| file_name.strip_prefix(&prefix))
+            .and_then(|file_name| file_name.strip_suffix(".zip"))
+            .and_then(parse_backup_stem);
+        if let Some(taken) = taken {
+            backups.push((taken, path));
+        }
+    }
+
+    backups.sort_by_key(|(taken, _)| std::cmp::Reverse(*taken));
+    Ok(backups
+        .into_iter()
+        .map(|((timestamp, _), path)| (timestamp, path))
+        .collect())
+}
+
+/// The timestamp of a backup and its suffix, 1 for the first backup taken in
+/// that second.
+fn parse_backup_stem(stem: &str) -> Option<(NaiveDateTime, u32)> {
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(stem, TIMESTAMP_FORMAT) {
+        return Some((timestamp, 1));
+    }
+    let (timestamp, suffix) = stem.rsplit_once('-')?;
+    Some((
+        NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?,
+        suffix.parse().ok()?,
+    ))
+}
+
+/// Deletes the backups `retention` doesn't keep and returns their paths.
+pub fn prune_backups(
+    backup_dir: &Path,
+    name: &str,
+    retention: &Retention,
+) -> std::io::Result<Vec<PathBuf>> {
+    let backups = list_backups(backup_dir, name)?;
+    let timestamps: Vec<_> = backups.iter().map(|(timestamp, _)| *timestamp).collect();
+    let keep = backups_to_keep(&timestamps, retention);
+
+    let mut removed = Vec::new();
+    for ((_, path), keep) in backups.into_iter().zip(keep) {
+        if !keep {
+            std::fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// Marks which of the newest-first `timestamps` the retention policy keeps. A
+/// backup is kept when any of the rules selects it.
+fn backups_to_keep(timestamps: &[NaiveDateTime], retention: &Retention) -> Vec<bool> {
+    let mut keep = vec![false; timestamps.len()];
+
+    for slot in keep.iter_mut().take(retention.keep_last.unwrap_or(0)) {
+        *slot = true;
+    }
+
+    keep_one_per_period(timestamps, retention.keep_daily, &mut keep, |timestamp| {
+        (timestamp.year(), timestamp.ordinal())
+    });
+    keep_one_per_period(timestamps, retention.keep_weekly, &mut keep, |timestamp| {
+        let week = timestamp.iso_week();
+        (week.year(), week.week())
+    });
+
+    keep
+}
+
+/// Keeps the newest backup of each of the `count` most recent periods.
+fn keep_one_per_period(
+    timestamps: &[NaiveDateTime],
+    count: Option<usize>,
+    keep: &mut [bool],
+    period: impl Fn(&NaiveDateTime) -> (i32, u32),
+) {
+    let Some(count) = count else {
+        return;
+    };
+
+    let mut seen = Vec::new();
+    for (i, timestamp) in timestamps.iter().enumerate() {
+        let period = period(timestamp);
+        if seen.contains(&period) {
+            continue;
+        }
+        if seen.len() == count {
+            break;
+        }
+        seen.push(period);
+        keep[i] = true;
+    }
+}
+
+/// Replaces the server's worlds and config files with the archive contents.
+/// Worlds present in the archive are replaced as a whole so no stale files
+/// remain. The archive is unpacked inside the server directory first and then
+/// renamed into place, so a backup that fails to unpack leaves the server as
+/// it was, and a failed rename puts back what was already replaced.
+pub fn restore_backup(archive: &Path, server_path: &str) -> std::io::Result<()> {
+    let server_path = PathBuf::from(shellexpand::tilde(server_path).to_string());
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+
+    let restore_dir = server_path.join(RESTORE_DIR);
+    let replaced = restore_dir.join("replaced");
+    if replaced.exists() {
+        return Err(std::io::Error::other(format!(
+            "{} holds files an earlier restore couldn't put back, move them back or remove it",
+            replaced.display()
+        )));
+    }
+    // An unpacked backup left behind by a restore that was interrupted.
+    remove_dir_if_exists(&restore_dir)?;
+
+    let unpacked = restore_dir.join("backup");
+    let result =
+        extract(&mut archive, &unpacked).and_then(|()| swap_in(&server_path, &unpacked, &replaced));
+    match result {
+        // Drops the replaced worlds along with what is left of the unpacked
+        // backup.
+        Ok(()) => std::fs::remove_dir_all(&restore_dir),
+        Err(e) => {
+            remove_dir_if_exists(&unpacked)?;
+            // Still there when a replaced file couldn't be put back.
+            if !replaced.exists() {
+                std::fs::remove_dir_all(&restore_dir)?;
+            }
+            Err(e)
+        }
+    }
+}
+
+fn remove_dir_if_exists(dir: &Path) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn extract(archive: &mut zip::ZipArchive<std::fs::File>, dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(relative_path) = file.enclosed_name() else {
+            continue;
+        };
+        let out_path = dir.join(relative_path);
+        if file.is_dir() {
+            std::fs::create_dir_all(&out_path)?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut std::fs::File::create(&out_path)?)?;
+    }
+    Ok(())
+}
+
+/// Renames each world and each other file in `unpacked` over its counterpart
+/// in the server, moving the one it replaces into `replaced`. When a rename
+/// fails, the ones before it are undone and `replaced` is removed, unless
+/// something in it couldn't be put back.
+fn swap_in(server_path: &Path, unpacked: &Path, replaced: &Path) -> std::io::Result<()> {
+    let mut entries = Vec::new();
+    let mut other_files = Vec::new();
+    let mut top_level = std::fs::read_dir(unpacked)?.collect::<Result<Vec<_>, _>>()?;
+    top_level.sort_by_key(|entry| entry.file_name());
+    for entry in top_level {
+        let relative = PathBuf::from(entry.file_name());
+        if relative == Path::new(WORLDS_DIR) {
+            let mut worlds = std::fs::read_dir(entry.path())?.collect::<Result<Vec<_>, _>>()?;
+            worlds.sort_by_key(|world| world.file_name());
+            entries.extend(worlds.iter().map(|world| relative.join(world.file_name())));
+        } else if entry.file_type()?.is_dir() {
+            collect_files(unpacked, &relative, &mut other_files)?;
+        } else {
+            other_files.push(relative);
+        }
+    }
+    entries.extend(other_files);
+
+    let mut swapped = Vec::new();
+    for relative in entries {
+        match swap(server_path, unpacked, replaced, &relative) {
+            Ok(had_old) => swapped.push((relative, had_old)),
+            Err(e) => {
+                for (relative, had_old) in swapped.iter().rev() {
+                    let target = server_path.join(relative);
+                    let _ = std::fs::rename(&target, unpacked.join(relative));
+                    if *had_old {
+                        let _ = std::fs::rename(replaced.join(relative), &target);
+                    }
+                }
+                let mut left = Vec::new();
+                if replaced.is_dir() {
+                    collect_files(replaced, Path::new(""), &mut left)?;
+                }
+                if left.is_empty() {
+                    remove_dir_if_exists(replaced)?;
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renames `relative` from `unpacked` into the server, moving what was there
+/// into `replaced`. Returns whether there was something to move.
+fn swap(
+    server_path: &Path,
+    unpacked: &Path,
+    replaced: &Path,
+    relative: &Path,
+) -> std::io::Result<bool> {
+    let target = server_path.join(relative);
+    let old = replaced.join(relative);
+    for dir in [target.parent(), old.parent()].into_iter().flatten() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let had_old = match std::fs::rename(&target, &old) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+    if let Err(e) = std::fs::rename(unpacked.join(relative), &target) {
+        if had_old {
+            let _ = std::fs::rename(&old, &target);
+        }
+        return Err(e);
+    }
+    Ok(had_old)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use tempfile::TempDir;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn test_server(temp_dir: &TempDir) -> ServerConfig {
+        let server_path = temp_dir.path().join("survival");
+        std::fs::create_dir_all(server_path.join("worlds/Bedrock level/db")).unwrap();
+        std::fs::write(server_path.join("worlds/Bedrock level/level.dat"), "level").unwrap();
+        std::fs::write(server_path.join("worlds/Bedrock level/db/CURRENT"), "db").unwrap();
+        std::fs::write(
+            server_path.join("server.properties"),
+            "level-name=Bedrock level",
+        )
+        .unwrap();
+        std::fs::write(server_path.join("bedrock_server"), "binary").unwrap();
+
+        ServerConfig {
+            server_path: server_path.to_str().unwrap().to_owned(),
+            ..ServerConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_create_backup_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let server = test_server(&temp_dir);
+        let backup_dir = temp_dir.path().join("backups");
+
+        let archive = create_backup(&server, &backup_dir, at(2025, 7, 4, 12)).unwrap();
+
+        assert_eq!(archive, backup_dir.join("survival-20250704-120000.zip"));
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(archive).unwrap()).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(|name| name.to_owned()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "server.properties",
+                "worlds/Bedrock level/db/CURRENT",
+                "worlds/Bedrock level/level.dat",
+            ]
+        );
+        let mut content = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("worlds/Bedrock level/level.dat").unwrap(),
+            &mut content,
+        )
+        .unwrap();
+        assert_eq!(content, "level");
+    }
+
+    #[test]
+    fn test_create_backup_in_the_same_second() {
+        let temp_dir = TempDir::new().unwrap();
+        let server = test_server(&temp_dir);
+        let backup_dir = temp_dir.path().join("backups");
+
+        let first = create_backup(&server, &backup_dir, at(2025, 7, 4, 12)).unwrap();
+        let second = create_backup(&server, &backup_dir, at(2025, 7, 4, 12)).unwrap();
+
+        assert_eq!(first, backup_dir.join("survival-20250704-120000.zip"));
+        assert_eq!(second, backup_dir.join("survival-20250704-120000-2.zip"));
+        let backups = list_backups(&backup_dir, "survival").unwrap();
+        assert_eq!(
+            backups,
+            vec![(at(2025, 7, 4, 12), second), (at(2025, 7, 4, 12), first)]
+        );
+    }
+
+    #[test]
+    fn test_create_backup_missing_server() {
+        let temp_dir = TempDir::new().unwrap();
+        let server = ServerConfig {
+            server_path: temp_dir.path().join("missing").to_str().unwrap().to_owned(),
+            ..ServerConfig::default()
+        };
+
+        let result = create_backup(
+            &server,
+            &temp_dir.path().join("backups"),
+            at(2025, 7, 4, 12),
+        );
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_list_backups_newest_first() {
+        let temp_dir = TempDir::new().unwrap();
+        for file in [
+            "survival-20250704-120000.zip",
+            "survival-20250706-120000.zip",
+            "survival-20250705-120000.zip",
+            "survival-20250707-120000.zip.partial",
+            "creative-20250708-120000.zip",
+            "notes.txt",
+        ] {
+            std::fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+
+        let result = list_backups(temp_dir.path(), "survival").unwrap();
+
+        let timestamps: Vec<_> = result.iter().map(|(timestamp, _)| *timestamp).collect();
+        assert_eq!(
+            timestamps,
+            vec![at(2025, 7, 6, 12), at(2025, 7, 5, 12), at(2025, 7, 4, 12)]
+        );
+    }
+
+    #[test]
+    fn test_backups_to_keep_last() {
+        let timestamps = [at(2025, 7, 4, 3), at(2025, 7, 4, 2), at(2025, 7, 4, 1)];
+        let retention = Retention {
+            keep_last: Some(2),
+            ..Retention::default()
+        };
+
+        assert_eq!(
+            backups_to_keep(&timestamps, &retention),
+            vec![true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_backups_to_keep_daily() {
+        let timestamps = [
+            at(2025, 7, 4, 18),
+            at(2025, 7, 4, 6),
+            at(2025, 7, 3, 18),
+            at(2025, 7, 2, 18),
+        ];
+        let retention = Retention {
+            keep_daily: Some(2),
+            ..Retention::default()
+        };
+
+        assert_eq!(
+            backups_to_keep(&timestamps, &retention),
+            vec![true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_backups_to_keep_weekly_and_last() {
+        // 2025-07-07 is a Monday, so the 6th and 5th share an ISO week
+        let timestamps = [
+            at(2025, 7, 7, 12),
+            at(2025, 7, 6, 12),
+            at(2025, 7, 5, 12),
+            at(2025, 6, 28, 12),
+            at(2025, 6, 20, 12),
+        ];
+        let retention = Retention {
+            keep_last: Some(1),
+            keep_weekly: Some(3),
+            ..Retention::default()
+        };
+
+        assert_eq!(
+            backups_to_keep(&timestamps, &retention),
+            vec![true, true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_prune_backups() {
+        let temp_dir = TempDir::new().unwrap();
+        for file in [
+            "survival-20250704-120000.zip",
+            "survival-20250705-120000.zip",
+            "survival-20250706-120000.zip",
+        ] {
+            std::fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+        let retention = Retention {
+            keep_last: Some(1),
+            ..Retention::default()
+        };
+
+        let removed = prune_backups(temp_dir.path(), "survival", &retention).unwrap();
+
+        assert_eq!(removed.len(), 2);
+        assert!(
+            temp_dir
+                .path()
+                .join("survival-20250706-120000.zip")
+                .exists()
+        );
+        assert!(
+            !temp_dir
+                .path()
+                .join("survival-20250704-120000.zip")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_restore_backup_replaces_worlds() {
+        let temp_dir = TempDir::new().unwrap();
+        let server = test_server(&temp_dir);
+        let server_path = PathBuf::from(&server.server_path);
+        let archive = create_backup(
+            &server,
+            &temp_dir.path().join("backups"),
+            at(2025, 7, 4, 12),
+        )
+        .unwrap();
+
+        // Play on after the backup was taken
+        std::fs::write(
+            server_path.join("worlds/Bedrock level/level.dat"),
+            "changed",
+        )
+        .unwrap();
+        std::fs::write(
+            server_path.join("worlds/Bedrock level/db/000005.ldb"),
+            "new",
+        )
+        .unwrap();
+        std::fs::write(server_path.join("server.properties"), "level-name=changed").unwrap();
+        std::fs::create_dir_all(server_path.join("worlds/Other level")).unwrap();
+
+        restore_backup(&archive, &server.server_path).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(server_path.join("worlds/Bedrock level/level.dat")).unwrap(),
+            "level"
+        );
+        assert!(
+            !server_path
+                .join("worlds/Bedrock level/db/000005.ldb")
+                .exists()
+        );
+        assert_eq!(
+            std::fs::read_to_string(server_path.join("server.properties")).unwrap(),
+            "level-name=Bedrock level"
+        );
+        // Worlds that aren't in the backup are left alone
+        assert!(server_path.join("worlds/Other level").exists());
+        assert!(server_path.join("bedrock_server").exists());
+        assert!(!server_path.join(RESTORE_DIR).exists());
+    }
+
+    #[test]
+    fn test_restore_corrupt_backup_keeps_worlds() {
+        let temp_dir = TempDir::new().unwrap();
+        let server = test_server(&temp_dir);
+        let server_path = PathBuf::from(&server.server_path);
+        let archive = temp_dir.path().join("survival-20250704-120000.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in [
+            ("worlds/Bedrock level/level.dat", "restored level"),
+            ("worlds/Bedrock level/db/CURRENT", "restored db"),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        // Damage the second entry so it fails its checksum.
+        let mut bytes = std::fs::read(&archive).unwrap();
+        let at = bytes
+            .windows(b"restored db".len())
+            .position(|window| window == b"restored db")
+            .unwrap();
+        bytes[at] = b'R';
+        std::fs::write(&archive, bytes).unwrap();
+
+        assert!(restore_backup(&archive, &server.server_path).is_err());
+
+        assert_eq!(
+            std::fs::read_to_string(server_path.join("worlds/Bedrock level/level.dat")).unwrap(),
+            "level"
+        );
+        assert_eq!(
+            std::fs::read_to_string(server_path.join("worlds/Bedrock level/db/CURRENT")).unwrap(),
+            "db"
+        );
+        assert!(!server_path.join(RESTORE_DIR).exists());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::permissions::{self, Owner};
+use crate::verify::FileHashes;
+use crate::world::{self, WORLDS_DIR};
+use std::path::{Path, PathBuf};
+
+/// Directories add-on packs are installed in, next to the packs the release
+/// ships.
+pub const PACK_DIRS: &[&str] = &[
+    "behavior_packs",
+    "resource_packs",
+    "development_behavior_packs",
+    "development_resource_packs",
+];
+
+/// What [`copy_server_data`] copied, relative to the server directories.
+#[derive(Debug, Default, PartialEq)]
+pub struct Copied {
+    pub worlds: Vec<PathBuf>,
+    pub packs: Vec<PathBuf>,
+    pub config_files: Vec<String>,
+}
+
+/// Copies the worlds, add-on packs and `config_files` of the server in `from`
+/// into `to`, replacing worlds and packs of the same name there. The world of
+/// `from`'s level is copied as `level_name`. Packs among the `installed` files
+/// of `from`'s release are left for `to`'s own release to provide. Copies are
+/// handed to `owner`.
+pub fn copy_server_data(
+    from: &Path,
+    to: &Path,
+    installed: &FileHashes,
+    config_files: &[String],
+    level_name: &str,
+    owner: Option<&Owner>,
+) -> std::io::Result<Copied> {
+    let mut copied = Copied::default();
+    std::fs::create_dir_all(to)?;
+
+    for file in config_files {
+        if from.join(file).is_file() {
+            copy_file(&from.join(file), &to.join(file), owner)?;
+            copied.config_files.push(file.clone());
+        }
+    }
+
+    let source_level = world::level_name(from);
+    for world in subdirectories(&from.join(WORLDS_DIR))? {
+        let name = match world.to_str() {
+            Some(name) if name == source_level => PathBuf::from(level_name),
+            _ => world.clone(),
+        };
+        let target = Path::new(WORLDS_DIR).join(name);
+        replace_dir(
+            &from.join(WORLDS_DIR).join(&world),
+            &to.join(&target),
+            owner,
+        )?;
+        copied.worlds.push(target);
+    }
+
+    for pack_dir in PACK_DIRS {
+        for pack in subdirectories(&from.join(pack_dir))? {
+            let relative = Path::new(pack_dir).join(&pack);
+            let shipped = installed
+                .keys()
+                .any(|path| Path::new(path).starts_with(&relative));
+            if shipped {
+                continue;
+            }
+            replace_dir(&from.join(&relative), &to.join(&relative), owner)?;
+            copied.packs.push(relative);
+        }
+    }
+
+    Ok(copied)
+}
+
+/// The names of the directories in `dir`, sorted. Empty when it doesn't exist.
+pub(crate) fn subdirectories(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(PathBuf::from(entry.file_name()));
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Copies `from` to `to`, removing what was at `to` first so no stale files
+/// remain.
+fn replace_dir(from: &Path, to: &Path, owner: Option<&Owner>) -> std::io::Result<()> {
+    if to.is_dir() {
+        std::fs::remove_dir_all(to)?;
+    }
+    copy_dir(from, to, owner)
+}
+
+fn copy_dir(from: &Path, to: &Path, owner: Option<&Owner>) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    if let Some(owner) = owner {
+        permissions::set_owner(to, owner)?;
+    }
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target, owner)?;
+        } else {
+            copy_file(&entry.path(), &target, owner)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path, owner: Option<&Owner>) -> std::io::Result<()> {
+    std::fs::copy(from, to)?;
+    if let Some(owner) = owner {
+        permissions::set_owner(to, owner)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_server_data() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let from = temp_dir.path().join("stable");
+        let to = temp_dir.path().join("preview");
+        for dir in [
+            "worlds/Survival/db",
+            "worlds/Creative",
+            "behavior_packs/vanilla",
+            "behavior_packs/custom",
+        ] {
+            std::fs::create_dir_all(from.join(dir)).unwrap();
+        }
+        std::fs::write(from.join("worlds/Survival/level.dat"), "survival").unwrap();
+        std::fs::write(from.join("worlds/Survival/db/CURRENT"), "db").unwrap();
+        std::fs::write(from.join("behavior_packs/vanilla/manifest.json"), "{}").unwrap();
+        std::fs::write(from.join("behavior_packs/custom/manifest.json"), "{}").unwrap();
+        std::fs::write(from.join("server.properties"), "level-name=Survival\n").unwrap();
+        std::fs::create_dir_all(to.join("worlds/Survival Preview")).unwrap();
+        std::fs::write(to.join("worlds/Survival Preview/stale"), "").unwrap();
+        let installed = FileHashes::from([(
+            "behavior_packs/vanilla/manifest.json".to_owned(),
+            String::new(),
+        )]);
+        let config_files = vec!["server.properties".to_owned(), "allowlist.json".to_owned()];
+
+        let copied = copy_server_data(
+            &from,
+            &to,
+            &installed,
+            &config_files,
+            "Survival Preview",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            copied,
+            Copied {
+                worlds: vec![
+                    PathBuf::from("worlds/Creative"),
+                    PathBuf::from("worlds/Survival Preview"),
+                ],
+                packs: vec![PathBuf::from("behavior_packs/custom")],
+                config_files: vec!["server.properties".to_owned()],
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(to.join("worlds/Survival Preview/db/CURRENT")).unwrap(),
+            "db"
+        );
+        assert!(!to.join("worlds/Survival Preview/stale").exists());
+        assert!(!to.join("behavior_packs/vanilla").exists());
+        assert_eq!(world::level_name(&to), "Survival");
+    }
+}
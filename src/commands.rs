@@ -0,0 +1,2009 @@
+use crate::args::{
+    ApplyArgs, AttachArgs, BackupArgs, KeygenArgs, LinksArgs, MirrorArgs, MirrorServeArgs,
+    PackCheckArgs, PackInstallArgs, PackListArgs, PackRemoveArgs, PackUpgradeArgs, PreviewArgs,
+    RepairArgs, RestoreArgs, RunArgs, ServerArgs, SignArgs, StatusArgs, UpdateArgs, VerifyArgs,
+};
+use bedrock_up::config::{Config, ServerConfig};
+use bedrock_up::console::{self, Viewers};
+use bedrock_up::defer::{self, Decision, Deferral};
+use bedrock_up::fingerprint::KnownVersions;
+use bedrock_up::http::HttpClient;
+use bedrock_up::manifest::{self, Manifest};
+use bedrock_up::permissions::Owner;
+use bedrock_up::policy::{UpdatePolicy, Verdict};
+use bedrock_up::source::{DirectorySource, MIRROR_INDEX_PATH};
+use bedrock_up::status::{self, ServerReport};
+use bedrock_up::supervisor::{Backoff, ConsoleInput, Output, ServerProcess};
+use bedrock_up::updater::{self, FileAction, Snapshot};
+use bedrock_up::verify::{self, FileHashes};
+use bedrock_up::{
+    DownloadType, Error, LinksEndpoint, Release, ReleaseIndex, ReleaseSource, Result, SourceConfig,
+    UpdatePlan, Version, backup, clone, compat, health, lock, mirror, pack, permissions, platform,
+    properties, release, signing, stage, world,
+};
+use ed25519_dalek::SigningKey;
+use std::collections::BTreeSet;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub fn update(mut args: UpdateArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.exclude, &args.server_path, &config);
+    let client = HttpClient::new(&config.http)?;
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let owner = owner(args.owner.as_ref(), &config)?;
+    let download_type = download_type(&args)?;
+    let source = release_source(&args, &config, client)?;
+
+    // Fail before downloading anything when the server can't be checked.
+    let command = if args.health_check {
+        Some(server_command(&args.server_path, &download_type)?)
+    } else {
+        None
+    };
+
+    let policy = update_policy(&args, &config);
+    let approved = args.force || args.approve;
+    let Some(pending) = check_for_update(
+        &args,
+        &download_type,
+        source.as_ref(),
+        &policy,
+        args.force,
+        approved,
+    )?
+    else {
+        return Ok(());
+    };
+    if let Some(deferral) = deferral(&args, &config) {
+        let since = Instant::now();
+        while !ready_to_apply(&deferral, since) {
+            std::thread::sleep(defer::POLL_INTERVAL);
+        }
+    }
+    // Checked after the deferral, which only waits while the server runs.
+    if command.is_some()
+        && let Err(e) = check_port_free(&args.server_path, None)
+    {
+        std::fs::remove_file(&pending.zip_path)?;
+        return Err(e);
+    }
+    let installed = install(&args, pending, owner.as_ref())?;
+
+    let checked = check_started(command, args.health_timeout);
+    installed.finish(checked, Some(&args.cache_path))
+}
+
+/// Switches the server to another download type: installs the latest release
+/// of it over the server, keeping worlds, packs and config, then removes the
+/// files only the old release had.
+pub fn migrate(mut args: UpdateArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.exclude, &args.server_path, &config);
+    let client = HttpClient::new(&config.http)?;
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let owner = owner(args.owner.as_ref(), &config)?;
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let target = args.download_type.ok_or_else(|| {
+        Error::Other("pass the --download-type to migrate the server to".to_owned())
+    })?;
+    let current = platform::detect(&server_path)?.ok_or_else(|| {
+        Error::Other(format!(
+            "no server installed in {}, run `bedrock-up update` instead",
+            server_path.display()
+        ))
+    })?;
+    if current == target && !args.force {
+        println!("The server is already {}.", target);
+        return Ok(());
+    }
+    let manifest = installed_manifest(&server_path)?;
+    if DownloadType::from_links_name(&manifest.download_type) != Some(current) {
+        return Err(Error::Other(format!(
+            "{} in {} is not for the installed {} server, cannot tell its files apart",
+            manifest::MANIFEST_FILE,
+            server_path.display(),
+            current
+        )));
+    }
+    let source = release_source(&args, &config, client)?;
+    let installed_files = installed_files(source.as_ref(), &manifest)?;
+
+    let command = if args.health_check {
+        Some(server_command(&args.server_path, &target)?)
+    } else {
+        None
+    };
+
+    println!("Migrating from {} to {}", current, target);
+    println!("Fetching releases from {}...", source.describe());
+    let (index, release) = source.latest(&target)?;
+    check_worlds(&server_path, &release, args.force)?;
+    check_packs(&server_path, &release, args.force)?;
+    let zip_path = source.fetch(&release)?;
+    println!("Downloaded update to: {}", zip_path.display());
+    let leftovers = updater::plan_update(&args.server_path, &zip_path, &args.exclude)
+        .map(|plan| updater::leftovers(&plan, &installed_files, &args.exclude));
+    let leftovers = match leftovers {
+        Ok(leftovers) => leftovers,
+        Err(e) => {
+            std::fs::remove_file(&zip_path)?;
+            return Err(e);
+        }
+    };
+
+    if let Some(deferral) = deferral(&args, &config) {
+        let since = Instant::now();
+        while !ready_to_apply(&deferral, since) {
+            std::thread::sleep(defer::POLL_INTERVAL);
+        }
+    }
+    if command.is_some()
+        && let Err(e) = check_port_free(&args.server_path, None)
+    {
+        std::fs::remove_file(&zip_path)?;
+        return Err(e);
+    }
+    let pending = Pending {
+        index,
+        release,
+        zip_path,
+    };
+    let installed = install(&args, pending, owner.as_ref())?;
+
+    // The old files stay until the new server has started, so a failed
+    // health check can restore the snapshot.
+    let checked = check_started(command, args.health_timeout);
+    installed.finish(checked, Some(&args.cache_path))?;
+    for path in &leftovers {
+        println!(
+            "Removing {} file: {}",
+            current,
+            server_path.join(path).display()
+        );
+    }
+    updater::remove_leftovers(&server_path, &leftovers)?;
+    println!("Migrated the server to {}.", target);
+    Ok(())
+}
+
+/// Copies a stable server's worlds, packs and config into a preview server,
+/// gives it its own port and level name, and installs the latest preview
+/// release there unless it already has it.
+pub fn preview(mut args: PreviewArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.update.exclude, &args.update.server_path, &config);
+    let client = HttpClient::new(&config.http)?;
+    let stable = stable_server(&args.from, &config);
+    let stable_path = PathBuf::from(shellexpand::tilde(&stable.server_path).to_string());
+    let preview_path = PathBuf::from(shellexpand::tilde(&args.update.server_path).to_string());
+    if stable_path == preview_path {
+        return Err(Error::Other(
+            "the preview server needs its own --server-path".to_owned(),
+        ));
+    }
+    let _stable_lock = lock::acquire(&lock::server_lock_path(&stable.server_path), Duration::ZERO)?;
+    let _preview_lock = lock::acquire(
+        &lock::server_lock_path(&args.update.server_path),
+        Duration::ZERO,
+    )?;
+    let owner = owner(args.update.owner.as_ref(), &config)?;
+
+    let download_type = match args.update.download_type {
+        Some(download_type) => download_type,
+        None => platform::installed_platform(&stable_path)?
+            .and_then(|platform| platform.preview())
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "{} has no Bedrock server to make a preview server of",
+                    stable_path.display()
+                ))
+            })?,
+    };
+    platform::check(&preview_path, &download_type)?;
+    let installed_files = match manifest::read_manifest(&stable_path)? {
+        Some(manifest) => {
+            let stable_source = configured_source(Some(&stable), &config, client.clone())?;
+            installed_files(stable_source.as_ref(), &manifest)?
+        }
+        None => FileHashes::new(),
+    };
+    let level_name = args
+        .level_name
+        .clone()
+        .unwrap_or_else(|| format!("{} Preview", world::level_name(&stable_path)));
+    let port = args
+        .port
+        .unwrap_or_else(|| properties::server_port(&stable_path).saturating_add(2));
+
+    let source = release_source(&args.update, &config, client)?;
+    let command = if args.update.health_check {
+        check_port_free(&args.update.server_path, Some(port))?;
+        Some(server_command(&args.update.server_path, &download_type)?)
+    } else {
+        None
+    };
+    println!("Fetching releases from {}...", source.describe());
+    let (index, release) = source.latest(&download_type)?;
+    check_worlds(&stable_path, &release, args.update.force)?;
+    check_worlds(&preview_path, &release, args.update.force)?;
+    // The stable server's packs are the ones copied into the preview server.
+    check_packs(&stable_path, &release, args.update.force)?;
+
+    println!(
+        "Copying {} into {}...",
+        stable_path.display(),
+        preview_path.display()
+    );
+    let config_files = stable.exclude();
+    let copied = clone::copy_server_data(
+        &stable_path,
+        &preview_path,
+        &installed_files,
+        &config_files,
+        &level_name,
+        owner.as_ref(),
+    )?;
+    for path in copied.worlds.iter().chain(&copied.packs) {
+        println!("Copied {}", path.display());
+    }
+    for file in &copied.config_files {
+        println!("Copied {}", file);
+    }
+    // The release must not overwrite the stable server's config.
+    add_exclude(&mut args.update.exclude, config_files);
+
+    let current = manifest::read_manifest(&preview_path)?;
+    let installed = if !args.update.force
+        && current.is_some_and(|current| current.download_url == release.download_url)
+    {
+        println!(
+            "The preview server already runs {}.",
+            release.version().unwrap_or(&release.download_url)
+        );
+        None
+    } else {
+        let zip_path = source.fetch(&release)?;
+        println!("Downloaded update to: {}", zip_path.display());
+        let pending = Pending {
+            index,
+            release,
+            zip_path,
+        };
+        Some(install(&args.update, pending, owner.as_ref())?)
+    };
+
+    properties::set_server_properties(
+        &preview_path,
+        &[
+            ("server-port", port.to_string()),
+            ("server-portv6", port.saturating_add(1).to_string()),
+            ("level-name", level_name.clone()),
+        ],
+    )?;
+    println!(
+        "The preview server listens on port {} and loads {}.",
+        port, level_name
+    );
+
+    match installed {
+        // The links cache belongs to the stable server, whose next update
+        // would look up to date if the preview release were recorded in it.
+        Some(installed) => {
+            let checked = check_started(command, args.update.health_timeout);
+            installed.finish(checked, None)
+        }
+        None => Ok(()),
+    }
+}
+
+/// The stable server `from` names, by its name in the config file or its
+/// path. Servers not in the config file get the default config files.
+fn stable_server(from: &str, config: &Config) -> ServerConfig {
+    config
+        .servers
+        .iter()
+        .find(|server| server.name == Some(from.to_owned()))
+        .or_else(|| server_config(from, config))
+        .cloned()
+        .unwrap_or_else(|| ServerConfig {
+            server_path: from.to_owned(),
+            ..ServerConfig::default()
+        })
+}
+
+/// Downloads and extracts the next update into the server's staging area, so
+/// `apply` only has to move the files into place.
+pub fn stage(mut args: UpdateArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.exclude, &args.server_path, &config);
+    let client = HttpClient::new(&config.http)?;
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let download_type = download_type(&args)?;
+    let source = release_source(&args, &config, client)?;
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+
+    let policy = update_policy(&args, &config);
+    let approved = args.force || args.approve;
+    let Some((index, release)) = find_update(
+        &args,
+        &download_type,
+        source.as_ref(),
+        &policy,
+        args.force,
+        approved,
+    )?
+    else {
+        return Ok(());
+    };
+    if let Some(staged) = stage::read_staged(&server_path)?
+        && staged.release == release
+        && !args.force
+    {
+        println!("Already staged: {}", release.download_url);
+        return Ok(());
+    }
+
+    let zip_path = source.fetch(&release)?;
+    println!("Downloaded update to: {}", zip_path.display());
+    if !args.force && adopt_installed(&args, &index, &release, &zip_path)? {
+        std::fs::remove_file(&zip_path)?;
+        return Ok(());
+    }
+    println!(
+        "Extracting to {}...",
+        server_path.join(stage::STAGE_DIR).display()
+    );
+    record_known_version(&args, &release, &zip_path);
+    let staged = stage::stage(&server_path, &zip_path, &release, &index);
+    std::fs::remove_file(&zip_path)?;
+    staged?;
+
+    println!(
+        "Staged {}, run `bedrock-up apply -s {}` to apply it.",
+        release.download_url, args.server_path
+    );
+    Ok(())
+}
+
+/// Moves the release staged by `stage` into the server.
+pub fn apply(mut args: ApplyArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.exclude, &args.server_path, &config);
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let owner = owner(args.owner.as_ref(), &config)?;
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+
+    let Some(staged) = stage::read_staged(&server_path)? else {
+        return Err(Error::Other(format!(
+            "nothing is staged for {}, run `bedrock-up stage` first",
+            args.server_path
+        )));
+    };
+    let command = if args.health_check {
+        check_port_free(&args.server_path, None)?;
+        Some(server_command(
+            &args.server_path,
+            &staged.release.download_type,
+        )?)
+    } else {
+        None
+    };
+
+    // An update applied since staging may have installed a newer release.
+    if let Some(installed) = installed_version(&args.server_path)
+        && let Some(version) = staged.release.version()
+        && version
+            .parse::<Version>()
+            .is_ok_and(|version| version < installed)
+    {
+        if !args.force {
+            return Err(Error::Other(format!(
+                "the staged {} is older than the installed {}, stage the update again or pass --force to apply it anyway",
+                version, installed
+            )));
+        }
+        eprintln!(
+            "Installing anyway: the staged {} is older than the installed {}",
+            version, installed
+        );
+    }
+    check_worlds(&server_path, &staged.release, args.force)?;
+    check_packs(&server_path, &staged.release, args.force)?;
+
+    println!(
+        "Applying {} staged at {}",
+        staged.release.download_url,
+        staged
+            .staged_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+    println!("Excluded files: {:?}", args.exclude);
+    let applied = staged
+        .plan(&args.server_path, &args.exclude)
+        .and_then(|plan| apply_plan(&plan, args.health_check, &args.exclude, owner.as_ref()));
+    // Partly moved into the server or not, the staged files are used up. A
+    // failure to apply them matters more than one to clean them up.
+    let discarded = staged.discard();
+    let (snapshot, files) = applied?;
+    discarded?;
+    let installed = Installed {
+        index: staged.index,
+        release: staged.release,
+        server_path,
+        snapshot,
+        files,
+    };
+
+    let checked = check_started(command, args.health_timeout);
+    installed.finish(checked, Some(&args.cache_path))
+}
+
+/// Reports files of the server that differ from the installed release, and
+/// fails when any were modified or are missing.
+pub fn verify(mut args: VerifyArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.exclude, &args.server_path, &config);
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let client = HttpClient::new(&config.http)?;
+
+    let expected = match &args.zip {
+        Some(zip) => verify::zip_hashes(&PathBuf::from(shellexpand::tilde(zip).to_string()))?,
+        None => {
+            let source =
+                configured_source(server_config(&args.server_path, &config), &config, client)?;
+            installed_files(source.as_ref(), &installed_manifest(&server_path)?)?
+        }
+    };
+
+    let report = verify::verify(&server_path, &expected, &args.exclude)?;
+    print_verify_report(&report);
+    match report.damaged().count() {
+        0 => Ok(()),
+        damaged => Err(Error::Other(format!(
+            "{} files differ from the installed release, run `bedrock-up repair` to restore them",
+            damaged
+        ))),
+    }
+}
+
+/// Restores modified and missing files from the installed release's zip,
+/// leaving everything else alone.
+pub fn repair(mut args: RepairArgs, config: Config) -> Result<()> {
+    add_config_exclude(&mut args.verify.exclude, &args.verify.server_path, &config);
+    let server_path = PathBuf::from(shellexpand::tilde(&args.verify.server_path).to_string());
+    let _server_lock = lock::acquire(
+        &lock::server_lock_path(&args.verify.server_path),
+        Duration::ZERO,
+    )?;
+    let owner = owner(args.owner.as_ref(), &config)?;
+    let client = HttpClient::new(&config.http)?;
+
+    let manifest = manifest::read_manifest(&server_path)?;
+    let (zip_path, downloaded) = match (&args.verify.zip, &manifest) {
+        (Some(zip), _) => (PathBuf::from(shellexpand::tilde(zip).to_string()), false),
+        (None, Some(manifest)) => {
+            let source = configured_source(
+                server_config(&args.verify.server_path, &config),
+                &config,
+                client,
+            )?;
+            println!("Downloading {}...", manifest.download_url);
+            (fetch_installed(source.as_ref(), manifest)?, true)
+        }
+        (None, None) => return Err(no_manifest(&server_path)),
+    };
+    let expected = manifest
+        .map(|manifest| manifest.files)
+        .filter(|files| !files.is_empty());
+
+    let repaired = restore_damaged(&args, &zip_path, expected, owner.as_ref());
+    if downloaded {
+        std::fs::remove_file(&zip_path)?;
+    }
+
+    match repaired? {
+        0 => println!("Nothing to repair."),
+        repaired => println!("Restored {} files.", repaired),
+    }
+    Ok(())
+}
+
+/// Restores the files that don't match `expected`, or the zip itself when no
+/// hashes were recorded. Returns how many files were restored.
+fn restore_damaged(
+    args: &RepairArgs,
+    zip_path: &Path,
+    expected: Option<FileHashes>,
+    owner: Option<&Owner>,
+) -> Result<usize> {
+    let server_path = PathBuf::from(shellexpand::tilde(&args.verify.server_path).to_string());
+    let expected = match expected {
+        Some(expected) => expected,
+        None => verify::zip_hashes(zip_path)?,
+    };
+
+    let report = verify::verify(&server_path, &expected, &args.verify.exclude)?;
+    let damaged: BTreeSet<String> = report.damaged().cloned().collect();
+    for path in &damaged {
+        println!("Restoring {}", path);
+    }
+    if !damaged.is_empty() {
+        verify::repair(&args.verify.server_path, zip_path, &damaged, owner)?;
+    }
+    Ok(damaged.len())
+}
+
+/// The manifest of the server, which `verify` needs to find the installed
+/// release.
+fn installed_manifest(server_path: &Path) -> Result<Manifest> {
+    manifest::read_manifest(server_path)?.ok_or_else(|| no_manifest(server_path))
+}
+
+/// The files of the installed release: the hashes recorded in its manifest,
+/// or those of its zip, downloaded again.
+fn installed_files(source: &dyn ReleaseSource, manifest: &Manifest) -> Result<FileHashes> {
+    if !manifest.files.is_empty() {
+        return Ok(manifest.files.clone());
+    }
+    println!("No hashes recorded, comparing against the release zip...");
+    let zip_path = fetch_installed(source, manifest)?;
+    let hashes = verify::zip_hashes(&zip_path);
+    std::fs::remove_file(&zip_path)?;
+    hashes
+}
+
+fn no_manifest(server_path: &Path) -> Error {
+    Error::Other(format!(
+        "no {} in {}, update the server once or pass --zip with the installed release",
+        manifest::MANIFEST_FILE,
+        server_path.display()
+    ))
+}
+
+/// Fetches the release in the manifest through `source`, so the zip is
+/// checked against a mirror's signed hashes like an update's.
+fn fetch_installed(source: &dyn ReleaseSource, manifest: &Manifest) -> Result<PathBuf> {
+    let download_type =
+        DownloadType::from_links_name(&manifest.download_type).ok_or_else(|| {
+            Error::Other(format!(
+                "unknown download type {} in {}",
+                manifest.download_type,
+                manifest::MANIFEST_FILE
+            ))
+        })?;
+    let release = Release {
+        download_type,
+        download_url: manifest.download_url.clone(),
+    };
+    // A release installed from a local directory is read from where it was,
+    // whatever source is configured now.
+    if release.download_url.starts_with("file:") {
+        return DirectorySource::new(".").fetch(&release);
+    }
+    source.fetch(&release)
+}
+
+fn print_verify_report(report: &verify::Report) {
+    for path in &report.modified {
+        println!("modified: {}", path);
+    }
+    for path in &report.missing {
+        println!("missing:  {}", path);
+    }
+    for path in &report.extra {
+        println!("extra:    {}", path);
+    }
+    if report.is_clean() {
+        println!("All files match the installed release.");
+    }
+}
+
+/// Starts the server from `command`, when there is one, and waits for it to
+/// come up.
+fn check_started(command: Option<std::process::Command>, timeout_secs: u64) -> Result<()> {
+    match command {
+        Some(command) => {
+            println!("Checking that the server starts...");
+            health::check_started(command, Duration::from_secs(timeout_secs))
+                .map_err(Error::Unhealthy)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Runs the server in the foreground, forwarding its console, restarting it
+/// when it crashes and applying updates between restarts.
+pub fn run(args: RunArgs, config: Config) -> Result<()> {
+    let update_args = &args.update;
+    let client = HttpClient::new(&config.http)?;
+    // Held for the whole run, the supervisor is the only one updating the server.
+    let _server_lock = lock::acquire(
+        &lock::server_lock_path(&update_args.server_path),
+        Duration::ZERO,
+    )?;
+    let owner = owner(update_args.owner.as_ref(), &config)?;
+    let download_type = download_type(update_args)?;
+    let source = release_source(update_args, &config, client)?;
+    server_command(&update_args.server_path, &download_type)?;
+
+    let input = ConsoleInput::default();
+    forward_stdin(input.clone());
+    let viewers = Viewers::default();
+    let socket_path = console::socket_path(&update_args.server_path);
+    let _console = console::listen(&socket_path, input.clone(), viewers.clone())
+        .inspect_err(|e| eprintln!("Console socket unavailable: {}", e));
+    let show = |line: &str| {
+        println!("{}", line);
+        viewers.broadcast(line);
+    };
+
+    let update_interval = Duration::from_secs(args.update_interval);
+    let stop_timeout = Duration::from_secs(args.stop_timeout);
+    let mut backoff = Backoff::new(
+        Duration::from_secs(args.restart_delay),
+        Duration::from_secs(args.max_restart_delay),
+    );
+    let policy = update_policy(update_args, &config);
+    let mut force = update_args.force;
+    let mut approved = update_args.force || update_args.approve;
+    let mut next_check = Some(Instant::now());
+    let mut unverified: Option<Installed> = None;
+    let deferral = deferral(update_args, &config);
+    // A downloaded update waiting for the players to leave.
+    let mut deferred: Option<(Pending, Instant)> = None;
+
+    loop {
+        let pending = match deferred.take() {
+            // The server is down, so there is nobody left to wait for.
+            Some((pending, _)) => Some(pending),
+            None if next_check.is_some_and(|next_check| next_check <= Instant::now()) => {
+                next_check = (!update_interval.is_zero()).then(|| Instant::now() + update_interval);
+                let pending = check_for_update(
+                    update_args,
+                    &download_type,
+                    source.as_ref(),
+                    &policy,
+                    force,
+                    approved,
+                )
+                .inspect_err(|e| eprintln!("Update check failed: {}", e))
+                .unwrap_or(None);
+                force = false;
+                approved = false;
+                pending
+            }
+            None => None,
+        };
+        if let Some(pending) = pending {
+            match install(update_args, pending, owner.as_ref()) {
+                Ok(installed) => unverified = Some(installed),
+                Err(e) => eprintln!("Update failed: {}", e),
+            }
+        }
+
+        println!("Starting the server...");
+        let mut server = match ServerProcess::spawn(
+            server_command(&update_args.server_path, &download_type)?,
+            input.clone(),
+        ) {
+            Ok(server) => server,
+            Err(e) => {
+                let delay = backoff.next_delay();
+                eprintln!("Failed to start the server: {}, retrying in {:?}", e, delay);
+                std::thread::sleep(delay);
+                continue;
+            }
+        };
+
+        if let Some(installed) = unverified.take() {
+            let checked = if update_args.health_check {
+                health::wait_until_started(
+                    &mut server,
+                    Duration::from_secs(update_args.health_timeout),
+                    &show,
+                )
+                .map_err(Error::Unhealthy)
+            } else {
+                Ok(())
+            };
+            if checked.is_err() {
+                // Nothing may hold on to the files while they are restored.
+                server.kill()?;
+            }
+            match installed.finish(checked, Some(&update_args.cache_path)) {
+                Ok(()) => backoff.reset(),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            }
+        }
+
+        let status = loop {
+            let wait = match next_check {
+                Some(next_check) => next_check.saturating_duration_since(Instant::now()),
+                None => Duration::MAX,
+            };
+            match server.next_line(wait) {
+                Output::Line(line) => {
+                    show(&line);
+                    if line.contains(health::STARTED_LINE) {
+                        backoff.reset();
+                    }
+                }
+                Output::Timeout => {
+                    if deferred.is_none() {
+                        next_check = Some(Instant::now() + update_interval);
+                        match check_for_update(
+                            update_args,
+                            &download_type,
+                            source.as_ref(),
+                            &policy,
+                            false,
+                            false,
+                        ) {
+                            Ok(Some(pending)) => deferred = Some((pending, Instant::now())),
+                            Ok(None) => {}
+                            Err(e) => eprintln!("Update check failed: {}", e),
+                        }
+                    }
+                    let Some((_, since)) = &deferred else {
+                        continue;
+                    };
+                    if let Some(deferral) = &deferral
+                        && !ready_to_apply(deferral, *since)
+                    {
+                        next_check = Some(Instant::now() + defer::POLL_INTERVAL);
+                        continue;
+                    }
+
+                    println!("Stopping the server to update...");
+                    server.stop(stop_timeout)?;
+                    break None;
+                }
+                Output::Closed => break Some(server.wait()?),
+            }
+        };
+
+        match status {
+            // Stopped from the console on purpose.
+            Some(status) if status.success() => {
+                println!("Server stopped.");
+                return Ok(());
+            }
+            Some(status) => {
+                let delay = backoff.next_delay();
+                eprintln!("Server exited with {}, restarting in {:?}", status, delay);
+                std::thread::sleep(delay);
+            }
+            None => {}
+        }
+    }
+}
+
+/// Connects to the console of a server started by `run`. Sends a single command
+/// when one is given, otherwise streams the console until the server goes away.
+#[cfg(unix)]
+pub fn attach(args: AttachArgs) -> Result<()> {
+    use std::io::Write;
+
+    let path = console::socket_path(&args.server_path);
+    let mut stream = console::connect(&path).map_err(|e| {
+        Error::Other(format!(
+            "cannot attach to {}: {}, is `bedrock-up run` running?",
+            path.display(),
+            e
+        ))
+    })?;
+
+    if !args.command.is_empty() {
+        writeln!(stream, "{}", args.command.join(" "))?;
+        return Ok(());
+    }
+
+    let mut commands = stream.try_clone()?;
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if writeln!(commands, "{}", line).is_err() {
+                break;
+            }
+        }
+    });
+    for line in std::io::BufReader::new(stream).lines() {
+        println!("{}", line?);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn attach(_args: AttachArgs) -> Result<()> {
+    Err(Error::Other(
+        "attaching to a console is only supported on unix".to_owned(),
+    ))
+}
+
+/// Sends every line typed into bedrock-up's own console to the server.
+fn forward_stdin(input: ConsoleInput) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if let Err(e) = input.send(&line) {
+                eprintln!("Cannot send {}: {}", line, e);
+            }
+        }
+    });
+}
+
+/// How long updates wait for players to leave, when they wait at all.
+/// Command line arguments win over the server's config entry.
+fn deferral(args: &UpdateArgs, config: &Config) -> Option<Deferral> {
+    let server = server_config(&args.server_path, config);
+    let enabled =
+        args.defer_until_empty || server.and_then(|server| server.defer_until_empty) == Some(true);
+    if !enabled {
+        return None;
+    }
+
+    let max = args
+        .max_deferral
+        .or(server.and_then(|server| server.max_deferral_secs))
+        .unwrap_or(defer::DEFAULT_MAX_DEFERRAL_SECS);
+    Some(Deferral::new(&args.server_path, Duration::from_secs(max)))
+}
+
+/// Whether an update deferred since `since` can be applied now.
+fn ready_to_apply(deferral: &Deferral, since: Instant) -> bool {
+    match deferral.decide(since) {
+        Decision::Apply => true,
+        Decision::Wait {
+            players: Some(players),
+        } => {
+            println!("{} players online, deferring the update...", players);
+            false
+        }
+        Decision::Wait { players: None } => {
+            println!("The server isn't answering pings, deferring the update...");
+            false
+        }
+        Decision::Overdue {
+            players: Some(players),
+        } => {
+            println!(
+                "{} players still online after {:?}, updating anyway",
+                players, deferral.max
+            );
+            true
+        }
+        Decision::Overdue { players: None } => {
+            println!(
+                "The server still isn't answering pings after {:?}, updating anyway",
+                deferral.max
+            );
+            true
+        }
+    }
+}
+
+fn owner(arg: Option<&String>, config: &Config) -> Result<Option<Owner>> {
+    match arg.or(config.owner.as_ref()) {
+        Some(owner) => permissions::parse_owner(owner)
+            .map(Some)
+            .map_err(|e| Error::Other(format!("invalid owner {}: {}", owner, e))),
+        None => Ok(None),
+    }
+}
+
+fn server_command(
+    server_path: &str,
+    download_type: &DownloadType,
+) -> Result<std::process::Command> {
+    let server_path = PathBuf::from(shellexpand::tilde(server_path).to_string());
+    health::server_command(&server_path, download_type).map_err(|e| Error::Other(e.to_string()))
+}
+
+/// Refuses a health check while the server's port, or `port` when given, is
+/// bound: the checked server would collide with one that is still running
+/// and roll the update back.
+fn check_port_free(server_path: &str, port: Option<u16>) -> Result<()> {
+    let port = port.unwrap_or_else(|| {
+        properties::server_port(&PathBuf::from(shellexpand::tilde(server_path).to_string()))
+    });
+    health::check_port_free(port).map_err(|e| Error::Other(e.to_string()))
+}
+
+/// The download type given on the command line, checked against the
+/// installed server, or detected from it.
+fn download_type(args: &UpdateArgs) -> Result<DownloadType> {
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    match args.download_type {
+        Some(requested) => {
+            platform::check(&server_path, &requested).map_err(|e| {
+                Error::Other(format!("{}, use `bedrock-up migrate` to switch it", e))
+            })?;
+            Ok(requested)
+        }
+        None => {
+            let detected = platform::detect(&server_path)?.ok_or_else(|| {
+                Error::Other(format!(
+                    "cannot tell the download type of {}, pass --download-type",
+                    server_path.display()
+                ))
+            })?;
+            println!("Detected download type: {}", detected);
+            Ok(detected)
+        }
+    }
+}
+
+/// Refuses a release older than a world of the server was last opened with,
+/// which it could corrupt, unless forced.
+fn check_worlds(server_path: &Path, release: &Release, force: bool) -> Result<()> {
+    let Some(version) = release.version().and_then(|v| v.parse::<Version>().ok()) else {
+        return Ok(());
+    };
+    match world::check_downgrade(server_path, &version) {
+        Err(e) if force => {
+            eprintln!("Installing anyway: {}", e);
+            Ok(())
+        }
+        Err(e) => Err(Error::Other(format!(
+            "{}\nPass --force to install it anyway.",
+            e
+        ))),
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Reports the custom packs of the server that `release` is likely to break,
+/// and refuses it when one of them won't load with it, unless forced.
+fn check_packs(server_path: &Path, release: &Release, force: bool) -> Result<()> {
+    let Some(version) = release.version().and_then(|v| v.parse::<Version>().ok()) else {
+        return Ok(());
+    };
+    let (installed, shipped) = installed_release(server_path)?;
+    let packs = compat::custom_packs(server_path, &shipped)?;
+    let findings = compat::check(&packs, installed.as_ref(), &version);
+    print_findings(&findings);
+
+    let breaking = breaking_packs(&findings);
+    match breaking {
+        0 => Ok(()),
+        _ if force => {
+            eprintln!(
+                "Installing anyway: {} packs are likely to break with {}",
+                breaking, version
+            );
+            Ok(())
+        }
+        _ => Err(Error::Other(format!(
+            "{} packs are likely to break with {}\nPass --force to install it anyway.",
+            breaking, version
+        ))),
+    }
+}
+
+/// The version and files of the release recorded in the server's manifest,
+/// none when it has no manifest.
+fn installed_release(server_path: &Path) -> Result<(Option<Version>, FileHashes)> {
+    Ok(match manifest::read_manifest(server_path)? {
+        Some(manifest) => (
+            manifest.version.as_deref().and_then(|v| v.parse().ok()),
+            manifest.files,
+        ),
+        None => (None, FileHashes::new()),
+    })
+}
+
+fn print_findings(findings: &[compat::Finding]) {
+    for finding in findings {
+        println!("{}: {}", finding.severity, finding);
+    }
+}
+
+/// The number of packs with a breaking finding.
+fn breaking_packs(findings: &[compat::Finding]) -> usize {
+    findings
+        .iter()
+        .filter(|finding| finding.severity == compat::Severity::Breaking)
+        .map(|finding| &finding.dir)
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+/// The release source for the server: from the command line, the server's
+/// config entry, or the config-wide default.
+fn release_source(
+    args: &UpdateArgs,
+    config: &Config,
+    client: HttpClient,
+) -> Result<Box<dyn ReleaseSource>> {
+    let mut source = args
+        .source
+        .clone()
+        .or_else(|| {
+            server_config(&args.server_path, config).and_then(|server| server.source.clone())
+        })
+        .or_else(|| config.source.clone())
+        .unwrap_or_default();
+    if let Some(mirror_key) = &args.mirror_key {
+        let SourceConfig::Mirror { public_key, .. } = &mut source else {
+            return Err(Error::Other(
+                "--mirror-key needs a mirror source".to_owned(),
+            ));
+        };
+        *public_key = Some(mirror_key.clone());
+    }
+    source.build(client, links_endpoints(&args.links(), config))
+}
+
+/// The release source the `server`'s config entry or the config-wide default
+/// names, for commands that take no source on the command line.
+fn configured_source(
+    server: Option<&ServerConfig>,
+    config: &Config,
+    client: HttpClient,
+) -> Result<Box<dyn ReleaseSource>> {
+    server
+        .and_then(|server| server.source.clone())
+        .or_else(|| config.source.clone())
+        .unwrap_or_default()
+        .build(client, links_endpoints(&LinksArgs::default(), config))
+}
+
+/// The server's update policy, with --auto-apply taking precedence.
+fn update_policy(args: &UpdateArgs, config: &Config) -> UpdatePolicy {
+    let mut policy = server_config(&args.server_path, config)
+        .and_then(|server| server.policy)
+        .or(config.policy)
+        .unwrap_or_default();
+    if let Some(auto_apply) = args.auto_apply {
+        policy.auto_apply = auto_apply;
+    }
+    policy
+}
+
+/// The version recorded in the server's manifest.
+fn installed_version(server_path: &str) -> Option<Version> {
+    let server_path = PathBuf::from(shellexpand::tilde(server_path).to_string());
+    manifest::read_manifest(&server_path)
+        .ok()
+        .flatten()?
+        .version?
+        .parse()
+        .ok()
+}
+
+/// The version of a server without a manifest, from its binary's hash in the
+/// known-version index, which is first extended with the source's when it
+/// doesn't know the binary.
+fn fingerprint(
+    args: &UpdateArgs,
+    server_path: &Path,
+    source: &dyn ReleaseSource,
+) -> Option<String> {
+    let path = PathBuf::from(shellexpand::tilde(&args.known_versions).to_string());
+    let identified = (|| -> Result<Option<String>> {
+        let mut known = KnownVersions::read(&path)?;
+        if let Some(version) = known.identify(server_path)? {
+            return Ok(Some(version));
+        }
+        if known.merge(source.known_versions(&known)?) {
+            known.write(&path)?;
+        }
+        known.identify(server_path)
+    })();
+    match identified {
+        Ok(Some(version)) => {
+            println!("Identified the installed server as {}", version);
+            Some(version)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Cannot identify the installed server: {}", e);
+            None
+        }
+    }
+}
+
+/// Adds the server binary in the release's zip to the known-version index.
+fn record_known_version(args: &UpdateArgs, release: &Release, zip_path: &Path) {
+    let Some(version) = release.version() else {
+        return;
+    };
+    let path = PathBuf::from(shellexpand::tilde(&args.known_versions).to_string());
+    let recorded = KnownVersions::read(&path).and_then(|mut known| {
+        if known.record_zip(zip_path, version)? {
+            known.write(&path)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = recorded {
+        eprintln!("Failed to update {}: {}", path.display(), e);
+    }
+}
+
+/// A downloaded release newer than the one last applied.
+struct Pending {
+    index: ReleaseIndex,
+    release: Release,
+    zip_path: PathBuf,
+}
+
+/// Downloads the latest release unless it is the one already applied, or the
+/// update policy holds it back and it wasn't `approved`.
+fn check_for_update(
+    args: &UpdateArgs,
+    download_type: &DownloadType,
+    source: &dyn ReleaseSource,
+    policy: &UpdatePolicy,
+    force: bool,
+    approved: bool,
+) -> Result<Option<Pending>> {
+    let Some((index, release)) = find_update(args, download_type, source, policy, force, approved)?
+    else {
+        return Ok(None);
+    };
+    let zip_path = source.fetch(&release)?;
+    println!("Downloaded update to: {}", zip_path.display());
+    if !force && adopt_installed(args, &index, &release, &zip_path)? {
+        std::fs::remove_file(&zip_path)?;
+        return Ok(None);
+    }
+    Ok(Some(Pending {
+        index,
+        release,
+        zip_path,
+    }))
+}
+
+/// Records `release` as installed, without reinstalling it, when the server
+/// has no manifest but already runs the release's server binary.
+fn adopt_installed(
+    args: &UpdateArgs,
+    index: &ReleaseIndex,
+    release: &Release,
+    zip_path: &Path,
+) -> Result<bool> {
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    if manifest::read_manifest(&server_path)?.is_some() {
+        return Ok(false);
+    }
+    let mut files = verify::zip_hashes(zip_path)?;
+    if !verify::same_binary(&server_path, &files)? {
+        return Ok(false);
+    }
+    files.retain(|path, _| !args.exclude.contains(path));
+    record_adopted(args, &server_path, index, release, files)?;
+    Ok(true)
+}
+
+/// Writes the manifest of a server found to already run `release`, so it isn't
+/// reinstalled. `files` may be empty when the zip wasn't downloaded.
+fn record_adopted(
+    args: &UpdateArgs,
+    server_path: &Path,
+    index: &ReleaseIndex,
+    release: &Release,
+    files: FileHashes,
+) -> Result<()> {
+    println!(
+        "The server already runs {}, recording it without reinstalling.",
+        release.version().unwrap_or(&release.download_url)
+    );
+    if let Err(e) = updater::write_cache(index, &args.cache_path) {
+        eprintln!("Failed to update cache: {}", e);
+    }
+    let manifest = Manifest::new(release, chrono::Utc::now(), files);
+    manifest::write_manifest(server_path, &manifest)?;
+    Ok(())
+}
+
+/// The latest release, unless it is the one already applied or the update
+/// policy holds it back and it wasn't `approved`.
+fn find_update(
+    args: &UpdateArgs,
+    download_type: &DownloadType,
+    source: &dyn ReleaseSource,
+    policy: &UpdatePolicy,
+    force: bool,
+    approved: bool,
+) -> Result<Option<(ReleaseIndex, Release)>> {
+    println!("Reading cache from: {}", args.cache_path);
+    let cache_index = updater::read_cache(&args.cache_path);
+
+    println!("Fetching releases from {}...", source.describe());
+    let latest = if force {
+        source.latest(download_type).map(Some)?
+    } else {
+        source.latest_if_changed(download_type, &cache_index)?
+    };
+    let Some((index, web_release)) = latest else {
+        println!(
+            "Links at {} unchanged since the last update, you are already on the latest version.",
+            cache_index.source
+        );
+        return Ok(None);
+    };
+    println!("Fetched links from {}", index.source);
+
+    let cache_download_url = cache_index
+        .resolve(download_type)
+        .map(|release| release.download_url)
+        .unwrap_or_else(|_| "0.0.0".to_owned());
+
+    println!("Current version in cache: {}", cache_download_url);
+    println!("Version available on the web: {}", web_release.download_url);
+
+    if !force && web_release.download_url == cache_download_url {
+        println!(
+            "You are already on the latest version: {}",
+            cache_download_url
+        );
+        return Ok(None);
+    }
+
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let fingerprinted = match manifest::read_manifest(&server_path)? {
+        Some(_) => None,
+        None => fingerprint(args, &server_path, source),
+    };
+    if !force && fingerprinted.is_some() && fingerprinted.as_deref() == web_release.version() {
+        record_adopted(args, &server_path, &index, &web_release, FileHashes::new())?;
+        return Ok(None);
+    }
+
+    println!("New version available: {}", web_release.download_url);
+    check_worlds(&server_path, &web_release, force)?;
+    check_packs(&server_path, &web_release, force)?;
+    if !approved {
+        let installed = installed_version(&args.server_path)
+            .or_else(|| fingerprinted.and_then(|v| v.parse().ok()))
+            .or_else(|| {
+                release::version_from_url(&cache_download_url).and_then(|v| v.parse().ok())
+            });
+        let available = web_release.version().and_then(|v| v.parse().ok());
+        if let Verdict::Hold(reason) =
+            policy.evaluate(download_type, installed.as_ref(), available.as_ref())
+        {
+            println!(
+                "Update held for approval: {}. Run again with --approve to apply it.",
+                reason
+            );
+            return Ok(None);
+        }
+    }
+    Ok(Some((index, web_release)))
+}
+
+/// An applied update that is kept or rolled back by [`Installed::finish`].
+struct Installed {
+    index: ReleaseIndex,
+    release: Release,
+    server_path: PathBuf,
+    snapshot: Option<Snapshot>,
+    files: FileHashes,
+}
+
+/// Applies the downloaded update.
+fn install(args: &UpdateArgs, pending: Pending, owner: Option<&Owner>) -> Result<Installed> {
+    println!("Applying update from: {}", pending.zip_path.display());
+    println!("Excluded files: {:?}", args.exclude);
+    record_known_version(args, &pending.release, &pending.zip_path);
+    let applied = updater::plan_update(&args.server_path, &pending.zip_path, &args.exclude)
+        .and_then(|plan| apply_plan(&plan, args.health_check, &args.exclude, owner));
+    std::fs::remove_file(&pending.zip_path)?;
+    let (snapshot, files) = applied?;
+
+    Ok(Installed {
+        index: pending.index,
+        release: pending.release,
+        server_path: PathBuf::from(shellexpand::tilde(&args.server_path).to_string()),
+        snapshot,
+        files,
+    })
+}
+
+/// Applies `plan`, taking a snapshot first when the server is health checked
+/// afterwards. Returns the snapshot and the hashes of the files written.
+fn apply_plan(
+    plan: &UpdatePlan,
+    health_check: bool,
+    exclude: &[String],
+    owner: Option<&Owner>,
+) -> Result<(Option<Snapshot>, FileHashes)> {
+    for file in plan.files_with(FileAction::Skip) {
+        println!(
+            "Skipping excluded file: {}",
+            plan.server_path.join(&file.path).display()
+        );
+    }
+
+    let snapshot = match health_check {
+        true => Some(updater::snapshot(plan)?),
+        false => None,
+    };
+    match updater::apply_update(plan, owner) {
+        Ok(()) => {
+            // Only `verify` needs the hashes, they don't fail the update.
+            let files = verify::plan_hashes(plan, exclude).unwrap_or_else(|e| {
+                eprintln!("Failed to hash the updated files: {}", e);
+                FileHashes::new()
+            });
+            Ok((snapshot, files))
+        }
+        Err(e) => {
+            if let Some(snapshot) = snapshot {
+                snapshot.restore()?;
+                snapshot.discard()?;
+            }
+            Err(e)
+        }
+    }
+}
+
+impl Installed {
+    /// Keeps the update when the health check passed and records it in the
+    /// cache, when given, and the server's manifest, or restores the snapshot
+    /// when it failed.
+    fn finish(self, checked: Result<()>, cache_path: Option<&str>) -> Result<()> {
+        if let Some(snapshot) = self.snapshot {
+            if checked.is_err() {
+                println!("Restoring the files from before the update...");
+                snapshot.restore()?;
+            } else {
+                println!("Server started.");
+            }
+            snapshot.discard()?;
+        }
+        checked?;
+
+        if let Some(cache_path) = cache_path
+            && let Err(e) = updater::write_cache(&self.index, cache_path)
+        {
+            eprintln!("Failed to update cache: {}", e);
+        }
+        let manifest = Manifest::new(&self.release, chrono::Utc::now(), self.files);
+        if let Err(e) = manifest::write_manifest(&self.server_path, &manifest) {
+            eprintln!("Failed to write manifest: {}", e);
+        }
+        println!("Update applied successfully.");
+        Ok(())
+    }
+}
+
+/// The fallback chain of endpoints. Command line and environment values win
+/// over the config file.
+fn links_endpoints(args: &LinksArgs, config: &Config) -> Vec<LinksEndpoint> {
+    let links_urls = if args.links_url.is_empty() {
+        &config.links_urls
+    } else {
+        &args.links_url
+    };
+    let download_page_url = args
+        .download_page_url
+        .as_ref()
+        .or(config.download_page_url.as_ref());
+
+    release::links_endpoints(
+        links_urls,
+        download_page_url.map(|url| url.as_str()),
+        args.no_fallback || config.no_fallback,
+    )
+}
+
+pub fn backup(args: BackupArgs, config: Config) -> Result<()> {
+    let servers = select_servers(&args.server, &config);
+    if servers.is_empty() {
+        return Err(Error::Other(
+            "no servers to back up, pass --server-path or add servers to the config file"
+                .to_owned(),
+        ));
+    }
+
+    let backup_dir = backup_dir(args.backup_dir.as_ref(), &config);
+    let retention = args.retention.merged_with(&config.backup.retention);
+
+    let mut failed = 0;
+    for server in &servers {
+        let name = server.name();
+        let server_backup_dir = backup_dir.join(&name);
+
+        let _server_lock =
+            match lock::acquire(&lock::server_lock_path(&server.server_path), Duration::ZERO) {
+                Ok(server_lock) => server_lock,
+                Err(e) => {
+                    eprintln!("Cannot back up {}: {}", name, e);
+                    failed += 1;
+                    continue;
+                }
+            };
+
+        match backup::create_backup(
+            server,
+            &server_backup_dir,
+            chrono::Local::now().naive_local(),
+        ) {
+            Ok(archive) => println!("Backed up {} to {}", name, archive.display()),
+            Err(e) => {
+                eprintln!("Failed to back up {}: {}", name, e);
+                failed += 1;
+                continue;
+            }
+        }
+
+        if retention.is_set() {
+            match backup::prune_backups(&server_backup_dir, &name, &retention) {
+                Ok(removed) => {
+                    for archive in removed {
+                        println!("Removed old backup {}", archive.display());
+                    }
+                }
+                Err(e) => eprintln!("Failed to prune backups of {}: {}", name, e),
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Other(format!(
+            "{} of {} backups failed",
+            failed,
+            servers.len()
+        )));
+    }
+    Ok(())
+}
+
+pub fn restore(args: RestoreArgs, config: Config) -> Result<()> {
+    let servers = select_servers(&args.server, &config);
+    let server = match servers.as_slice() {
+        [server] => server,
+        [] => {
+            return Err(Error::Other(
+                "no server to restore, pass --server-path or add a server to the config file"
+                    .to_owned(),
+            ));
+        }
+        _ => {
+            return Err(Error::Other(
+                "several servers are configured, pass --server-path to pick one".to_owned(),
+            ));
+        }
+    };
+    let name = server.name();
+
+    let archive = match &args.archive {
+        Some(archive) => PathBuf::from(shellexpand::tilde(archive).to_string()),
+        None => {
+            let server_backup_dir = backup_dir(args.backup_dir.as_ref(), &config).join(&name);
+            let backups = backup::list_backups(&server_backup_dir, &name)?;
+            match backups.into_iter().next() {
+                Some((_, archive)) => archive,
+                None => {
+                    return Err(Error::Other(format!(
+                        "no backups of {} in {}",
+                        name,
+                        server_backup_dir.display()
+                    )));
+                }
+            }
+        }
+    };
+
+    let _server_lock = lock::acquire(&lock::server_lock_path(&server.server_path), Duration::ZERO)?;
+
+    println!("Restoring {} from {}", name, archive.display());
+    backup::restore_backup(&archive, &server.server_path)?;
+    println!("Restore applied successfully.");
+    Ok(())
+}
+
+/// Prints what is installed and running for each server, flagging servers
+/// that still run the version from before their last update.
+pub fn status(args: StatusArgs, config: Config) -> Result<()> {
+    let servers = select_servers(&args.server, &config);
+    if servers.is_empty() {
+        return Err(Error::Other(
+            "no servers to report on, pass --server-path or add servers to the config file"
+                .to_owned(),
+        ));
+    }
+
+    let cached = args.download_type.and_then(|download_type| {
+        updater::read_cache(&args.cache_path)
+            .resolve(&download_type)
+            .ok()
+    });
+    let known = KnownVersions::read(Path::new(
+        &shellexpand::tilde(&args.known_versions).to_string(),
+    ))?;
+
+    let mut failed = 0;
+    for (i, server) in servers.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let server_path = PathBuf::from(shellexpand::tilde(&server.server_path).to_string());
+        let report = match ServerReport::collect(&server_path, &known, cached.as_ref()) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", server.name(), e);
+                failed += 1;
+                continue;
+            }
+        };
+        print_report(&server.name(), &report);
+    }
+
+    if failed > 0 {
+        return Err(Error::Other(format!(
+            "{} of {} servers could not be read",
+            failed,
+            servers.len()
+        )));
+    }
+    Ok(())
+}
+
+fn print_report(name: &str, report: &ServerReport) {
+    println!("{} ({})", name, report.server_path.display());
+    println!(
+        "  Installed:   {}",
+        report.installed_version.as_deref().unwrap_or("unknown")
+    );
+    match report.updated_at {
+        Some(updated_at) => println!(
+            "  Last update: {}",
+            updated_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => println!("  Last update: unknown"),
+    }
+    match &report.running {
+        Some(running) => {
+            println!(
+                "  Running:     {} (protocol {}), {}/{} players",
+                running.version, running.protocol, running.players, running.max_players
+            );
+            println!("  MOTD:        {}", running.motd);
+            if let Some(port) = running.port {
+                println!("  Port:        {}", port);
+            }
+        }
+        None => println!("  Running:     no"),
+    }
+    println!("  Disk usage:  {}", status::human_size(report.disk_usage));
+
+    if report.restart_pending() {
+        println!(
+            "  ! Still running {}, restart the server to load {}",
+            report
+                .running
+                .as_ref()
+                .map_or("", |running| &running.version),
+            report.installed_version.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Downloads the latest releases into the mirror directory.
+pub fn mirror_sync(args: MirrorArgs, config: Config) -> Result<()> {
+    let client = HttpClient::new(&config.http)?;
+    let dir = mirror_dir(&args);
+    let endpoints = links_endpoints(&args.links, &config);
+    let key = mirror_secret_key(&args)?;
+    sync_mirror(&client, &endpoints, &dir, key.as_ref())
+}
+
+/// Serves the mirror directory, syncing it every `sync_interval` seconds
+/// unless it is frozen. A failed sync keeps serving what was mirrored before.
+pub fn mirror_serve(args: MirrorServeArgs, config: Config) -> Result<()> {
+    let client = HttpClient::new(&config.http)?;
+    let dir = mirror_dir(&args.mirror);
+    let endpoints = links_endpoints(&args.mirror.links, &config);
+    let key = mirror_secret_key(&args.mirror)?;
+    std::fs::create_dir_all(&dir)?;
+
+    let server = mirror::MirrorServer::bind(&args.listen, &dir)?;
+    println!("Serving {} on http://{}", dir.display(), server.addr());
+    if args.frozen {
+        if !dir.join(MIRROR_INDEX_PATH).exists() {
+            return Err(Error::Other(format!(
+                "nothing to serve, {} has no {}; run `mirror sync` first",
+                dir.display(),
+                MIRROR_INDEX_PATH
+            )));
+        }
+        println!("Frozen, not syncing.");
+        server.serve();
+        return Ok(());
+    }
+
+    std::thread::spawn(move || server.serve());
+    loop {
+        if let Err(e) = sync_mirror(&client, &endpoints, &dir, key.as_ref()) {
+            eprintln!("Mirror sync failed: {}", e);
+        }
+        std::thread::sleep(Duration::from_secs(args.sync_interval));
+    }
+}
+
+/// Writes a new key pair for signing the mirror's release hashes.
+pub fn mirror_keygen(args: KeygenArgs) -> Result<()> {
+    let secret_path = PathBuf::from(shellexpand::tilde(&args.secret_key).to_string());
+    let key = signing::generate_key();
+    signing::write_secret_key(&secret_path, &key).map_err(|e| {
+        Error::Other(format!(
+            "cannot write the secret key to {}: {}",
+            secret_path.display(),
+            e
+        ))
+    })?;
+    let public_key = signing::encode_public_key(&key.verifying_key());
+    let public_path = secret_path.with_extension("pub");
+    std::fs::write(&public_path, format!("{}\n", public_key))?;
+
+    println!("Secret key written to {}", secret_path.display());
+    println!("Public key written to {}", public_path.display());
+    println!("Public key: {}", public_key);
+    Ok(())
+}
+
+/// Signs the hashes of every zip in the mirror directory.
+pub fn mirror_sign(args: SignArgs) -> Result<()> {
+    let dir = PathBuf::from(shellexpand::tilde(&args.dir).to_string());
+    let key = read_secret_key(&args.secret_key)?;
+    sign_mirror(&dir, &key)
+}
+
+fn mirror_dir(args: &MirrorArgs) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(&args.dir).to_string())
+}
+
+fn mirror_secret_key(args: &MirrorArgs) -> Result<Option<SigningKey>> {
+    args.secret_key
+        .as_ref()
+        .map(|path| read_secret_key(path))
+        .transpose()
+}
+
+fn read_secret_key(path: &str) -> Result<SigningKey> {
+    let path = PathBuf::from(shellexpand::tilde(path).to_string());
+    signing::read_secret_key(&path).map_err(|e| {
+        Error::Other(format!(
+            "cannot read the secret key {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+fn sign_mirror(dir: &Path, key: &SigningKey) -> Result<()> {
+    let hashes = signing::sign_dir(dir, key)?;
+    println!(
+        "Signed the hashes of {} releases in {}.",
+        hashes.files.len(),
+        signing::RELEASES_FILE
+    );
+    Ok(())
+}
+
+fn sync_mirror(
+    client: &HttpClient,
+    endpoints: &[LinksEndpoint],
+    dir: &Path,
+    key: Option<&SigningKey>,
+) -> Result<()> {
+    println!("Syncing {}...", dir.display());
+    let synced = mirror::sync(client, endpoints, dir)?;
+    println!("Fetched links from {}", synced.upstream);
+    for zip in &synced.downloaded {
+        println!("Downloaded {}", zip);
+    }
+    if synced.changed {
+        println!("Updated {}.", MIRROR_INDEX_PATH);
+    } else {
+        println!("Mirror is up to date.");
+    }
+    match key {
+        Some(key) => sign_mirror(dir, key),
+        None => Ok(()),
+    }
+}
+
+fn backup_dir(arg: Option<&String>, config: &Config) -> PathBuf {
+    let backup_dir = arg
+        .or(config.backup.directory.as_ref())
+        .map(|dir| dir.as_str())
+        .unwrap_or(backup::DEFAULT_BACKUP_DIR);
+    PathBuf::from(shellexpand::tilde(backup_dir).to_string())
+}
+
+/// Installs the packs in a `.mcpack` or `.mcaddon` and enables them in the
+/// world.
+pub fn pack_install(args: PackInstallArgs, config: Config) -> Result<()> {
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let owner = owner(args.owner.as_ref(), &config)?;
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let world = args
+        .world
+        .clone()
+        .unwrap_or_else(|| world::level_name(&server_path));
+
+    for archived in read_pack_archive(&args.file)? {
+        let installation = pack::install(
+            &server_path,
+            &archived,
+            Some(&world),
+            args.force,
+            owner.as_ref(),
+        )?;
+        print_installation(&installation);
+    }
+    Ok(())
+}
+
+/// Replaces installed packs with the versions in a `.mcpack` or `.mcaddon`,
+/// keeping the worlds they are enabled in.
+pub fn pack_upgrade(args: PackUpgradeArgs, config: Config) -> Result<()> {
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let owner = owner(args.owner.as_ref(), &config)?;
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+
+    for archived in read_pack_archive(&args.file)? {
+        let installation = pack::install(&server_path, &archived, None, args.force, owner.as_ref())
+            .map_err(|e| Error::Other(format!("{}, use `bedrock-up pack install`", e)))?;
+        print_installation(&installation);
+    }
+    Ok(())
+}
+
+pub fn pack_list(args: PackListArgs) -> Result<()> {
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let packs = pack::read_packs(&server_path)?.packs;
+    if packs.is_empty() {
+        println!("No packs installed with `bedrock-up pack install`.");
+    }
+    for installed in packs {
+        println!(
+            "{} {} ({} pack, {})",
+            installed.name, installed.version, installed.kind, installed.uuid
+        );
+        println!("  {}", installed.dir);
+        if !installed.worlds.is_empty() {
+            println!("  enabled in: {}", installed.worlds.join(", "));
+        }
+    }
+    Ok(())
+}
+
+pub fn pack_remove(args: PackRemoveArgs) -> Result<()> {
+    let _server_lock = lock::acquire(&lock::server_lock_path(&args.server_path), Duration::ZERO)?;
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let removed = pack::remove(&server_path, &args.pack)?;
+    println!(
+        "Removed {} {} from {}",
+        removed.name, removed.version, removed.dir
+    );
+    Ok(())
+}
+
+/// Reports the custom packs of the server and its worlds that a version is
+/// likely to break, and fails when any won't load with it.
+pub fn pack_check(args: PackCheckArgs) -> Result<()> {
+    let server_path = PathBuf::from(shellexpand::tilde(&args.server_path).to_string());
+    let (installed, shipped) = installed_release(&server_path)?;
+    let version = args.against.or_else(|| installed.clone()).ok_or_else(|| {
+        Error::Other(format!(
+            "no installed version recorded in {}, pass the version to check with --against",
+            server_path.display()
+        ))
+    })?;
+    let packs = compat::custom_packs(&server_path, &shipped)?;
+    let findings = compat::check(&packs, installed.as_ref(), &version);
+    print_findings(&findings);
+    if findings.is_empty() {
+        println!(
+            "No problems found in {} custom packs for {}.",
+            packs.len(),
+            version
+        );
+    }
+    match breaking_packs(&findings) {
+        0 => Ok(()),
+        breaking => Err(Error::Other(format!(
+            "{} packs are likely to break with {}",
+            breaking, version
+        ))),
+    }
+}
+
+fn read_pack_archive(file: &str) -> Result<Vec<pack::ArchivedPack>> {
+    let path = PathBuf::from(shellexpand::tilde(file).to_string());
+    pack::read_archive(&path)
+}
+
+fn print_installation(installation: &pack::Installation) {
+    let installed = &installation.pack;
+    match &installation.replaced {
+        Some(replaced) => println!(
+            "Upgraded {} from {} to {} in {}",
+            installed.name, replaced, installed.version, installed.dir
+        ),
+        None => println!(
+            "Installed {} {} ({} pack) into {}",
+            installed.name, installed.version, installed.kind, installed.dir
+        ),
+    }
+    if !installed.worlds.is_empty() {
+        println!("  enabled in: {}", installed.worlds.join(", "));
+    }
+}
+
+/// The config file entry for the server at `server_path`, if there is one.
+/// Adds the `exclude` the config file sets for the server to the command
+/// line's, so neither list's files are overwritten.
+fn add_config_exclude(exclude: &mut Vec<String>, server_path: &str, config: &Config) {
+    if let Some(files) =
+        server_config(server_path, config).and_then(|server| server.exclude.clone())
+    {
+        add_exclude(exclude, files);
+    }
+}
+
+fn add_exclude(exclude: &mut Vec<String>, files: Vec<String>) {
+    for file in files {
+        if !exclude.contains(&file) {
+            exclude.push(file);
+        }
+    }
+}
+
+fn server_config<'a>(server_path: &str, config: &'a Config) -> Option<&'a ServerConfig> {
+    let server_path = shellexpand::tilde(server_path);
+    config
+        .servers
+        .iter()
+        .find(|server| shellexpand::tilde(&server.server_path) == server_path)
+}
+
+/// The servers a command acts on: the one given on the command line, or every
+/// server in the config file.
+fn select_servers(args: &ServerArgs, config: &Config) -> Vec<ServerConfig> {
+    match &args.server_path {
+        Some(server_path) => vec![ServerConfig {
+            name: args.name.clone(),
+            server_path: server_path.clone(),
+            exclude: if args.exclude.is_empty() {
+                None
+            } else {
+                Some(args.exclude.clone())
+            },
+            ..ServerConfig::default()
+        }],
+        None => config.servers.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bedrock_up::config::DEFAULT_EXCLUDE;
+
+    fn test_update_args(extra: &[&str]) -> UpdateArgs {
+        use clap::Parser;
+
+        let mut args = vec!["bedrock-up", "-d", "linux", "-s", "/tmp/server"];
+        args.extend_from_slice(extra);
+        crate::args::Cli::try_parse_from(args)
+            .unwrap()
+            .update
+            .unwrap()
+    }
+
+    #[test]
+    fn test_links_endpoints_args_override_config() {
+        let args = test_update_args(&[
+            "--links-url",
+            "https://a.example.com/links,https://b.example.com/links",
+        ]);
+        let config = Config {
+            links_urls: vec!["https://config.example.com/links".to_owned()],
+            download_page_url: Some("https://config.example.com/download".to_owned()),
+            ..Config::default()
+        };
+
+        let result = links_endpoints(&args.links(), &config);
+
+        assert_eq!(
+            result,
+            vec![
+                LinksEndpoint::Api("https://a.example.com/links".to_owned()),
+                LinksEndpoint::Api("https://b.example.com/links".to_owned()),
+                LinksEndpoint::Api(release::DEFAULT_LINKS_URL.to_owned()),
+                LinksEndpoint::DownloadPage("https://config.example.com/download".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_endpoints_no_fallback_from_args() {
+        let args = test_update_args(&["--no-fallback"]);
+        let config = Config {
+            links_urls: vec!["https://config.example.com/links".to_owned()],
+            ..Config::default()
+        };
+
+        let result = links_endpoints(&args.links(), &config);
+
+        assert_eq!(
+            result,
+            vec![LinksEndpoint::Api(
+                "https://config.example.com/links".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_select_servers() {
+        let config = Config {
+            servers: vec![ServerConfig {
+                server_path: "/srv/survival".to_owned(),
+                ..ServerConfig::default()
+            }],
+            ..Config::default()
+        };
+        let from_config = ServerArgs {
+            server_path: None,
+            name: None,
+            exclude: vec![],
+        };
+        let from_args = ServerArgs {
+            server_path: Some("/srv/creative".to_owned()),
+            name: Some("creative-1".to_owned()),
+            exclude: vec![],
+        };
+
+        assert_eq!(select_servers(&from_config, &config), config.servers);
+
+        let result = select_servers(&from_args, &config);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name(), "creative-1");
+        assert_eq!(result[0].exclude(), DEFAULT_EXCLUDE);
+    }
+
+    #[test]
+    fn test_add_config_exclude() {
+        let config = Config {
+            servers: vec![
+                ServerConfig {
+                    server_path: "/srv/survival".to_owned(),
+                    exclude: Some(vec![
+                        "server.properties".to_owned(),
+                        "world_settings.json".to_owned(),
+                    ]),
+                    ..ServerConfig::default()
+                },
+                ServerConfig {
+                    server_path: "/srv/creative".to_owned(),
+                    ..ServerConfig::default()
+                },
+            ],
+            ..Config::default()
+        };
+
+        let mut exclude = vec!["server.properties".to_owned(), "ops.json".to_owned()];
+        add_config_exclude(&mut exclude, "/srv/survival", &config);
+        assert_eq!(
+            exclude,
+            ["server.properties", "ops.json", "world_settings.json"]
+        );
+
+        let mut exclude = vec!["ops.json".to_owned()];
+        add_config_exclude(&mut exclude, "/srv/creative", &config);
+        add_config_exclude(&mut exclude, "/srv/other", &config);
+        assert_eq!(exclude, ["ops.json"]);
+    }
+}
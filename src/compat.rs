@@ -0,0 +1,303 @@
+use crate::clone::{PACK_DIRS, subdirectories};
+use crate::error::Result;
+use crate::pack::{self, Dependency, PACK_MANIFEST, PackManifest};
+use crate::verify::FileHashes;
+use crate::version::{Change, Version};
+use crate::world::WORLDS_DIR;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The directories a world keeps its own packs in.
+const WORLD_PACK_DIRS: &[&str] = &["behavior_packs", "resource_packs"];
+
+/// A custom pack of the server or one of its worlds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomPack {
+    /// The pack's directory, relative to the server.
+    pub dir: PathBuf,
+    /// The pack's manifest, or why it couldn't be read.
+    pub manifest: std::result::Result<PackManifest, String>,
+}
+
+/// Reads the manifests of the packs in the server's pack directories that
+/// aren't among the `shipped` files of the installed release, and of the packs
+/// in every world.
+pub fn custom_packs(server_path: &Path, shipped: &FileHashes) -> Result<Vec<CustomPack>> {
+    let mut dirs = Vec::new();
+    for pack_dir in PACK_DIRS {
+        for pack in subdirectories(&server_path.join(pack_dir))? {
+            let dir = Path::new(pack_dir).join(pack);
+            let manifest = format!("{}/{}", dir.to_string_lossy(), PACK_MANIFEST);
+            if !shipped.contains_key(&manifest) {
+                dirs.push(dir);
+            }
+        }
+    }
+    for world in subdirectories(&server_path.join(WORLDS_DIR))? {
+        for pack_dir in WORLD_PACK_DIRS {
+            let world_packs = Path::new(WORLDS_DIR).join(&world).join(pack_dir);
+            for pack in subdirectories(&server_path.join(&world_packs))? {
+                dirs.push(world_packs.join(pack));
+            }
+        }
+    }
+
+    let mut packs = Vec::new();
+    for dir in dirs {
+        let manifest = match std::fs::read(server_path.join(&dir).join(PACK_MANIFEST)) {
+            Ok(content) => pack::parse_manifest(&content).map_err(|e| e.to_string()),
+            // A directory without a manifest isn't a pack the game loads.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => Err(e.to_string()),
+        };
+        packs.push(CustomPack { dir, manifest });
+    }
+    Ok(packs)
+}
+
+/// How likely a [`Finding`] is to break the pack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The pack may misbehave.
+    Warning,
+    /// The pack will fail to load.
+    Breaking,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Breaking => write!(f, "breaking"),
+        }
+    }
+}
+
+/// A reason a pack may not work with the incoming version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// The pack's directory, relative to the server.
+    pub dir: PathBuf,
+    /// The pack's name, empty when its manifest couldn't be read.
+    pub name: String,
+    pub severity: Severity,
+    pub reason: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.as_str() {
+            "" => write!(f, "{}: {}", self.dir.display(), self.reason),
+            name => write!(f, "{} ({}): {}", name, self.dir.display(), self.reason),
+        }
+    }
+}
+
+/// Compares the `packs` against the `incoming` version that replaces the
+/// `installed` one:
+///
+/// - a pack whose `min_engine_version` is newer than `incoming` won't load,
+/// - beta scripting modules change with every release, so a pack that uses
+///   one breaks when the major, minor or patch part changes, and may break
+///   when the installed version is unknown,
+/// - a pack depending on a pack that isn't there, or only in an older version,
+///   may misbehave.
+///
+/// Findings are sorted with the breaking ones first.
+pub fn check(
+    packs: &[CustomPack],
+    installed: Option<&Version>,
+    incoming: &Version,
+) -> Vec<Finding> {
+    let manifests: Vec<&PackManifest> = packs
+        .iter()
+        .filter_map(|pack| pack.manifest.as_ref().ok())
+        .collect();
+    let beta_severity = match installed.map(|installed| installed.change_to(incoming)) {
+        None => Some(Severity::Warning),
+        Some(change) if change >= Change::Patch => Some(Severity::Breaking),
+        Some(_) => None,
+    };
+
+    let mut findings = Vec::new();
+    for pack in packs {
+        let manifest = match &pack.manifest {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                findings.push(Finding {
+                    dir: pack.dir.clone(),
+                    name: String::new(),
+                    severity: Severity::Warning,
+                    reason: format!("cannot read {}: {}", PACK_MANIFEST, e),
+                });
+                continue;
+            }
+        };
+        let mut finding = |severity: Severity, reason: String| {
+            findings.push(Finding {
+                dir: pack.dir.clone(),
+                name: manifest.name.clone(),
+                severity,
+                reason,
+            })
+        };
+
+        if let Some(min) = &manifest.min_engine_version
+            && newer(min, incoming)
+        {
+            finding(
+                Severity::Breaking,
+                format!("needs {} or newer, not {}", min, incoming),
+            );
+        }
+        for dependency in &manifest.dependencies {
+            match dependency {
+                Dependency::Module { name, version } if version.contains("-beta") => {
+                    if let Some(severity) = beta_severity {
+                        finding(
+                            severity,
+                            format!(
+                                "uses the beta scripting module {} {}, which changes with every release",
+                                name, version
+                            ),
+                        );
+                    }
+                }
+                Dependency::Module { .. } => {}
+                Dependency::Pack { uuid, version } => {
+                    let found = manifests
+                        .iter()
+                        .filter(|other| other.uuid.eq_ignore_ascii_case(uuid))
+                        .map(|other| &other.version)
+                        .max();
+                    match (found, version) {
+                        (None, _) => finding(
+                            Severity::Warning,
+                            format!("depends on pack {}, which isn't installed", uuid),
+                        ),
+                        (Some(found), Some(version)) if newer(version, found) => finding(
+                            Severity::Warning,
+                            format!(
+                                "depends on pack {} {}, but only {} is installed",
+                                uuid, version, found
+                            ),
+                        ),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    findings
+}
+
+/// Whether `a` is newer than `b`, reading missing parts as 0, so `1.21.90` is
+/// not newer than `1.21.90.0`.
+fn newer(a: &Version, b: &Version) -> bool {
+    let len = a.parts().len().max(b.parts().len());
+    let padded = |version: &Version| {
+        let mut parts = version.parts().to_vec();
+        parts.resize(len, 0);
+        Version::from(parts)
+    };
+    padded(a) > padded(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_manifest(dir: &Path, header: &str, dependencies: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join(PACK_MANIFEST),
+            format!(
+                r#"{{"header": {}, "modules": [{{"type": "data"}}], "dependencies": [{}]}}"#,
+                header, dependencies
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_check() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let server_path = temp_dir.path();
+        write_manifest(
+            &server_path.join("behavior_packs/vanilla"),
+            r#"{"uuid": "v", "version": [1, 0, 0], "min_engine_version": [9, 0, 0]}"#,
+            "",
+        );
+        write_manifest(
+            &server_path.join("behavior_packs/scripts"),
+            r#"{"name": "Scripts", "uuid": "s", "version": [1, 0, 0], "min_engine_version": [1, 21, 90]}"#,
+            r#"{"module_name": "@minecraft/server", "version": "2.1.0-beta"},
+               {"module_name": "@minecraft/server-ui", "version": "1.3.0"},
+               {"uuid": "L", "version": [2, 0, 0]}"#,
+        );
+        write_manifest(
+            &server_path.join("worlds/Survival/behavior_packs/library"),
+            r#"{"name": "Library", "uuid": "l", "version": [1, 4, 0], "min_engine_version": [1, 21, 100]}"#,
+            r#"{"uuid": "missing"}"#,
+        );
+        std::fs::create_dir_all(server_path.join("worlds/Survival/resource_packs/broken")).unwrap();
+        std::fs::write(
+            server_path.join("worlds/Survival/resource_packs/broken/manifest.json"),
+            "{",
+        )
+        .unwrap();
+        std::fs::create_dir_all(server_path.join("resource_packs/empty")).unwrap();
+        let shipped = FileHashes::from([(
+            "behavior_packs/vanilla/manifest.json".to_owned(),
+            String::new(),
+        )]);
+
+        let packs = custom_packs(server_path, &shipped).unwrap();
+        assert_eq!(
+            packs
+                .iter()
+                .map(|pack| pack.dir.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("behavior_packs/scripts"),
+                PathBuf::from("worlds/Survival/behavior_packs/library"),
+                PathBuf::from("worlds/Survival/resource_packs/broken"),
+            ]
+        );
+
+        let installed: Version = "1.21.92.1".parse().unwrap();
+        let hotfix = check(&packs, Some(&installed), &"1.21.92.2".parse().unwrap());
+        let summary = |findings: &[Finding]| {
+            findings
+                .iter()
+                .map(|finding| (finding.name.clone(), finding.severity))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(&hotfix),
+            vec![
+                ("Library".to_owned(), Severity::Breaking),
+                ("Scripts".to_owned(), Severity::Warning),
+                ("Library".to_owned(), Severity::Warning),
+                (String::new(), Severity::Warning),
+            ]
+        );
+        assert!(hotfix[1].reason.contains("1.4.0"), "{}", hotfix[1].reason);
+
+        let release = check(&packs, Some(&installed), &"1.21.100.6".parse().unwrap());
+        assert_eq!(
+            summary(&release),
+            vec![
+                ("Scripts".to_owned(), Severity::Breaking),
+                ("Scripts".to_owned(), Severity::Warning),
+                ("Library".to_owned(), Severity::Warning),
+                (String::new(), Severity::Warning),
+            ]
+        );
+        assert!(release[0].reason.contains("@minecraft/server 2.1.0-beta"));
+
+        let unknown = check(&packs, None, &"1.21.100.6".parse().unwrap());
+        assert_eq!(unknown[0].severity, Severity::Warning);
+    }
+}
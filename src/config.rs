@@ -0,0 +1,241 @@
+use crate::backup::Retention;
+use crate::http::HttpSettings;
+use crate::policy::UpdatePolicy;
+use crate::source::SourceConfig;
+use serde::Deserialize;
+
+/// Config files kept when updating, and archived alongside worlds in backups.
+pub const DEFAULT_EXCLUDE: &[&str] = &["server.properties", "permissions.json", "allowlist.json"];
+
+/// Settings read from the bedrock-up config file. Every field is optional and
+/// command line arguments or environment variables take precedence.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    /// Links API endpoints to query, in order, before the built-in default.
+    pub links_urls: Vec<String>,
+
+    /// Public download page scraped when every links endpoint fails.
+    pub download_page_url: Option<String>,
+
+    /// Only query the configured endpoints, never the built-in defaults.
+    pub no_fallback: bool,
+
+    /// Owner to give extracted files, as `user[:group]`.
+    pub owner: Option<String>,
+
+    /// Where releases come from when a server doesn't set its own source.
+    /// Defaults to the official links API.
+    pub source: Option<SourceConfig>,
+
+    /// Which updates are applied without approval when a server doesn't set
+    /// its own policy. Defaults to every update.
+    pub policy: Option<UpdatePolicy>,
+
+    /// HTTP client settings.
+    pub http: HttpSettings,
+
+    /// Servers managed by commands that act on every server.
+    pub servers: Vec<ServerConfig>,
+
+    /// Backup location and retention.
+    pub backup: BackupConfig,
+}
+
+/// A server managed by bedrock-up.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ServerConfig {
+    /// Name used in messages and backup file names. Defaults to the directory name.
+    pub name: Option<String>,
+
+    /// Directory where the server files are located.
+    pub server_path: String,
+
+    /// Config files to keep when updating. Defaults to `DEFAULT_EXCLUDE`.
+    pub exclude: Option<Vec<String>>,
+
+    /// Where this server's releases come from. Defaults to the config-wide source.
+    pub source: Option<SourceConfig>,
+
+    /// Hold updates back until no players are online.
+    pub defer_until_empty: Option<bool>,
+
+    /// Longest an update waits for players to leave, in seconds.
+    pub max_deferral_secs: Option<u64>,
+
+    /// Which updates are applied without approval. Defaults to the config-wide policy.
+    pub policy: Option<UpdatePolicy>,
+}
+
+impl ServerConfig {
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            let server_path = shellexpand::tilde(&self.server_path).to_string();
+            std::path::Path::new(&server_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "server".to_owned())
+        })
+    }
+
+    pub fn exclude(&self) -> Vec<String> {
+        self.exclude.clone().unwrap_or_else(|| {
+            DEFAULT_EXCLUDE
+                .iter()
+                .map(|file| file.to_string())
+                .collect()
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BackupConfig {
+    /// Directory backups are written to. Defaults to `~/.bedrock-up/backups`.
+    pub directory: Option<String>,
+
+    #[serde(flatten)]
+    pub retention: Retention,
+}
+
+/// Loads the config file at `config_path`. A missing file is not an error and
+/// yields the default config.
+pub fn load_config(config_path: &str) -> std::io::Result<Config> {
+    let config_path = shellexpand::tilde(config_path).to_string();
+
+    let file = match std::fs::File::open(&config_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(e),
+    };
+
+    serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Failed to parse config file {}: {}", config_path, e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_load_config_missing_file() {
+        let result = load_config("/non/existent/config.json").unwrap();
+
+        assert_eq!(result, Config::default());
+    }
+
+    #[test]
+    fn test_load_config_success() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(
+            temp_file,
+            r#"{{
+                "linksUrls": ["https://mirror.example.com/links"],
+                "downloadPageUrl": "https://example.com/download",
+                "noFallback": true,
+                "http": {{ "readTimeoutSecs": 120, "proxy": "http://proxy.internal:3128" }}
+            }}"#
+        )
+        .unwrap();
+        temp_file.flush().unwrap();
+
+        let result = load_config(temp_file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(result.links_urls, vec!["https://mirror.example.com/links"]);
+        assert_eq!(
+            result.download_page_url.as_deref(),
+            Some("https://example.com/download")
+        );
+        assert!(result.no_fallback);
+        assert_eq!(result.http.read_timeout_secs, Some(120));
+        assert_eq!(
+            result.http.proxy.as_deref(),
+            Some("http://proxy.internal:3128")
+        );
+    }
+
+    #[test]
+    fn test_load_config_partial() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"{{ "noFallback": true }}"#).unwrap();
+        temp_file.flush().unwrap();
+
+        let result = load_config(temp_file.path().to_str().unwrap()).unwrap();
+
+        assert!(result.links_urls.is_empty());
+        assert_eq!(result.download_page_url, None);
+        assert!(result.no_fallback);
+    }
+
+    #[test]
+    fn test_load_config_servers() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(
+            temp_file,
+            r#"{{
+                "servers": [
+                    {{ "name": "survival", "serverPath": "/srv/survival",
+                       "deferUntilEmpty": true, "maxDeferralSecs": 1800,
+                       "policy": {{ "autoApply": "patch", "autoApplyPreviews": false }} }},
+                    {{ "serverPath": "/srv/creative", "exclude": ["server.properties"],
+                       "source": {{ "type": "directory", "path": "/srv/zips" }} }}
+                ],
+                "source": {{ "type": "mirror", "url": "http://mirror.lan:8080", "publicKey": "ab12" }},
+                "backup": {{ "directory": "/srv/backups", "keepLast": 3 }}
+            }}"#
+        )
+        .unwrap();
+        temp_file.flush().unwrap();
+
+        let result = load_config(temp_file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(result.servers.len(), 2);
+        assert_eq!(result.servers[0].name(), "survival");
+        assert_eq!(result.servers[0].exclude(), DEFAULT_EXCLUDE);
+        assert_eq!(result.servers[0].defer_until_empty, Some(true));
+        assert_eq!(result.servers[0].max_deferral_secs, Some(1800));
+        assert_eq!(
+            result.servers[0].policy,
+            Some(UpdatePolicy {
+                auto_apply: crate::version::Change::Patch,
+                auto_apply_previews: false,
+            })
+        );
+        assert_eq!(result.servers[1].policy, None);
+        assert_eq!(result.servers[1].name(), "creative");
+        assert_eq!(result.servers[1].exclude(), vec!["server.properties"]);
+        assert_eq!(
+            result.servers[1].source,
+            Some(SourceConfig::Directory {
+                path: "/srv/zips".to_owned()
+            })
+        );
+        assert_eq!(
+            result.source,
+            Some(SourceConfig::Mirror {
+                url: "http://mirror.lan:8080".to_owned(),
+                public_key: Some("ab12".to_owned()),
+            })
+        );
+        assert_eq!(result.backup.directory.as_deref(), Some("/srv/backups"));
+        assert_eq!(result.backup.retention.keep_last, Some(3));
+    }
+
+    #[test]
+    fn test_load_config_invalid_json() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "invalid json content").unwrap();
+        temp_file.flush().unwrap();
+
+        let result = load_config(temp_file.path().to_str().unwrap());
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex};
+
+/// Lines queued for a viewer before it counts as not keeping up.
+const VIEWER_BACKLOG: usize = 256;
+
+/// Where a supervised server's console socket lives.
+pub fn socket_path(server_path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(server_path).to_string()).join(".bedrock-up.sock")
+}
+
+/// Everyone watching the server's console. Output is copied to each viewer
+/// by a thread of its own, so a stuck viewer never holds up the console, and
+/// a viewer that can't keep up or went away is dropped.
+#[derive(Clone, Default)]
+pub struct Viewers(Arc<Mutex<Vec<SyncSender<String>>>>);
+
+impl Viewers {
+    pub fn add(&self, mut viewer: Box<dyn Write + Send>) {
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<String>(VIEWER_BACKLOG);
+        std::thread::spawn(move || {
+            for line in receiver {
+                // Ending the thread drops the viewer on the next broadcast.
+                if writeln!(viewer, "{}", line)
+                    .and_then(|()| viewer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+    }
+
+    pub fn broadcast(&self, line: &str) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|viewer| viewer.try_send(line.to_owned()).is_ok());
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A listening console socket. The socket file is removed when dropped.
+#[derive(Debug)]
+pub struct ConsoleSocket {
+    path: PathBuf,
+}
+
+impl Drop for ConsoleSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::{ConsoleSocket, Viewers};
+    use crate::supervisor::ConsoleInput;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::DirBuilderExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    /// How long writing to a viewer may block before the viewer is dropped.
+    const VIEWER_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Accepts console clients on a Unix domain socket at `path`. Each client
+    /// becomes a viewer, and every line it sends is a console command.
+    pub fn listen(
+        path: &Path,
+        input: ConsoleInput,
+        viewers: Viewers,
+    ) -> std::io::Result<ConsoleSocket> {
+        // A socket is created with the umask's mode, so it is bound in a
+        // directory only we can enter and moved into place once it is owner
+        // and group only: anyone who can connect can run commands.
+        let private_dir = private_dir_path(path);
+        // Left behind by a supervisor that didn't exit cleanly. The server lock
+        // guarantees no other supervisor is using it.
+        match std::fs::remove_dir_all(&private_dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&private_dir)?;
+        let bound = private_dir.join("console.sock");
+        let listener = UnixListener::bind(&bound)?;
+        crate::permissions::set_mode(&bound, 0o660)?;
+        // Replaces a socket left behind the same way.
+        std::fs::rename(&bound, path)?;
+        std::fs::remove_dir(&private_dir)?;
+        let socket = ConsoleSocket {
+            path: path.to_path_buf(),
+        };
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                // A client that fails here is already gone, there is no one to tell.
+                let _ = accept(stream, input.clone(), &viewers);
+            }
+        });
+
+        Ok(socket)
+    }
+
+    fn private_dir_path(path: &Path) -> PathBuf {
+        let mut dir = path.as_os_str().to_owned();
+        dir.push(".bind");
+        PathBuf::from(dir)
+    }
+
+    fn accept(stream: UnixStream, input: ConsoleInput, viewers: &Viewers) -> std::io::Result<()> {
+        stream.set_write_timeout(Some(VIEWER_WRITE_TIMEOUT))?;
+        let mut replies = stream.try_clone()?;
+        viewers.add(Box::new(stream.try_clone()?));
+
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Err(e) = input.send(&line) {
+                    let _ = writeln!(replies, "bedrock-up: cannot send {}: {}", line, e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Connects to the console socket at `path`.
+    pub fn connect(path: &Path) -> std::io::Result<UnixStream> {
+        UnixStream::connect(path)
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{connect, listen};
+
+#[cfg(not(unix))]
+pub fn listen(
+    _path: &std::path::Path,
+    _input: crate::supervisor::ConsoleInput,
+    _viewers: Viewers,
+) -> std::io::Result<ConsoleSocket> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "The console socket is only supported on unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewers_drop_failed_viewer() {
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let viewers = Viewers::default();
+        viewers.add(Box::new(Vec::new()));
+        viewers.add(Box::new(Broken));
+
+        // The broken viewer is dropped once its thread has seen the failure.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while viewers.len() > 1 && std::time::Instant::now() < deadline {
+            viewers.broadcast("[INFO] Server started.");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(viewers.len(), 1);
+    }
+
+    #[test]
+    fn test_viewers_drop_stuck_viewer() {
+        /// Blocks on its first write until the test ends.
+        struct Stuck(std::sync::mpsc::Receiver<()>);
+        impl Write for Stuck {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                let _ = self.0.recv();
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let (release, stuck) = std::sync::mpsc::channel();
+        let viewers = Viewers::default();
+        viewers.add(Box::new(Stuck(stuck)));
+
+        let started = std::time::Instant::now();
+        for _ in 0..=VIEWER_BACKLOG + 1 {
+            viewers.broadcast("[INFO] Running AutoCompaction...");
+        }
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        assert!(viewers.is_empty());
+        drop(release);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_console_socket_multiple_viewers() {
+        use crate::supervisor::{ConsoleInput, Output, ServerProcess};
+        use std::io::{BufRead, BufReader};
+        use std::time::Duration;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("console.sock");
+        let input = ConsoleInput::default();
+        let viewers = Viewers::default();
+        let socket = listen(&path, input.clone(), viewers.clone()).unwrap();
+
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", r#"read cmd; echo "got $cmd""#]);
+        let server = ServerProcess::spawn(command, input).unwrap();
+
+        let mut first = connect(&path).unwrap();
+        let second = connect(&path).unwrap();
+        while viewers.len() < 2 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        writeln!(first, "say hi").unwrap();
+        let Output::Line(line) = server.next_line(Duration::from_secs(10)) else {
+            panic!("the server got no command");
+        };
+        viewers.broadcast(&line);
+
+        for viewer in [first, second] {
+            let mut received = String::new();
+            BufReader::new(viewer).read_line(&mut received).unwrap();
+            assert_eq!(received, "got say hi\n");
+        }
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_console_socket_mode() {
+        use crate::supervisor::ConsoleInput;
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("console.sock");
+        // A leftover from a supervisor that was killed.
+        std::fs::write(&path, "").unwrap();
+
+        let _socket = listen(&path, ConsoleInput::default(), Viewers::default()).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+        assert!(connect(&path).is_ok());
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::health::{self, HealthError};
+use crate::{properties, raknet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_MAX_DEFERRAL_SECS: u64 = 3600;
+/// How often a deferred update checks whether the players have left.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// Pings sent before a server is taken not to answer. A single lost packet or
+/// a busy server mustn't pass for an empty one.
+const PING_ATTEMPTS: u32 = 3;
+
+/// Holds updates back while players are on the server, for at most `max`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deferral {
+    pub server_path: PathBuf,
+    pub max: Duration,
+}
+
+/// Whether a deferred update may go ahead. `players` is `None` when the
+/// server is running but didn't answer its pings, so players may be online.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Apply,
+    /// Players are online, check again later.
+    Wait {
+        players: Option<u32>,
+    },
+    /// Players are still online, but the update was held back long enough.
+    Overdue {
+        players: Option<u32>,
+    },
+}
+
+impl Deferral {
+    pub fn new(server_path: &str, max: Duration) -> Deferral {
+        Deferral {
+            server_path: PathBuf::from(shellexpand::tilde(server_path).to_string()),
+            max,
+        }
+    }
+
+    /// Players on the local server, or `None` when it answers none of
+    /// [`PING_ATTEMPTS`] pings.
+    pub fn players_online(&self) -> Option<u32> {
+        let port = properties::server_port(&self.server_path);
+        (0..PING_ATTEMPTS).find_map(|_| {
+            raknet::ping(("127.0.0.1", port), PING_TIMEOUT)
+                .ok()
+                .map(|status| status.players)
+        })
+    }
+
+    /// Decides on an update that has been waiting since `since`.
+    pub fn decide(&self, since: Instant) -> Decision {
+        let players = match self.players_online() {
+            Some(0) => return Decision::Apply,
+            Some(players) => Some(players),
+            // A server that still holds its port is running, just not
+            // answering. Only one that doesn't can't have players on it.
+            None if self.port_in_use() => None,
+            None => return Decision::Apply,
+        };
+        if since.elapsed() >= self.max {
+            Decision::Overdue { players }
+        } else {
+            Decision::Wait { players }
+        }
+    }
+
+    fn port_in_use(&self) -> bool {
+        let port = properties::server_port(&self.server_path);
+        matches!(
+            health::check_port_free(port),
+            Err(HealthError::PortInUse(_))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+
+    /// Answers pings on a random port with `players` online, after ignoring
+    /// the first `ignored` ones, and points the server's `server.properties`
+    /// at it.
+    fn fake_server(server_path: &std::path::Path, players: u32, ignored: usize) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        std::fs::write(
+            server_path.join(properties::SERVER_PROPERTIES),
+            format!("server-port={}\n", port),
+        )
+        .unwrap();
+
+        std::thread::spawn(move || {
+            let status = format!(
+                "MCPE;Dedicated Server;818;1.21.92;{};10;1;Bedrock level;Survival;1;{};{};",
+                players,
+                port,
+                port + 1
+            );
+            let pong = raknet::pong_packet(&status);
+
+            let mut buf = [0u8; 1500];
+            let mut pings = 0;
+            while let Ok((_, client)) = socket.recv_from(&mut buf) {
+                pings += 1;
+                if pings > ignored {
+                    let _ = socket.send_to(&pong, client);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_decide_waits_for_players() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fake_server(temp_dir.path(), 3, 0);
+        let deferral = Deferral::new(temp_dir.path().to_str().unwrap(), Duration::from_secs(60));
+
+        assert_eq!(deferral.players_online(), Some(3));
+        assert_eq!(
+            deferral.decide(Instant::now()),
+            Decision::Wait { players: Some(3) }
+        );
+    }
+
+    #[test]
+    fn test_decide_overdue() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fake_server(temp_dir.path(), 1, 0);
+        let deferral = Deferral::new(temp_dir.path().to_str().unwrap(), Duration::ZERO);
+
+        assert_eq!(
+            deferral.decide(Instant::now()),
+            Decision::Overdue { players: Some(1) }
+        );
+    }
+
+    #[test]
+    fn test_decide_empty_server() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fake_server(temp_dir.path(), 0, 0);
+        let deferral = Deferral::new(temp_dir.path().to_str().unwrap(), Duration::from_secs(60));
+
+        assert_eq!(deferral.decide(Instant::now()), Decision::Apply);
+    }
+
+    #[test]
+    fn test_decide_retries_a_lost_ping() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fake_server(temp_dir.path(), 2, 1);
+        let deferral = Deferral::new(temp_dir.path().to_str().unwrap(), Duration::from_secs(60));
+
+        assert_eq!(
+            deferral.decide(Instant::now()),
+            Decision::Wait { players: Some(2) }
+        );
+    }
+
+    #[test]
+    fn test_decide_waits_for_silent_server() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fake_server(temp_dir.path(), 2, usize::MAX);
+        let deferral = Deferral::new(temp_dir.path().to_str().unwrap(), Duration::ZERO);
+
+        assert_eq!(
+            deferral.decide(Instant::now()),
+            Decision::Overdue { players: None }
+        );
+    }
+
+    #[test]
+    fn test_decide_stopped_server() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let port = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        std::fs::write(
+            temp_dir.path().join(properties::SERVER_PROPERTIES),
+            format!("server-port={}\n", port),
+        )
+        .unwrap();
+        let deferral = Deferral::new(temp_dir.path().to_str().unwrap(), Duration::from_secs(60));
+
+        assert_eq!(deferral.decide(Instant::now()), Decision::Apply);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::health::HealthError;
+use crate::lock::LockError;
+use crate::release::{DownloadType, LinksSchemaError};
+use crate::version::Version;
+use crate::world::World;
+
+/// Everything that can go wrong while checking for, downloading or applying an
+/// update.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    Zip(zip::result::ZipError),
+    Lock(LockError),
+    /// A links endpoint answered, but not in the expected shape.
+    Schema(LinksSchemaError),
+    /// No endpoint in the fallback chain had a release for the download type.
+    /// Holds the failure of each endpoint, in order.
+    NoRelease {
+        download_type: DownloadType,
+        failures: Vec<(String, Error)>,
+    },
+    /// The updated server failed its health check and was rolled back.
+    Unhealthy(HealthError),
+    /// Installing `version` would open worlds last opened by a newer version.
+    Downgrade {
+        version: Version,
+        worlds: Vec<World>,
+    },
+    /// A mirror's release failed its signature or hash check.
+    Signature(String),
+    /// A failure described only by a message, such as unusable arguments.
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Http(e) => write!(f, "{}", e),
+            Error::Json(e) => write!(f, "invalid JSON: {}", e),
+            Error::Zip(e) => write!(f, "invalid archive: {}", e),
+            Error::Lock(e) => write!(f, "{}", e),
+            Error::Schema(e) => write!(f, "unexpected links response: {}", e),
+            Error::NoRelease {
+                download_type,
+                failures,
+            } => {
+                write!(
+                    f,
+                    "no links endpoint provided a download for {}",
+                    download_type
+                )?;
+                for (url, e) in failures {
+                    write!(f, "\n  {}: {}", url, e)?;
+                }
+                Ok(())
+            }
+            Error::Unhealthy(e) => {
+                write!(
+                    f,
+                    "update rolled back, the server failed its health check: {}",
+                    e
+                )
+            }
+            Error::Downgrade { version, worlds } => {
+                write!(
+                    f,
+                    "refusing to install {}, which is older than the worlds were opened with",
+                    version
+                )?;
+                for world in worlds {
+                    if let Some(required) = world.required_version() {
+                        write!(f, "\n  {}: {}", world.name, required)?;
+                    }
+                }
+                Ok(())
+            }
+            Error::Signature(message) => write!(f, "untrusted release: {}", message),
+            Error::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Http(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Zip(e) => Some(e),
+            Error::Lock(e) => Some(e),
+            Error::Schema(e) => Some(e),
+            Error::Unhealthy(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::Zip(e)
+    }
+}
+
+impl From<LockError> for Error {
+    fn from(e: LockError) -> Self {
+        Error::Lock(e)
+    }
+}
@@ -0,0 +1,134 @@
+use crate::error::Result;
+use crate::platform::Platform;
+use crate::verify::{self, FileHashes};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The name the known-version index is kept and served under.
+pub const KNOWN_VERSIONS_FILE: &str = "known-versions.json";
+
+/// Which release each server binary belongs to, keyed by the binary's
+/// SHA-256. Lets a server installed without bedrock-up be identified from its
+/// `bedrock_server`, whichever version it runs.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KnownVersions(BTreeMap<String, String>);
+
+impl KnownVersions {
+    /// Reads the index at `path`, empty when it doesn't exist yet.
+    pub fn read(path: &Path) -> Result<KnownVersions> {
+        match std::fs::read(path) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(KnownVersions::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether a server binary of `version` is recorded.
+    pub fn has_version(&self, version: &str) -> bool {
+        self.0.values().any(|known| known == version)
+    }
+
+    /// Records the server binary among the `files` of a release as
+    /// `version`. Returns whether the index changed.
+    pub fn record_files(&mut self, files: &FileHashes, version: &str) -> bool {
+        let mut changed = false;
+        for platform in Platform::ALL {
+            if let Some(hash) = files.get(platform.marker()) {
+                changed |=
+                    self.0.insert(hash.clone(), version.to_owned()).as_deref() != Some(version);
+            }
+        }
+        changed
+    }
+
+    /// Records the server binary in the release zip at `zip_path` as
+    /// `version`. Returns whether the index changed.
+    pub fn record_zip(&mut self, zip_path: &Path, version: &str) -> Result<bool> {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(zip_path)?)?;
+        let mut files = FileHashes::new();
+        for platform in Platform::ALL {
+            if let Ok(file) = archive.by_name(platform.marker()) {
+                files.insert(platform.marker().to_owned(), verify::hash_reader(file)?);
+            }
+        }
+        Ok(self.record_files(&files, version))
+    }
+
+    /// Adds the entries of `other`. Returns whether the index changed.
+    pub fn merge(&mut self, other: KnownVersions) -> bool {
+        let len = self.0.len();
+        for (hash, version) in other.0 {
+            self.0.entry(hash).or_insert(version);
+        }
+        self.0.len() != len
+    }
+
+    /// The version of the server binary installed in `server_path`, when the
+    /// index knows it.
+    pub fn identify(&self, server_path: &Path) -> Result<Option<String>> {
+        for platform in Platform::ALL {
+            let file = match std::fs::File::open(server_path.join(platform.marker())) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let hash = verify::hash_reader(file)?;
+            return Ok(self.0.get(&hash).cloned());
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_zip;
+
+    #[test]
+    fn test_identify() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("bedrock-server-1.21.90.3.zip");
+        write_zip(&zip_path, &[("bedrock_server", "old binary")]);
+        let server_path = temp_dir.path().join("server");
+        std::fs::create_dir(&server_path).unwrap();
+
+        let mut known = KnownVersions::default();
+        assert_eq!(known.identify(&server_path).unwrap(), None);
+        assert!(known.record_zip(&zip_path, "1.21.90.3").unwrap());
+        assert!(!known.record_zip(&zip_path, "1.21.90.3").unwrap());
+        std::fs::write(server_path.join("bedrock_server"), "new binary").unwrap();
+        assert_eq!(known.identify(&server_path).unwrap(), None);
+        std::fs::write(server_path.join("bedrock_server"), "old binary").unwrap();
+        assert_eq!(
+            known.identify(&server_path).unwrap().as_deref(),
+            Some("1.21.90.3")
+        );
+
+        let index_path = temp_dir.path().join(KNOWN_VERSIONS_FILE);
+        known.write(&index_path).unwrap();
+        let mut read = KnownVersions::read(&index_path).unwrap();
+        assert_eq!(read, known);
+        assert!(!read.merge(known));
+        let files = FileHashes::from([(
+            "bedrock_server.exe".to_owned(),
+            verify::hash_reader(&b"windows binary"[..]).unwrap(),
+        )]);
+        assert!(read.record_files(&files, "1.21.92.1"));
+    }
+}
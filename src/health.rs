@@ -0,0 +1,189 @@
+use crate::release::DownloadType;
+use crate::supervisor::{ConsoleInput, DEFAULT_STOP_TIMEOUT_SECS, Output, ServerProcess};
+use std::net::{Ipv4Addr, UdpSocket};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// What the server logs once it accepts players.
+pub const STARTED_LINE: &str = "Server started.";
+pub const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+/// Why a server failed its health check.
+#[derive(Debug)]
+pub enum HealthError {
+    Exited(ExitStatus),
+    TimedOut(Duration),
+    Unsupported(DownloadType),
+    /// Something, most likely the running server, is listening on the port.
+    PortInUse(u16),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for HealthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthError::Exited(status) => {
+                write!(f, "the server exited with {} before it started", status)
+            }
+            HealthError::TimedOut(timeout) => {
+                write!(f, "the server did not start within {:?}", timeout)
+            }
+            HealthError::Unsupported(download_type) => {
+                write!(f, "health checks are not supported for {}", download_type)
+            }
+            HealthError::PortInUse(port) => write!(
+                f,
+                "port {} is already in use, stop the server before checking that it starts",
+                port
+            ),
+            HealthError::Io(e) => write!(f, "could not run the server: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HealthError {}
+
+impl From<std::io::Error> for HealthError {
+    fn from(e: std::io::Error) -> Self {
+        HealthError::Io(e)
+    }
+}
+
+/// The command that runs the server installed at `server_path`.
+pub fn server_command(
+    server_path: &Path,
+    download_type: &DownloadType,
+) -> Result<Command, HealthError> {
+    let mut command = match download_type {
+        DownloadType::Windows | DownloadType::PreviewWindows => {
+            Command::new(server_path.join("bedrock_server.exe"))
+        }
+        DownloadType::Linux | DownloadType::PreviewLinux => {
+            let mut command = Command::new(server_path.join("bedrock_server"));
+            // The server ships its own libraries next to the binary.
+            command.env("LD_LIBRARY_PATH", server_path);
+            command
+        }
+        DownloadType::ServerJar => return Err(HealthError::Unsupported(*download_type)),
+    };
+    command.current_dir(server_path);
+    Ok(command)
+}
+
+/// Fails when `port` is already bound, so the checked server doesn't collide
+/// with one that is still running.
+pub fn check_port_free(port: u16) -> Result<(), HealthError> {
+    match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => Err(HealthError::PortInUse(port)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Runs `command` until it logs [`STARTED_LINE`], then stops it. Fails when the
+/// process exits first or doesn't start within `timeout`.
+pub fn check_started(command: Command, timeout: Duration) -> Result<(), HealthError> {
+    let mut server = ServerProcess::spawn(command, ConsoleInput::default())?;
+    wait_until_started(&mut server, timeout, |_| {})?;
+    server.stop(Duration::from_secs(DEFAULT_STOP_TIMEOUT_SECS))?;
+    Ok(())
+}
+
+/// Watches a running server's console, passing each line to `on_line`, until
+/// it logs [`STARTED_LINE`]. A server that doesn't start in time is killed.
+pub fn wait_until_started(
+    server: &mut ServerProcess,
+    timeout: Duration,
+    mut on_line: impl FnMut(&str),
+) -> Result<(), HealthError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match server.next_line(deadline.saturating_duration_since(Instant::now())) {
+            Output::Line(line) => {
+                on_line(&line);
+                if line.contains(STARTED_LINE) {
+                    return Ok(());
+                }
+            }
+            Output::Timeout => {
+                server.kill()?;
+                return Err(HealthError::TimedOut(timeout));
+            }
+            Output::Closed => return Err(HealthError::Exited(server.wait()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_started_stops_server() {
+        let command = shell(
+            r#"echo "NO LOG FILE! - setting up server logging..."
+            echo "[2025-07-04 12:00:00:000 INFO] Server started."
+            read cmd
+            [ "$cmd" = stop ] || exit 3"#,
+        );
+
+        let result = check_started(command, Duration::from_secs(10));
+
+        assert!(result.is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_started_server_exits() {
+        let command = shell("echo 'Loading...'; exit 2");
+
+        let result = check_started(command, Duration::from_secs(10));
+
+        match result {
+            Err(HealthError::Exited(status)) => assert_eq!(status.code(), Some(2)),
+            other => panic!("expected Exited, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_started_times_out() {
+        let command = shell("echo 'Loading...'; sleep 10");
+
+        let result = check_started(command, Duration::from_millis(200));
+
+        assert!(matches!(result, Err(HealthError::TimedOut(_))));
+    }
+
+    #[test]
+    fn test_check_port_free() {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        assert!(matches!(
+            check_port_free(port),
+            Err(HealthError::PortInUse(p)) if p == port
+        ));
+        drop(socket);
+        assert!(check_port_free(port).is_ok());
+    }
+
+    #[test]
+    fn test_server_command_unsupported() {
+        let result = server_command(Path::new("/srv/bedrock"), &DownloadType::ServerJar);
+
+        assert!(matches!(
+            result,
+            Err(HealthError::Unsupported(DownloadType::ServerJar))
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+use clap::Args;
+use reqwest::header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const DEFAULT_USER_AGENT: &str = concat!("bedrock-up/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 500;
+
+/// HTTP client settings, taken from the command line or the config file's
+/// `http` section.
+#[derive(Args, Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HttpSettings {
+    /// Seconds to wait for a connection to be established [default: 10]
+    #[arg(long = "connect-timeout", value_name = "SECS", global = true)]
+    pub connect_timeout_secs: Option<u64>,
+
+    /// Seconds to wait for each read from a connection [default: 60]
+    #[arg(long = "read-timeout", value_name = "SECS", global = true)]
+    pub read_timeout_secs: Option<u64>,
+
+    /// How many times to retry a request after a transient failure [default: 3]
+    #[arg(long, global = true)]
+    pub retries: Option<u32>,
+
+    /// Delay before the first retry, doubled on every further retry [default: 500]
+    #[arg(long = "retry-backoff", value_name = "MS", global = true)]
+    pub retry_backoff_ms: Option<u64>,
+
+    /// Proxy to send every request through, e.g. http://proxy.internal:3128
+    #[arg(long, env = "BEDROCK_UP_PROXY", global = true)]
+    pub proxy: Option<String>,
+
+    /// User-Agent header sent with every request
+    #[arg(long, env = "BEDROCK_UP_USER_AGENT", global = true)]
+    pub user_agent: Option<String>,
+
+    /// Extra PEM encoded root certificates to trust
+    #[arg(long = "ca-cert", value_name = "PATH", global = true)]
+    pub ca_certs: Vec<String>,
+}
+
+impl HttpSettings {
+    /// Fills every unset value from `fallback`.
+    pub fn merged_with(&self, fallback: &HttpSettings) -> HttpSettings {
+        HttpSettings {
+            connect_timeout_secs: self.connect_timeout_secs.or(fallback.connect_timeout_secs),
+            read_timeout_secs: self.read_timeout_secs.or(fallback.read_timeout_secs),
+            retries: self.retries.or(fallback.retries),
+            retry_backoff_ms: self.retry_backoff_ms.or(fallback.retry_backoff_ms),
+            proxy: self.proxy.clone().or_else(|| fallback.proxy.clone()),
+            user_agent: self
+                .user_agent
+                .clone()
+                .or_else(|| fallback.user_agent.clone()),
+            ca_certs: if self.ca_certs.is_empty() {
+                fallback.ca_certs.clone()
+            } else {
+                self.ca_certs.clone()
+            },
+        }
+    }
+}
+
+/// The `ETag` and `Last-Modified` of a response, sent back with a later
+/// request so the server can answer 304 Not Modified when nothing changed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Validators {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// The validators of `resp`, or `None` when it has neither header.
+    pub fn from_response(resp: &reqwest::blocking::Response) -> Option<Validators> {
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        (validators != Validators::default()).then_some(validators)
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = self.etag.as_ref().and_then(|etag| etag.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = self
+            .last_modified
+            .as_ref()
+            .and_then(|last_modified| last_modified.parse().ok())
+        {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+}
+
+/// A configured HTTP client shared by every request bedrock-up makes.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::blocking::Client,
+    retries: u32,
+    retry_backoff: Duration,
+}
+
+impl HttpClient {
+    pub fn new(settings: &HttpSettings) -> std::io::Result<HttpClient> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, message);
+
+        let mut builder = reqwest::blocking::Client::builder()
+            .connect_timeout(Duration::from_secs(
+                settings
+                    .connect_timeout_secs
+                    .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+            ))
+            .timeout(Duration::from_secs(
+                settings
+                    .read_timeout_secs
+                    .unwrap_or(DEFAULT_READ_TIMEOUT_SECS),
+            ))
+            .user_agent(settings.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+
+        if let Some(proxy) = &settings.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| invalid(format!("Invalid proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        for ca_cert in &settings.ca_certs {
+            let ca_cert = shellexpand::tilde(ca_cert).to_string();
+            let pem = std::fs::read(&ca_cert)?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| invalid(format!("Invalid certificate {}: {}", ca_cert, e)))?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| invalid(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(HttpClient {
+            client,
+            retries: settings.retries.unwrap_or(DEFAULT_RETRIES),
+            retry_backoff: Duration::from_millis(
+                settings
+                    .retry_backoff_ms
+                    .unwrap_or(DEFAULT_RETRY_BACKOFF_MS),
+            ),
+        })
+    }
+
+    /// Sends a GET request, retrying with exponential backoff on connection
+    /// failures, timeouts, 429 and 5xx responses. The last response or error is
+    /// returned once the retries run out.
+    pub fn get(&self, url: &str) -> reqwest::Result<reqwest::blocking::Response> {
+        self.send(url, HeaderMap::new())
+    }
+
+    /// Like [`HttpClient::get`], but asks the server to answer 304 Not Modified
+    /// when the resource still matches `validators`.
+    pub fn get_if_changed(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        self.send(url, validators.headers())
+    }
+
+    fn send(&self, url: &str, headers: HeaderMap) -> reqwest::Result<reqwest::blocking::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = self.client.get(url).headers(headers.clone()).send();
+            let transient = match &result {
+                Ok(resp) => is_transient_status(resp.status()),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !transient || attempt >= self.retries {
+                return result;
+            }
+
+            let delay = self.retry_backoff * 2u32.saturating_pow(attempt);
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    fn fast_retry_settings(retries: u32) -> HttpSettings {
+        HttpSettings {
+            retries: Some(retries),
+            retry_backoff_ms: Some(1),
+            ..HttpSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_merged_with_prefers_self() {
+        let args = HttpSettings {
+            retries: Some(1),
+            user_agent: Some("from-args".to_owned()),
+            ..HttpSettings::default()
+        };
+        let config = HttpSettings {
+            retries: Some(5),
+            read_timeout_secs: Some(120),
+            ca_certs: vec!["/etc/ssl/internal.pem".to_owned()],
+            ..HttpSettings::default()
+        };
+
+        let result = args.merged_with(&config);
+
+        assert_eq!(result.retries, Some(1));
+        assert_eq!(result.user_agent.as_deref(), Some("from-args"));
+        assert_eq!(result.read_timeout_secs, Some(120));
+        assert_eq!(result.ca_certs, vec!["/etc/ssl/internal.pem"]);
+    }
+
+    #[test]
+    fn test_get_sends_user_agent() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/links")
+            .match_header("user-agent", "custom-agent/1.0")
+            .with_status(200)
+            .create();
+
+        let client = HttpClient::new(&HttpSettings {
+            user_agent: Some("custom-agent/1.0".to_owned()),
+            ..HttpSettings::default()
+        })
+        .unwrap();
+        let result = client.get(&format!("{}/links", server.url())).unwrap();
+
+        mock.assert();
+        assert!(result.status().is_success());
+    }
+
+    #[test]
+    fn test_get_sends_default_user_agent() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/links")
+            .match_header("user-agent", DEFAULT_USER_AGENT)
+            .with_status(200)
+            .create();
+
+        let client = HttpClient::new(&HttpSettings::default()).unwrap();
+        client.get(&format!("{}/links", server.url())).unwrap();
+
+        mock.assert();
+    }
+
+    #[test]
+    fn test_get_retries_transient_status() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/links")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        let client = HttpClient::new(&fast_retry_settings(2)).unwrap();
+        let result = client.get(&format!("{}/links", server.url())).unwrap();
+
+        mock.assert();
+        assert_eq!(result.status(), 503);
+    }
+
+    #[test]
+    fn test_get_does_not_retry_client_error() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/links")
+            .with_status(404)
+            .expect(1)
+            .create();
+
+        let client = HttpClient::new(&fast_retry_settings(2)).unwrap();
+        let result = client.get(&format!("{}/links", server.url())).unwrap();
+
+        mock.assert();
+        assert_eq!(result.status(), 404);
+    }
+
+    #[test]
+    fn test_get_if_changed_sends_validators() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/links")
+            .match_header("if-none-match", "\"abc\"")
+            .match_header("if-modified-since", "Wed, 21 Oct 2015 07:28:00 GMT")
+            .with_status(304)
+            .create();
+        let validators = Validators {
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_owned()),
+        };
+
+        let client = HttpClient::new(&fast_retry_settings(0)).unwrap();
+        let result = client
+            .get_if_changed(&format!("{}/links", server.url()), &validators)
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(result.status(), 304);
+    }
+
+    #[test]
+    fn test_validators_from_response() {
+        let mut server = Server::new();
+        server
+            .mock("GET", "/links")
+            .with_header("etag", "\"abc\"")
+            .create();
+        server.mock("GET", "/plain").create();
+
+        let client = HttpClient::new(&fast_retry_settings(0)).unwrap();
+        let with_etag = client.get(&format!("{}/links", server.url())).unwrap();
+        let plain = client.get(&format!("{}/plain", server.url())).unwrap();
+
+        assert_eq!(
+            Validators::from_response(&with_etag),
+            Some(Validators {
+                etag: Some("\"abc\"".to_owned()),
+                last_modified: None,
+            })
+        );
+        assert_eq!(Validators::from_response(&plain), None);
+    }
+
+    #[test]
+    fn test_new_missing_ca_cert() {
+        let result = HttpClient::new(&HttpSettings {
+            ca_certs: vec!["/non/existent/ca.pem".to_owned()],
+            ..HttpSettings::default()
+        });
+
+        assert_eq!(result.err().unwrap().kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_new_invalid_proxy() {
+        let result = HttpClient::new(&HttpSettings {
+            proxy: Some("not a proxy url".to_owned()),
+            ..HttpSettings::default()
+        });
+
+        assert_eq!(
+            result.err().unwrap().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Keeps Minecraft Bedrock Edition dedicated servers up to date.
+//!
+//! The `bedrock-up` command is a thin wrapper around this crate. An update is
+//! split into steps that can be driven one at a time:
+//!
+//! 1. fetch a [`ReleaseIndex`] and resolve the [`Release`] for a
+//!    [`DownloadType`], both done by [`ReleaseIndex::fetch_first`],
+//! 2. download it with [`updater::fetch_update_zip`],
+//! 3. work out what changes with [`updater::plan_update`],
+//! 4. and write them with [`updater::apply_update`], optionally after taking an
+//!    [`updater::snapshot`] and confirming the server still starts with
+//!    [`health::check_started`].
+//!
+//! ```no_run
+//! use bedrock_up::http::{HttpClient, HttpSettings};
+//! use bedrock_up::{DownloadType, ReleaseIndex, release, updater};
+//!
+//! # fn main() -> bedrock_up::Result<()> {
+//! let client = HttpClient::new(&HttpSettings::default())?;
+//! let endpoints = release::links_endpoints(&[], None, false);
+//! let (_, release) = ReleaseIndex::fetch_first(&client, &endpoints, &DownloadType::Linux)?;
+//!
+//! let zip_path = updater::fetch_update_zip(&client, &release.download_url)?;
+//! let plan = updater::plan_update("/srv/bedrock", &zip_path, &["server.properties".to_owned()])?;
+//! updater::apply_update(&plan, None)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod backup;
+pub mod clone;
+pub mod compat;
+pub mod config;
+pub mod console;
+pub mod defer;
+pub mod error;
+pub mod fingerprint;
+pub mod health;
+pub mod http;
+pub mod lock;
+pub mod manifest;
+pub mod mirror;
+pub mod nbt;
+pub mod pack;
+pub mod permissions;
+pub mod platform;
+pub mod policy;
+pub mod properties;
+pub mod raknet;
+pub mod release;
+pub mod signing;
+pub mod source;
+pub mod stage;
+pub mod status;
+pub mod supervisor;
+pub mod updater;
+pub mod verify;
+pub mod version;
+pub mod world;
+
+#[cfg(test)]
+mod test_util;
+
+pub use error::{Error, Result};
+pub use release::{DownloadType, LinksEndpoint, Release, ReleaseIndex};
+pub use source::{ReleaseSource, SourceConfig};
+pub use updater::{FileAction, PlannedFile, UpdatePlan};
+pub use version::Version;
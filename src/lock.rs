@@ -0,0 +1,404 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Locks whose owner can't be checked are considered stale after this long.
+const STALE_AFTER: Duration = Duration::from_secs(2 * 60 * 60);
+/// Lock files without a readable owner are considered stale after this long.
+const UNREADABLE_STALE_AFTER: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An advisory lock held by owning a lock file. The file records the owner's
+/// pid, when the lock was taken and, on Linux, when the owner started, and is
+/// removed when the lock is dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another live process holds the lock.
+    InProgress {
+        path: PathBuf,
+        pid: Option<u32>,
+    },
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::InProgress {
+                path,
+                pid: Some(pid),
+            } => write!(
+                f,
+                "another update is in progress (pid {}, lock file {})",
+                pid,
+                path.display()
+            ),
+            LockError::InProgress { path, pid: None } => write!(
+                f,
+                "another update is in progress (lock file {})",
+                path.display()
+            ),
+            LockError::Io(e) => write!(f, "failed to take lock: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+impl From<std::io::Error> for LockError {
+    fn from(e: std::io::Error) -> Self {
+        LockError::Io(e)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The lock file guarding updates of a server directory.
+pub fn server_lock_path(server_path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(server_path).to_string()).join(".bedrock-up.lock")
+}
+
+/// The lock file guarding writes to a cache file.
+pub fn cache_lock_path(cache_path: &str) -> PathBuf {
+    let mut path = shellexpand::tilde(cache_path).to_string();
+    path.push_str(".lock");
+    PathBuf::from(path)
+}
+
+/// Takes the lock at `path`, waiting up to `wait` for a live owner to release
+/// it. Locks left behind by a dead process are removed and taken over.
+pub fn acquire(path: &Path, wait: Duration) -> Result<FileLock, LockError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let started = std::time::Instant::now();
+    loop {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+        {
+            Ok(mut file) => {
+                let pid = std::process::id();
+                writeln!(file, "{}\n{}", pid, now_secs())?;
+                if let Some(started) = process_start_time(pid) {
+                    writeln!(file, "{}", started)?;
+                }
+                return Ok(FileLock {
+                    path: path.to_path_buf(),
+                });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let owner = read_lock_owner(path);
+        if lock_is_stale(path, owner.as_ref()) && take_over_stale(path)? {
+            continue;
+        }
+
+        if started.elapsed() >= wait {
+            return Err(LockError::InProgress {
+                path: path.to_path_buf(),
+                pid: owner.map(|owner| owner.pid),
+            });
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Removes the lock file at `path` if it is still stale, holding a lock on a
+/// companion file meanwhile. Without it, two runs that both found the same
+/// stale lock could each remove it, the second removing the lock the first
+/// had just taken. Returns false when another run is taking the lock over.
+fn take_over_stale(path: &Path) -> Result<bool, LockError> {
+    let guard = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(takeover_path(path))?;
+    match guard.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => return Ok(false),
+        Err(std::fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+    // Another run may have replaced it before we got here.
+    if lock_is_stale(path, read_lock_owner(path).as_ref()) {
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+/// The file stale takeovers of the lock at `path` are serialized on. It is
+/// left in place, removing it would let a run lock a file no one else opens.
+fn takeover_path(path: &Path) -> PathBuf {
+    let mut takeover = path.as_os_str().to_owned();
+    takeover.push(".takeover");
+    PathBuf::from(takeover)
+}
+
+fn lock_is_stale(path: &Path, owner: Option<&LockOwner>) -> bool {
+    match owner {
+        Some(owner) => is_stale(owner),
+        // The owner may still be writing the file, give it a moment.
+        None => lock_file_age(path) > UNREADABLE_STALE_AFTER,
+    }
+}
+
+fn lock_file_age(path: &Path) -> Duration {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .unwrap_or_default()
+}
+
+/// What a lock file records about the process holding it.
+struct LockOwner {
+    pid: u32,
+    /// When the lock was taken, in seconds since the epoch.
+    created: u64,
+    /// The owner's [`process_start_time`], missing in lock files written
+    /// where it can't be read.
+    started: Option<u64>,
+}
+
+fn read_lock_owner(path: &Path) -> Option<LockOwner> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut lines = content.lines();
+    let pid = lines.next()?.trim().parse().ok()?;
+    let created = lines.next()?.trim().parse().ok()?;
+    let started = lines.next().and_then(|line| line.trim().parse().ok());
+    Some(LockOwner {
+        pid,
+        created,
+        started,
+    })
+}
+
+fn is_stale(owner: &LockOwner) -> bool {
+    match process_is_alive(owner.pid) {
+        // After a reboot the pid may belong to another process, which started
+        // at a different time than the owner.
+        Some(true) => owner
+            .started
+            .zip(process_start_time(owner.pid))
+            .is_some_and(|(recorded, current)| recorded != current),
+        Some(false) => true,
+        None => now_secs().saturating_sub(owner.created) > STALE_AFTER.as_secs(),
+    }
+}
+
+/// Whether `pid` is a running process, or `None` when that can't be checked.
+fn process_is_alive(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        Some(Path::new(&format!("/proc/{}", pid)).exists())
+    } else {
+        None
+    }
+}
+
+/// When `pid` started, in clock ticks since boot, from `/proc/<pid>/stat`.
+/// `None` where that can't be read.
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name in parentheses may contain spaces, the start time is
+    // the 22nd field and the 20th after it.
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_acquire_and_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("server.lock");
+
+        let lock = acquire(&lock_path, Duration::ZERO).unwrap();
+        assert!(lock_path.exists());
+
+        let owner = read_lock_owner(&lock_path).unwrap();
+        assert_eq!(owner.pid, std::process::id());
+
+        drop(lock);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn test_acquire_while_held() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("server.lock");
+
+        let _lock = acquire(&lock_path, Duration::ZERO).unwrap();
+        let result = acquire(&lock_path, Duration::from_millis(100));
+
+        match result {
+            Err(LockError::InProgress { pid, .. }) => assert_eq!(pid, Some(std::process::id())),
+            other => panic!("expected InProgress, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_acquire_waits_for_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("cache.lock");
+
+        let lock = acquire(&lock_path, Duration::ZERO).unwrap();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            drop(lock);
+        });
+
+        let result = acquire(&lock_path, Duration::from_secs(10));
+        handle.join().unwrap();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_acquire_replaces_stale_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("server.lock");
+
+        // A pid that can't be running, created long enough ago to be stale
+        // on platforms where the pid can't be checked.
+        std::fs::write(&lock_path, format!("{}\n{}\n", u32::MAX, 0)).unwrap();
+
+        let lock = acquire(&lock_path, Duration::ZERO).unwrap();
+
+        let owner = read_lock_owner(&lock_path).unwrap();
+        assert_eq!(owner.pid, std::process::id());
+        drop(lock);
+    }
+
+    #[test]
+    fn test_racing_takeovers_of_stale_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("server.lock");
+
+        // Many rounds, since the runs only collide when one removes the stale
+        // lock between another's read and removal.
+        for _ in 0..500 {
+            std::fs::write(&lock_path, format!("{}\n{}\n", u32::MAX, 0)).unwrap();
+            let barrier = std::sync::Barrier::new(32);
+            let locks: Vec<_> = std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..32)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            barrier.wait();
+                            acquire(&lock_path, Duration::ZERO)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+
+            let held = locks.iter().filter(|lock| lock.is_ok()).count();
+            assert_eq!(held, 1, "{:?}", locks);
+            assert!(
+                locks
+                    .iter()
+                    .all(|lock| matches!(lock, Ok(_) | Err(LockError::InProgress { .. })))
+            );
+        }
+    }
+
+    #[test]
+    fn test_acquire_waits_for_takeover_in_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("server.lock");
+        std::fs::write(&lock_path, format!("{}\n{}\n", u32::MAX, 0)).unwrap();
+
+        let takeover = std::fs::File::create(takeover_path(&lock_path)).unwrap();
+        takeover.lock().unwrap();
+        assert!(matches!(
+            acquire(&lock_path, Duration::ZERO),
+            Err(LockError::InProgress {
+                pid: Some(u32::MAX),
+                ..
+            })
+        ));
+        drop(takeover);
+        assert!(acquire(&lock_path, Duration::ZERO).is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_acquire_replaces_lock_of_reused_pid() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("server.lock");
+        let pid = std::process::id();
+        let started = process_start_time(pid).unwrap();
+
+        // Our own pid, recorded by a process that started at another time.
+        std::fs::write(
+            &lock_path,
+            format!("{}\n{}\n{}\n", pid, now_secs(), started + 1),
+        )
+        .unwrap();
+        let lock = acquire(&lock_path, Duration::ZERO).unwrap();
+        assert_eq!(read_lock_owner(&lock_path).unwrap().started, Some(started));
+        drop(lock);
+
+        std::fs::write(
+            &lock_path,
+            format!("{}\n{}\n{}\n", pid, now_secs(), started),
+        )
+        .unwrap();
+        assert!(matches!(
+            acquire(&lock_path, Duration::ZERO),
+            Err(LockError::InProgress { .. })
+        ));
+    }
+
+    #[test]
+    fn test_acquire_creates_parent_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("nested").join("server.lock");
+
+        let lock = acquire(&lock_path, Duration::ZERO);
+
+        assert!(lock.is_ok());
+        assert!(lock_path.exists());
+    }
+
+    #[test]
+    fn test_lock_paths() {
+        assert_eq!(
+            server_lock_path("/srv/minecraft"),
+            PathBuf::from("/srv/minecraft/.bedrock-up.lock")
+        );
+        assert_eq!(
+            cache_lock_path("/srv/cache/links.json"),
+            PathBuf::from("/srv/cache/links.json.lock")
+        );
+    }
+}
@@ -1,17 +1,50 @@
 mod args;
-use args::UpdateArgs;
-mod updater;
-use clap::{CommandFactory, Parser};
-use updater::update;
+use args::{Cli, Command, MirrorCommand, PackCommand};
+mod commands;
+use bedrock_up::config;
+use clap::Parser;
 
 fn main() {
-    let args = UpdateArgs::try_parse();
-    match args {
-        Ok(args) => {
-            update(args);
-        }
-        Err(_) => {
-            UpdateArgs::command().print_help().unwrap();
+    let cli = Cli::parse();
+    let mut config = match config::load_config(&cli.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to load config: {}", e);
+            std::process::exit(1);
         }
+    };
+    config.http = cli.http.merged_with(&config.http);
+
+    let result = match cli.command {
+        Some(Command::Update(args)) => commands::update(args, config),
+        Some(Command::Stage(args)) => commands::stage(args, config),
+        Some(Command::Migrate(args)) => commands::migrate(args, config),
+        Some(Command::Preview(args)) => commands::preview(args, config),
+        Some(Command::Apply(args)) => commands::apply(args, config),
+        Some(Command::Verify(args)) => commands::verify(args, config),
+        Some(Command::Repair(args)) => commands::repair(args, config),
+        Some(Command::Backup(args)) => commands::backup(args, config),
+        Some(Command::Restore(args)) => commands::restore(args, config),
+        Some(Command::Run(args)) => commands::run(args, config),
+        Some(Command::Attach(args)) => commands::attach(args),
+        Some(Command::Status(args)) => commands::status(args, config),
+        Some(Command::Mirror(MirrorCommand::Sync(args))) => commands::mirror_sync(args, config),
+        Some(Command::Mirror(MirrorCommand::Serve(args))) => commands::mirror_serve(args, config),
+        Some(Command::Mirror(MirrorCommand::Keygen(args))) => commands::mirror_keygen(args),
+        Some(Command::Mirror(MirrorCommand::Sign(args))) => commands::mirror_sign(args),
+        Some(Command::Pack(PackCommand::Install(args))) => commands::pack_install(args, config),
+        Some(Command::Pack(PackCommand::Upgrade(args))) => commands::pack_upgrade(args, config),
+        Some(Command::Pack(PackCommand::List(args))) => commands::pack_list(args),
+        Some(Command::Pack(PackCommand::Remove(args))) => commands::pack_remove(args),
+        Some(Command::Pack(PackCommand::Check(args))) => commands::pack_check(args),
+        None => match cli.update {
+            Some(args) => commands::update(args, config),
+            None => Ok(()),
+        },
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
 }
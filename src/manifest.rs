@@ -0,0 +1,75 @@
+use crate::release::Release;
+use crate::verify::FileHashes;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Written to the server directory after each successful update.
+pub const MANIFEST_FILE: &str = ".bedrock-up.json";
+
+/// What was installed into a server directory, and when.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: Option<String>,
+    pub download_type: String,
+    pub download_url: String,
+    pub installed_at: DateTime<Utc>,
+    /// Hashes of the release's files as installed, for `verify`.
+    #[serde(default, skip_serializing_if = "FileHashes::is_empty")]
+    pub files: FileHashes,
+}
+
+impl Manifest {
+    pub fn new(release: &Release, installed_at: DateTime<Utc>, files: FileHashes) -> Manifest {
+        Manifest {
+            version: release.version().map(str::to_owned),
+            download_type: release.download_type.to_string(),
+            download_url: release.download_url.clone(),
+            installed_at,
+            files,
+        }
+    }
+}
+
+/// Reads the server's manifest, or `None` when no update was recorded yet.
+pub fn read_manifest(server_path: &Path) -> std::io::Result<Option<Manifest>> {
+    match std::fs::read(server_path.join(MANIFEST_FILE)) {
+        Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn write_manifest(server_path: &Path, manifest: &Manifest) -> std::io::Result<()> {
+    // Renamed into place so readers never see a partially written file.
+    let temp_path = server_path.join(format!("{}.tmp", MANIFEST_FILE));
+    let file = std::fs::File::create(&temp_path)?;
+    serde_json::to_writer_pretty(file, manifest)?;
+    std::fs::rename(temp_path, server_path.join(MANIFEST_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::release::DownloadType;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(read_manifest(temp_dir.path()).unwrap(), None);
+
+        let release = Release {
+            download_type: DownloadType::Linux,
+            download_url: "https://example.com/bin-linux/bedrock-server-1.21.92.1.zip".to_owned(),
+        };
+        let files = FileHashes::from([("bedrock_server".to_owned(), "ab12".to_owned())]);
+        let manifest = Manifest::new(&release, Utc::now(), files);
+        write_manifest(temp_dir.path(), &manifest).unwrap();
+
+        let result = read_manifest(temp_dir.path()).unwrap().unwrap();
+        assert_eq!(result, manifest);
+        assert_eq!(result.version.as_deref(), Some("1.21.92.1"));
+        assert_eq!(result.download_type, "serverBedrockLinux");
+    }
+}
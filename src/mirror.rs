@@ -0,0 +1,480 @@
+use crate::error::{Error, Result};
+use crate::fingerprint::{KNOWN_VERSIONS_FILE, KnownVersions};
+use crate::http::HttpClient;
+use crate::release::{DownloadType, LinksEndpoint, ReleaseIndex};
+use crate::signing;
+use crate::source::MIRROR_INDEX_PATH;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+use tiny_http::{Header, Method, Request, Response, ResponseBox, Server};
+
+pub const DEFAULT_SYNC_INTERVAL_SECS: u64 = 3600;
+/// How many requests the mirror answers at once, so hosts downloading a zip
+/// don't hold up the others.
+const SERVE_WORKERS: usize = 8;
+
+/// What a sync of the mirror directory did.
+#[derive(Debug)]
+pub struct Synced {
+    /// The upstream index the mirror was synced from.
+    pub upstream: String,
+    /// Zips downloaded by this sync, relative to the mirror directory.
+    pub downloaded: Vec<String>,
+    /// Whether the served links index changed.
+    pub changed: bool,
+}
+
+/// Brings the mirror directory in line with the first of `endpoints` that
+/// answers: every listed zip is downloaded once into a subdirectory named
+/// after its download type, and `links.json` is rewritten to point at them
+/// with relative urls. Zips of older releases are kept so hosts pinned to
+/// them can still install.
+pub fn sync(client: &HttpClient, endpoints: &[LinksEndpoint], dir: &Path) -> Result<Synced> {
+    let upstream = fetch_upstream(client, endpoints)?;
+
+    let mut links = Vec::new();
+    let mut downloaded = Vec::new();
+    let mut zips = Vec::new();
+    for download_type in DownloadType::value_variants() {
+        let Ok(release) = upstream.resolve(download_type) else {
+            continue;
+        };
+        // Only server zips are mirrored, the index also lists the `.jar`.
+        let Some(file_name) = zip_file_name(&release.download_url) else {
+            continue;
+        };
+        let relative = format!("{}/{}", download_type, file_name);
+        let path = dir.join(&relative);
+        if !path.exists() {
+            download(client, &release.download_url, &path)?;
+            downloaded.push(relative.clone());
+        }
+        links.push(serde_json::json!({
+            "downloadType": download_type.to_string(),
+            "downloadUrl": relative,
+        }));
+        zips.push((relative, release));
+    }
+
+    // Grows with every release the mirror downloads, so hosts can identify
+    // servers that are a few releases behind.
+    let known_path = dir.join(KNOWN_VERSIONS_FILE);
+    let mut known = KnownVersions::read(&known_path)?;
+    let rebuild = known.is_empty();
+    let mut known_changed = false;
+    for (relative, release) in &zips {
+        if let Some(version) = release.version()
+            && (rebuild || downloaded.contains(relative))
+        {
+            known_changed |= known.record_zip(&dir.join(relative), version)?;
+        }
+    }
+    if known_changed {
+        known.write(&known_path)?;
+    }
+
+    let index = serde_json::json!({ "result": { "links": links } });
+    let index_path = dir.join(MIRROR_INDEX_PATH);
+    let current = std::fs::read(&index_path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<serde_json::Value>(&content).ok());
+    // Left alone when nothing changed, so clients holding its validators get
+    // 304 Not Modified.
+    let changed = current.as_ref() != Some(&index);
+    if changed {
+        let temp_path = dir.join(format!("{}.tmp", MIRROR_INDEX_PATH));
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(&index)?)?;
+        std::fs::rename(temp_path, index_path)?;
+    }
+
+    Ok(Synced {
+        upstream: upstream.source,
+        downloaded,
+        changed,
+    })
+}
+
+fn fetch_upstream(client: &HttpClient, endpoints: &[LinksEndpoint]) -> Result<ReleaseIndex> {
+    let mut failures = Vec::new();
+    for endpoint in endpoints {
+        match ReleaseIndex::fetch(client, endpoint) {
+            Ok(index) => return Ok(index),
+            Err(e) => failures.push(format!("{}: {}", endpoint.url(), e)),
+        }
+    }
+    Err(Error::Other(format!(
+        "no links endpoint answered ({})",
+        failures.join("; ")
+    )))
+}
+
+/// The zip's file name, when the url ends in one.
+fn zip_file_name(download_url: &str) -> Option<&str> {
+    let name = download_url.rsplit('/').next()?;
+    (name.ends_with(".zip") && name.len() > ".zip".len()).then_some(name)
+}
+
+/// Downloads next to `path` and renames into place, so a zip that exists is
+/// always complete.
+fn download(client: &HttpClient, url: &str, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut resp = client.get(url)?.error_for_status()?;
+    let temp_path = path.with_extension("zip.part");
+    let mut file = std::fs::File::create(&temp_path)?;
+    resp.copy_to(&mut file)?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+/// Serves a mirror directory: `links.json` and the zips next to it, nothing
+/// else. Responses carry `ETag` and `Last-Modified` so clients can check the
+/// index with a conditional request.
+pub struct MirrorServer {
+    server: Server,
+    dir: PathBuf,
+}
+
+impl MirrorServer {
+    pub fn bind(addr: &str, dir: &Path) -> Result<MirrorServer> {
+        let server = Server::http(addr)
+            .map_err(|e| Error::Other(format!("cannot listen on {}: {}", addr, e)))?;
+        Ok(MirrorServer {
+            server,
+            dir: dir.to_owned(),
+        })
+    }
+
+    /// The address the server listens on, with the port it was given when
+    /// bound to port 0.
+    pub fn addr(&self) -> String {
+        self.server.server_addr().to_string()
+    }
+
+    /// Answers requests on [`SERVE_WORKERS`] threads until the process exits.
+    pub fn serve(&self) {
+        std::thread::scope(|scope| {
+            for _ in 0..SERVE_WORKERS {
+                scope.spawn(|| {
+                    for request in self.server.incoming_requests() {
+                        let response = respond(&self.dir, &request);
+                        // The client hung up, nothing to do about it.
+                        let _ = request.respond(response);
+                    }
+                });
+            }
+        });
+    }
+}
+
+fn respond(dir: &Path, request: &Request) -> ResponseBox {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return Response::empty(405).boxed();
+    }
+    let Some(path) = served_path(dir, request.url()) else {
+        return Response::empty(404).boxed();
+    };
+    let (file, metadata) = match std::fs::File::open(&path).and_then(|file| {
+        let metadata = file.metadata()?;
+        Ok((file, metadata))
+    }) {
+        Ok(opened) if opened.1.is_file() => opened,
+        _ => return Response::empty(404).boxed(),
+    };
+
+    let modified: DateTime<Utc> = match metadata.modified() {
+        Ok(modified) => modified.into(),
+        Err(_) => Utc::now(),
+    };
+    let etag = format!("\"{:x}-{:x}\"", metadata.len(), modified.timestamp());
+    let last_modified = modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let request_header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+    let not_modified = match request_header("If-None-Match") {
+        Some(if_none_match) => if_none_match == etag,
+        None => request_header("If-Modified-Since") == Some(last_modified.as_str()),
+    };
+
+    let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => "application/json",
+        Some("zip") => "application/zip",
+        _ => "text/plain",
+    };
+    let headers = [
+        header("ETag", &etag),
+        header("Last-Modified", &last_modified),
+    ];
+    if not_modified {
+        let mut response = Response::empty(304);
+        for header in headers {
+            response.add_header(header);
+        }
+        return response.boxed();
+    }
+    let mut response = Response::from_file(file).with_header(header("Content-Type", content_type));
+    for header in headers {
+        response.add_header(header);
+    }
+    response.boxed()
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes())
+        .expect("header names and values are ASCII")
+}
+
+/// Maps a request url onto the mirror directory. Only `links.json`, the
+/// signed release hashes, the known-version index and
+/// `<download type>/<name>.zip` are served, so nothing outside the mirror or
+/// half downloaded is reachable.
+fn served_path(dir: &Path, url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next()?.strip_prefix('/')?;
+    let segments: Vec<&str> = path.split('/').collect();
+    match segments.as_slice() {
+        [file]
+            if [
+                MIRROR_INDEX_PATH,
+                signing::RELEASES_FILE,
+                signing::SIGNATURE_FILE,
+                KNOWN_VERSIONS_FILE,
+            ]
+            .contains(file) =>
+        {
+            Some(dir.join(file))
+        }
+        [download_type, file_name]
+            if DownloadType::from_links_name(download_type).is_some()
+                && zip_file_name(file_name).is_some()
+                && !file_name.starts_with('.') =>
+        {
+            Some(dir.join(download_type).join(file_name))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ReleaseSource;
+    use crate::source::MirrorSource;
+    use crate::test_util::{create_test_zip, test_client};
+    use mockito::Server;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sync_downloads_each_release_once() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let zip = |binary: &str, content: &str| {
+            std::fs::read(create_test_zip(temp_dir.path(), &[(binary, content)])).unwrap()
+        };
+        let linux_zip = zip("bedrock_server", "linux");
+        let windows_zip = zip("bedrock_server.exe", "windows");
+        let mut server = Server::new();
+        let links = serde_json::json!({ "result": { "links": [
+            {
+                "downloadType": "serverBedrockLinux",
+                "downloadUrl": format!("{}/bin-linux/bedrock-server-1.21.92.1.zip", server.url()),
+            },
+            {
+                "downloadType": "serverBedrockWindows",
+                "downloadUrl": format!("{}/bin-win/bedrock-server-1.21.92.1.zip", server.url()),
+            },
+            {
+                "downloadType": "serverJar",
+                "downloadUrl": format!("{}/bin-jar/bedrock-server.jar", server.url()),
+            },
+        ] } });
+        let links_mock = server
+            .mock("GET", "/links")
+            .with_body(links.to_string())
+            .expect(2)
+            .create();
+        let linux_mock = server
+            .mock("GET", "/bin-linux/bedrock-server-1.21.92.1.zip")
+            .with_body(&linux_zip)
+            .expect(1)
+            .create();
+        let windows_mock = server
+            .mock("GET", "/bin-win/bedrock-server-1.21.92.1.zip")
+            .with_body(&windows_zip)
+            .expect(1)
+            .create();
+        let mirror_dir = temp_dir.path().join("mirror");
+        std::fs::create_dir(&mirror_dir).unwrap();
+        let endpoints = vec![LinksEndpoint::Api(format!("{}/links", server.url()))];
+
+        let first = sync(&test_client(), &endpoints, &mirror_dir).unwrap();
+        let second = sync(&test_client(), &endpoints, &mirror_dir).unwrap();
+
+        links_mock.assert();
+        linux_mock.assert();
+        windows_mock.assert();
+        assert_eq!(
+            first.downloaded,
+            vec![
+                "serverBedrockWindows/bedrock-server-1.21.92.1.zip",
+                "serverBedrockLinux/bedrock-server-1.21.92.1.zip",
+            ]
+        );
+        assert!(first.changed);
+        let index = std::fs::read_to_string(mirror_dir.join(MIRROR_INDEX_PATH)).unwrap();
+        assert!(!index.contains("serverJar"));
+        assert!(second.downloaded.is_empty());
+        assert!(!second.changed);
+        assert_eq!(
+            std::fs::read(mirror_dir.join("serverBedrockLinux/bedrock-server-1.21.92.1.zip"))
+                .unwrap(),
+            linux_zip
+        );
+
+        let known = KnownVersions::read(&mirror_dir.join(KNOWN_VERSIONS_FILE)).unwrap();
+        let server_path = temp_dir.path().join("server");
+        std::fs::create_dir(&server_path).unwrap();
+        std::fs::write(server_path.join("bedrock_server.exe"), "windows").unwrap();
+        assert_eq!(
+            known.identify(&server_path).unwrap().as_deref(),
+            Some("1.21.92.1")
+        );
+    }
+
+    #[test]
+    fn test_serve_answers_during_a_slow_download() {
+        use std::io::{Read, Write};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("serverBedrockLinux")).unwrap();
+        std::fs::write(
+            temp_dir
+                .path()
+                .join("serverBedrockLinux/bedrock-server-1.21.92.1.zip"),
+            vec![0; 32 * 1024 * 1024],
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join(MIRROR_INDEX_PATH), "{}").unwrap();
+        let mirror = MirrorServer::bind("127.0.0.1:0", temp_dir.path()).unwrap();
+        let addr = mirror.addr();
+        std::thread::spawn(move || mirror.serve());
+
+        // Requests the zip and never reads it, so its response blocks.
+        let mut slow = std::net::TcpStream::connect(&addr).unwrap();
+        slow.write_all(
+            b"GET /serverBedrockLinux/bedrock-server-1.21.92.1.zip HTTP/1.1\r\nHost: mirror\r\n\r\n",
+        )
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut other = std::net::TcpStream::connect(&addr).unwrap();
+        other
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        other
+            .write_all(b"GET /links.json HTTP/1.1\r\nHost: mirror\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        other.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        drop(slow);
+    }
+
+    #[test]
+    fn test_served_path() {
+        let dir = Path::new("/srv/mirror");
+
+        assert_eq!(
+            served_path(dir, "/links.json?x=1"),
+            Some(dir.join("links.json"))
+        );
+        assert_eq!(
+            served_path(dir, "/serverBedrockLinux/bedrock-server-1.21.92.1.zip"),
+            Some(dir.join("serverBedrockLinux/bedrock-server-1.21.92.1.zip"))
+        );
+        assert_eq!(
+            served_path(dir, "/serverBedrockLinux/../../etc/passwd"),
+            None
+        );
+        assert_eq!(served_path(dir, "/serverBedrockLinux/..zip"), None);
+        assert_eq!(
+            served_path(dir, "/other/bedrock-server-1.21.92.1.zip"),
+            None
+        );
+        assert_eq!(served_path(dir, "/links.json.tmp"), None);
+    }
+
+    #[test]
+    fn test_serve_to_mirror_source() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("serverBedrockLinux")).unwrap();
+        std::fs::write(
+            temp_dir
+                .path()
+                .join("serverBedrockLinux/bedrock-server-1.21.92.1.zip"),
+            "linux",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join(MIRROR_INDEX_PATH),
+            serde_json::json!({ "result": { "links": [{
+                "downloadType": "serverBedrockLinux",
+                "downloadUrl": "serverBedrockLinux/bedrock-server-1.21.92.1.zip",
+            }] } })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join(KNOWN_VERSIONS_FILE),
+            r#"{ "0000": "1.21.92.1" }"#,
+        )
+        .unwrap();
+        let key = signing::generate_key();
+        signing::sign_dir(temp_dir.path(), &key).unwrap();
+        let mirror = MirrorServer::bind("127.0.0.1:0", temp_dir.path()).unwrap();
+        let url = format!("http://{}", mirror.addr());
+        std::thread::spawn(move || mirror.serve());
+
+        let source = MirrorSource::new(test_client(), &url)
+            .unwrap()
+            .with_public_key(key.verifying_key());
+        let (index, release) = source.latest(&DownloadType::Linux).unwrap();
+        let zip_path = source.fetch(&release).unwrap();
+        let unchanged = source
+            .latest_if_changed(&DownloadType::Linux, &index)
+            .unwrap();
+
+        assert_eq!(
+            release.download_url,
+            format!("{}/serverBedrockLinux/bedrock-server-1.21.92.1.zip", url)
+        );
+        assert_eq!(std::fs::read_to_string(zip_path).unwrap(), "linux");
+        assert!(unchanged.is_none());
+        assert!(
+            source
+                .known_versions(&KnownVersions::default())
+                .unwrap()
+                .is_empty()
+        );
+        let unsigned = MirrorSource::new(test_client(), &url).unwrap();
+        assert!(
+            !unsigned
+                .known_versions(&KnownVersions::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        std::fs::write(
+            temp_dir
+                .path()
+                .join("serverBedrockLinux/bedrock-server-1.21.92.1.zip"),
+            "tampered",
+        )
+        .unwrap();
+        assert!(matches!(source.fetch(&release), Err(Error::Signature(_))));
+    }
+}
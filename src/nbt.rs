@@ -0,0 +1,180 @@
+use std::fmt;
+
+/// How deeply lists and compounds may nest before the data is rejected,
+/// so a malformed file can't exhaust the stack.
+const MAX_DEPTH: usize = 512;
+
+/// A tag of the little-endian NBT Bedrock writes `level.dat` in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// The tag named `name` in a compound.
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(tags) => tags
+                .iter()
+                .find(|(tag_name, _)| tag_name == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NbtError(String);
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid NBT: {}", self.0)
+    }
+}
+
+impl std::error::Error for NbtError {}
+
+/// Reads the named root tag at the start of `bytes`.
+pub fn read(bytes: &[u8]) -> Result<(String, Tag), NbtError> {
+    let mut reader = Reader { bytes };
+    let tag_type = reader.u8()?;
+    let name = reader.string()?;
+    let tag = reader.payload(tag_type, 0)?;
+    Ok((name, tag))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NbtError> {
+        if len > self.bytes.len() {
+            return Err(NbtError("unexpected end of data".to_owned()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], NbtError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, NbtError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn len(&mut self) -> Result<usize, NbtError> {
+        let len = i32::from_le_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| NbtError(format!("negative length {}", len)))
+    }
+
+    fn string(&mut self) -> Result<String, NbtError> {
+        let len = u16::from_le_bytes(self.array()?);
+        let bytes = self.take(len.into())?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| NbtError(e.to_string()))
+    }
+
+    fn payload(&mut self, tag_type: u8, depth: usize) -> Result<Tag, NbtError> {
+        if depth > MAX_DEPTH {
+            return Err(NbtError("nested too deeply".to_owned()));
+        }
+        Ok(match tag_type {
+            1 => Tag::Byte(i8::from_le_bytes(self.array()?)),
+            2 => Tag::Short(i16::from_le_bytes(self.array()?)),
+            3 => Tag::Int(i32::from_le_bytes(self.array()?)),
+            4 => Tag::Long(i64::from_le_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_le_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_le_bytes(self.array()?)),
+            7 => {
+                let len = self.len()?;
+                Tag::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element_type = self.u8()?;
+                let len = self.len()?;
+                let mut tags = Vec::new();
+                for _ in 0..len {
+                    tags.push(self.payload(element_type, depth + 1)?);
+                }
+                Tag::List(tags)
+            }
+            10 => {
+                let mut tags = Vec::new();
+                loop {
+                    let tag_type = self.u8()?;
+                    if tag_type == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    tags.push((name, self.payload(tag_type, depth + 1)?));
+                }
+                Tag::Compound(tags)
+            }
+            11 => {
+                let len = self.len()?;
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(i32::from_le_bytes(self.array()?));
+                }
+                Tag::IntArray(values)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(i64::from_le_bytes(self.array()?));
+                }
+                Tag::LongArray(values)
+            }
+            other => return Err(NbtError(format!("unknown tag type {}", other))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let mut bytes = vec![10, 0, 0];
+        bytes.extend([8, 9, 0]);
+        bytes.extend(b"LevelName");
+        bytes.extend([7, 0]);
+        bytes.extend(b"Bedrock");
+        bytes.extend([9, 3, 0]);
+        bytes.extend(b"ver");
+        bytes.extend([3, 2, 0, 0, 0, 1, 0, 0, 0, 21, 0, 0, 0]);
+        bytes.extend([1, 1, 0, b'b', 0xff]);
+        bytes.push(0);
+
+        let (name, tag) = read(&bytes).unwrap();
+
+        assert_eq!(name, "");
+        assert_eq!(
+            tag.get("LevelName"),
+            Some(&Tag::String("Bedrock".to_owned()))
+        );
+        assert_eq!(
+            tag.get("ver"),
+            Some(&Tag::List(vec![Tag::Int(1), Tag::Int(21)]))
+        );
+        assert_eq!(tag.get("b"), Some(&Tag::Byte(-1)));
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read(&[9, 0, 0, 3, 0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+}
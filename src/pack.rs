@@ -0,0 +1,733 @@
+use crate::error::{Error, Result};
+use crate::permissions::{self, Owner};
+use crate::version::Version;
+use crate::world::WORLDS_DIR;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// Where the packs bedrock-up installed are recorded, in the server directory.
+pub const PACKS_FILE: &str = ".bedrock-up-packs.json";
+/// The file at the root of every pack that describes it.
+pub const PACK_MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PackKind {
+    Behavior,
+    Resource,
+}
+
+impl PackKind {
+    /// The server directory packs of this kind are installed in.
+    pub fn dir(&self) -> &'static str {
+        match self {
+            PackKind::Behavior => "behavior_packs",
+            PackKind::Resource => "resource_packs",
+        }
+    }
+
+    /// The file in a world's directory listing the packs of this kind it uses.
+    pub fn world_file(&self) -> &'static str {
+        match self {
+            PackKind::Behavior => "world_behavior_packs.json",
+            PackKind::Resource => "world_resource_packs.json",
+        }
+    }
+}
+
+impl fmt::Display for PackKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackKind::Behavior => write!(f, "behavior"),
+            PackKind::Resource => write!(f, "resource"),
+        }
+    }
+}
+
+/// Another pack, or a scripting module, a pack needs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dependency {
+    Pack {
+        uuid: String,
+        version: Option<Version>,
+    },
+    /// A scripting API module such as `@minecraft/server`, whose version may
+    /// carry a `-beta` suffix.
+    Module { name: String, version: String },
+}
+
+/// What bedrock-up reads from a pack's `manifest.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackManifest {
+    pub name: String,
+    pub uuid: String,
+    pub version: Version,
+    pub kind: PackKind,
+    /// The oldest game version the pack is made for.
+    pub min_engine_version: Option<Version>,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct RawManifest {
+    header: RawHeader,
+    #[serde(default)]
+    modules: Vec<RawModule>,
+    #[serde(default)]
+    dependencies: Vec<RawDependency>,
+}
+
+#[derive(Deserialize)]
+struct RawHeader {
+    #[serde(default)]
+    name: String,
+    uuid: String,
+    version: Value,
+    min_engine_version: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct RawModule {
+    #[serde(rename = "type")]
+    module_type: String,
+}
+
+#[derive(Deserialize)]
+struct RawDependency {
+    uuid: Option<String>,
+    module_name: Option<String>,
+    version: Option<Value>,
+}
+
+/// Parses a pack's `manifest.json`. Skin packs and world templates, which a
+/// server doesn't load, are refused.
+pub fn parse_manifest(content: &[u8]) -> Result<PackManifest> {
+    let raw: RawManifest = serde_json::from_slice(content)?;
+    let invalid = |message: String| Error::Other(format!("pack {}: {}", raw.header.uuid, message));
+
+    let version = pack_version(&raw.header.version)
+        .ok_or_else(|| invalid(format!("invalid version {}", raw.header.version)))?;
+    let types: Vec<&str> = raw
+        .modules
+        .iter()
+        .map(|module| module.module_type.as_str())
+        .collect();
+    let kind = if types.contains(&"resources") {
+        PackKind::Resource
+    } else if types
+        .iter()
+        .any(|module_type| matches!(*module_type, "data" | "script" | "client_data"))
+    {
+        PackKind::Behavior
+    } else {
+        return Err(invalid(format!(
+            "modules {:?} are neither a behavior nor a resource pack",
+            types
+        )));
+    };
+    let dependencies = raw
+        .dependencies
+        .iter()
+        .filter_map(
+            |dependency| match (&dependency.uuid, &dependency.module_name) {
+                (Some(uuid), _) => Some(Dependency::Pack {
+                    uuid: uuid.clone(),
+                    version: dependency.version.as_ref().and_then(pack_version),
+                }),
+                (None, Some(name)) => Some(Dependency::Module {
+                    name: name.clone(),
+                    version: match &dependency.version {
+                        Some(Value::String(version)) => version.clone(),
+                        Some(version) => version.to_string(),
+                        None => String::new(),
+                    },
+                }),
+                (None, None) => None,
+            },
+        )
+        .collect();
+
+    Ok(PackManifest {
+        name: raw.header.name.clone(),
+        uuid: raw.header.uuid.clone(),
+        version,
+        kind,
+        min_engine_version: raw
+            .header
+            .min_engine_version
+            .as_ref()
+            .and_then(pack_version),
+        dependencies,
+    })
+}
+
+/// Reads a manifest version, either `[1, 2, 0]` or `"1.2.0"`. A pre-release
+/// suffix like `-beta` is dropped.
+fn pack_version(value: &Value) -> Option<Version> {
+    match value {
+        Value::Array(parts) => parts
+            .iter()
+            .map(Value::as_u64)
+            .collect::<Option<Vec<u64>>>()
+            .map(Version::from),
+        Value::String(version) => version.split(['-', '+']).next()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// A pack read from a `.mcpack` or `.mcaddon`, with its files in memory.
+#[derive(Debug, Clone)]
+pub struct ArchivedPack {
+    pub manifest: PackManifest,
+    /// The directory the pack is installed as, unless that is taken.
+    pub dir_name: String,
+    /// The pack's files, relative to its root.
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// Reads every pack in a `.mcpack` or `.mcaddon`. An add-on holds its packs in
+/// directories of their own, or as `.mcpack` files.
+pub fn read_archive(path: &Path) -> Result<Vec<ArchivedPack>> {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "pack".to_owned());
+    let packs = read_zip(std::fs::File::open(path)?, &stem)?;
+    if packs.is_empty() {
+        return Err(Error::Other(format!(
+            "{} has no {}",
+            path.display(),
+            PACK_MANIFEST
+        )));
+    }
+    Ok(packs)
+}
+
+fn read_zip(reader: impl Read + Seek, stem: &str) -> Result<Vec<ArchivedPack>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut files = Vec::new();
+    let mut packs = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        if file.is_dir() {
+            continue;
+        }
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "mcpack")
+        {
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| stem.to_owned());
+            packs.extend(read_zip(std::io::Cursor::new(content), &stem)?);
+        } else {
+            files.push((path, content));
+        }
+    }
+
+    // A pack's root is where its manifest is. Manifests nested in another
+    // pack belong to that pack.
+    let mut roots: Vec<PathBuf> = files
+        .iter()
+        .filter(|(path, _)| path.file_name().is_some_and(|name| name == PACK_MANIFEST))
+        .filter_map(|(path, _)| path.parent().map(Path::to_path_buf))
+        .collect();
+    roots.sort();
+    roots.dedup();
+    let outer: Vec<PathBuf> = roots
+        .iter()
+        .filter(|root| {
+            !roots
+                .iter()
+                .any(|other| other != *root && root.starts_with(other))
+        })
+        .cloned()
+        .collect();
+
+    for root in outer {
+        let (_, manifest) = files
+            .iter()
+            .find(|(path, _)| *path == root.join(PACK_MANIFEST))
+            .expect("roots come from manifests");
+        let manifest = parse_manifest(manifest)?;
+        let dir_name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| stem.to_owned());
+        let pack_files = files
+            .iter()
+            .filter_map(|(path, content)| {
+                let relative = path.strip_prefix(&root).ok()?;
+                Some((relative.to_path_buf(), content.clone()))
+            })
+            .collect();
+        packs.push(ArchivedPack {
+            manifest,
+            dir_name,
+            files: pack_files,
+        });
+    }
+    Ok(packs)
+}
+
+/// A pack bedrock-up installed into a server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledPack {
+    pub uuid: String,
+    pub name: String,
+    pub version: String,
+    pub kind: PackKind,
+    /// The pack's directory, `/`-separated and relative to the server.
+    pub dir: String,
+    /// The worlds the pack is registered in.
+    pub worlds: Vec<String>,
+}
+
+impl InstalledPack {
+    /// Whether `id` is the pack's UUID or name.
+    pub fn matches(&self, id: &str) -> bool {
+        self.uuid.eq_ignore_ascii_case(id) || self.name == id
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Packs {
+    pub packs: Vec<InstalledPack>,
+}
+
+/// Reads the packs recorded for the server, none when nothing was installed.
+pub fn read_packs(server_path: &Path) -> Result<Packs> {
+    match std::fs::read(server_path.join(PACKS_FILE)) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Packs::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_packs(server_path: &Path, packs: &Packs) -> Result<()> {
+    write_json(&server_path.join(PACKS_FILE), packs)
+}
+
+/// The directories of the installed packs, which updates leave alone.
+pub fn installed_dirs(server_path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(read_packs(server_path)?
+        .packs
+        .into_iter()
+        .map(|pack| PathBuf::from(pack.dir))
+        .collect())
+}
+
+/// The result of [`install`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installation {
+    pub pack: InstalledPack,
+    /// The version the pack replaced, when it was installed before.
+    pub replaced: Option<String>,
+}
+
+/// Installs `pack` into the server and registers it in `world`, or in the
+/// worlds it was registered in before when `world` is `None`. A pack installed
+/// before is replaced in place, unless it is the same or a newer version and
+/// the install isn't forced.
+pub fn install(
+    server_path: &Path,
+    pack: &ArchivedPack,
+    world: Option<&str>,
+    force: bool,
+    owner: Option<&Owner>,
+) -> Result<Installation> {
+    let manifest = &pack.manifest;
+    let mut packs = read_packs(server_path)?;
+    let existing = packs
+        .packs
+        .iter()
+        .position(|installed| installed.uuid.eq_ignore_ascii_case(&manifest.uuid));
+
+    if let Some(index) = existing
+        && !force
+    {
+        let installed = &packs.packs[index];
+        if installed
+            .version
+            .parse::<Version>()
+            .is_ok_and(|version| version >= manifest.version)
+        {
+            return Err(Error::Other(format!(
+                "{} {} is already installed, refusing to replace it with {}",
+                installed.name, installed.version, manifest.version
+            )));
+        }
+    }
+
+    let mut worlds = match (existing, world) {
+        (Some(index), _) => packs.packs[index].worlds.clone(),
+        (None, Some(_)) => Vec::new(),
+        (None, None) => {
+            return Err(Error::Other(format!("{} is not installed", manifest.name)));
+        }
+    };
+    if let Some(world) = world
+        && !worlds.iter().any(|name| name == world)
+    {
+        worlds.push(world.to_owned());
+    }
+
+    let dir = match existing {
+        Some(index) => packs.packs[index].dir.clone(),
+        None => free_dir(server_path, pack, &packs)?,
+    };
+    write_pack_files(&server_path.join(&dir), pack, owner)?;
+    for world in &worlds {
+        register(server_path, world, manifest)?;
+    }
+
+    let installed = InstalledPack {
+        uuid: manifest.uuid.clone(),
+        name: manifest.name.clone(),
+        version: manifest.version.to_string(),
+        kind: manifest.kind,
+        dir,
+        worlds,
+    };
+    let replaced = match existing {
+        Some(index) => Some(std::mem::replace(&mut packs.packs[index], installed.clone()).version),
+        None => {
+            packs.packs.push(installed.clone());
+            None
+        }
+    };
+    write_packs(server_path, &packs)?;
+    Ok(Installation {
+        pack: installed,
+        replaced,
+    })
+}
+
+/// Removes the pack `id`, by UUID or name, from the server and the worlds it
+/// is registered in.
+pub fn remove(server_path: &Path, id: &str) -> Result<InstalledPack> {
+    let mut packs = read_packs(server_path)?;
+    let index = packs
+        .packs
+        .iter()
+        .position(|pack| pack.matches(id))
+        .ok_or_else(|| Error::Other(format!("no pack {} is installed", id)))?;
+    let pack = packs.packs.remove(index);
+
+    for world in &pack.worlds {
+        unregister(server_path, world, pack.kind, &pack.uuid)?;
+    }
+    match std::fs::remove_dir_all(server_path.join(&pack.dir)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    write_packs(server_path, &packs)?;
+    Ok(pack)
+}
+
+/// A directory for a new pack: its own name, or with its UUID appended when
+/// something else has that name already.
+fn free_dir(server_path: &Path, pack: &ArchivedPack, packs: &Packs) -> Result<String> {
+    let kind_dir = pack.manifest.kind.dir();
+    let short_uuid: String = pack.manifest.uuid.chars().take(8).collect();
+    [
+        pack.dir_name.clone(),
+        format!("{}_{}", pack.dir_name, short_uuid),
+    ]
+    .into_iter()
+    .map(|name| format!("{}/{}", kind_dir, name))
+    .find(|dir| !server_path.join(dir).exists() && !packs.packs.iter().any(|pack| pack.dir == *dir))
+    .ok_or_else(|| {
+        Error::Other(format!(
+            "{}/{} is taken by another pack",
+            kind_dir, pack.dir_name
+        ))
+    })
+}
+
+/// Writes the pack's files next to `dir`, then swaps them in for whatever was
+/// there, so a failed write leaves the old pack in place.
+fn write_pack_files(dir: &Path, pack: &ArchivedPack, owner: Option<&Owner>) -> Result<()> {
+    let mut temp_name = dir.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_dir = dir.with_file_name(temp_name);
+    if temp_dir.exists() {
+        std::fs::remove_dir_all(&temp_dir)?;
+    }
+
+    for (relative, content) in &pack.files {
+        let path = temp_dir.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+    }
+    if let Some(owner) = owner {
+        set_owner_all(&temp_dir, owner)?;
+    }
+
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::rename(&temp_dir, dir)?;
+    Ok(())
+}
+
+fn set_owner_all(path: &Path, owner: &Owner) -> std::io::Result<()> {
+    permissions::set_owner(path, owner)?;
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            set_owner_all(&entry?.path(), owner)?;
+        }
+    }
+    Ok(())
+}
+
+/// An entry of a world's `world_behavior_packs.json` or
+/// `world_resource_packs.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldPack {
+    pub pack_id: String,
+    pub version: Value,
+}
+
+/// The packs the world uses, in the order they are applied.
+pub fn world_packs(server_path: &Path, world: &str, kind: PackKind) -> Result<Vec<WorldPack>> {
+    let path = world_file(server_path, world, kind);
+    match std::fs::read(&path) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn world_file(server_path: &Path, world: &str, kind: PackKind) -> PathBuf {
+    server_path
+        .join(WORLDS_DIR)
+        .join(world)
+        .join(kind.world_file())
+}
+
+/// Adds the pack to the world's pack list, or updates its version there. The
+/// world's directory is created for a world the server hasn't generated yet.
+fn register(server_path: &Path, world: &str, manifest: &PackManifest) -> Result<()> {
+    let mut entries = world_packs(server_path, world, manifest.kind)?;
+    let version = Value::from(manifest.version.parts().to_vec());
+    match entries
+        .iter_mut()
+        .find(|entry| entry.pack_id.eq_ignore_ascii_case(&manifest.uuid))
+    {
+        Some(entry) => entry.version = version,
+        None => entries.push(WorldPack {
+            pack_id: manifest.uuid.clone(),
+            version,
+        }),
+    }
+    let path = world_file(server_path, world, manifest.kind);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_json(&path, &entries)
+}
+
+fn unregister(server_path: &Path, world: &str, kind: PackKind, uuid: &str) -> Result<()> {
+    let path = world_file(server_path, world, kind);
+    if !path.is_file() {
+        return Ok(());
+    }
+    let mut entries = world_packs(server_path, world, kind)?;
+    entries.retain(|entry| !entry.pack_id.eq_ignore_ascii_case(uuid));
+    write_json(&path, &entries)
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    // Renamed into place so readers never see a partially written file.
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    serde_json::to_writer_pretty(std::fs::File::create(&temp_path)?, value)?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_zip;
+    use std::io::Write;
+
+    const BEHAVIOR_UUID: &str = "5f1a8c1e-0000-4000-8000-000000000001";
+    const RESOURCE_UUID: &str = "5f1a8c1e-0000-4000-8000-000000000002";
+
+    fn manifest(uuid: &str, module_type: &str, version: &str) -> String {
+        format!(
+            r#"{{
+                "format_version": 2,
+                "header": {{
+                    "name": "Example",
+                    "uuid": "{}",
+                    "version": {},
+                    "min_engine_version": [1, 21, 0]
+                }},
+                "modules": [{{ "type": "{}", "uuid": "0", "version": [1, 0, 0] }}],
+                "dependencies": [
+                    {{ "uuid": "{}", "version": [1, 0, 0] }},
+                    {{ "module_name": "@minecraft/server", "version": "1.12.0-beta" }}
+                ]
+            }}"#,
+            uuid, version, module_type, RESOURCE_UUID
+        )
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let parsed =
+            parse_manifest(manifest(BEHAVIOR_UUID, "data", "\"1.2.0\"").as_bytes()).unwrap();
+
+        assert_eq!(parsed.uuid, BEHAVIOR_UUID);
+        assert_eq!(parsed.version, "1.2.0".parse().unwrap());
+        assert_eq!(parsed.kind, PackKind::Behavior);
+        assert_eq!(parsed.min_engine_version, Some("1.21.0".parse().unwrap()));
+        assert_eq!(
+            parsed.dependencies,
+            vec![
+                Dependency::Pack {
+                    uuid: RESOURCE_UUID.to_owned(),
+                    version: Some("1.0.0".parse().unwrap()),
+                },
+                Dependency::Module {
+                    name: "@minecraft/server".to_owned(),
+                    version: "1.12.0-beta".to_owned(),
+                },
+            ]
+        );
+        assert!(
+            parse_manifest(manifest(BEHAVIOR_UUID, "skin_pack", "[1, 0, 0]").as_bytes()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_install_upgrade_and_remove() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let server_path = temp_dir.path().join("server");
+        std::fs::create_dir_all(server_path.join("behavior_packs/Example")).unwrap();
+
+        let mcpack = temp_dir.path().join("Example_RP.mcpack");
+        write_zip(
+            &mcpack,
+            &[
+                (
+                    "manifest.json",
+                    manifest(RESOURCE_UUID, "resources", "[1, 0, 0]").into_bytes(),
+                ),
+                ("textures/block.png", b"png".to_vec()),
+            ],
+        );
+        let mut nested = Vec::new();
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut nested));
+            writer
+                .start_file(
+                    "Example_RP.mcpack",
+                    zip::write::SimpleFileOptions::default(),
+                )
+                .unwrap();
+            writer.write_all(&std::fs::read(&mcpack).unwrap()).unwrap();
+            writer.finish().unwrap();
+        }
+        let mcaddon = temp_dir.path().join("Example.mcaddon");
+        write_zip(
+            &mcaddon,
+            &[
+                (
+                    "Example/manifest.json",
+                    manifest(BEHAVIOR_UUID, "data", "[1, 0, 0]").into_bytes(),
+                ),
+                ("Example/entities/cow.json", b"{}".to_vec()),
+                ("Example_RP.mcpack", std::fs::read(&mcpack).unwrap()),
+            ],
+        );
+
+        let packs = read_archive(&mcaddon).unwrap();
+        assert_eq!(packs.len(), 2);
+        for pack in &packs {
+            install(&server_path, pack, Some("Survival"), false, None).unwrap();
+        }
+
+        let installed = read_packs(&server_path).unwrap().packs;
+        let dirs: Vec<&str> = installed.iter().map(|pack| pack.dir.as_str()).collect();
+        // `behavior_packs/Example` was taken by a pack bedrock-up didn't install.
+        assert_eq!(
+            dirs,
+            vec![
+                "resource_packs/Example_RP",
+                "behavior_packs/Example_5f1a8c1e"
+            ]
+        );
+        assert!(
+            server_path
+                .join("behavior_packs/Example_5f1a8c1e/entities/cow.json")
+                .is_file()
+        );
+        assert_eq!(
+            world_packs(&server_path, "Survival", PackKind::Behavior).unwrap(),
+            vec![WorldPack {
+                pack_id: BEHAVIOR_UUID.to_owned(),
+                version: serde_json::json!([1, 0, 0]),
+            }]
+        );
+        assert_eq!(
+            installed_dirs(&server_path).unwrap(),
+            vec![
+                PathBuf::from("resource_packs/Example_RP"),
+                PathBuf::from("behavior_packs/Example_5f1a8c1e"),
+            ]
+        );
+
+        // The same version again is refused, a newer one replaces it in place.
+        assert!(install(&server_path, &packs[0], None, false, None).is_err());
+        write_zip(
+            &mcpack,
+            &[(
+                "manifest.json",
+                manifest(RESOURCE_UUID, "resources", "[1, 1, 0]").into_bytes(),
+            )],
+        );
+        let upgraded = read_archive(&mcpack).unwrap();
+        let installation = install(&server_path, &upgraded[0], None, false, None).unwrap();
+        assert_eq!(installation.replaced.as_deref(), Some("1.0.0"));
+        assert_eq!(installation.pack.worlds, vec!["Survival".to_owned()]);
+        assert!(
+            !server_path
+                .join("resource_packs/Example_RP/textures")
+                .exists()
+        );
+        assert_eq!(
+            world_packs(&server_path, "Survival", PackKind::Resource).unwrap()[0].version,
+            serde_json::json!([1, 1, 0])
+        );
+
+        let removed = remove(&server_path, BEHAVIOR_UUID).unwrap();
+        assert_eq!(removed.dir, "behavior_packs/Example_5f1a8c1e");
+        assert!(!server_path.join(&removed.dir).exists());
+        assert!(server_path.join("behavior_packs/Example").exists());
+        assert!(
+            world_packs(&server_path, "Survival", PackKind::Behavior)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(read_packs(&server_path).unwrap().packs.len(), 1);
+        assert!(remove(&server_path, BEHAVIOR_UUID).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::path::Path;
+
+/// Files that must be executable even when the archive doesn't say so.
+const EXECUTABLES: &[&str] = &["bedrock_server"];
+
+/// The user and group extracted files are handed to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Owner {
+    pub uid: u32,
+    pub gid: Option<u32>,
+}
+
+/// Parses `user[:group]`, where each part is a name or a numeric id. Without a
+/// group the user's primary group is used.
+pub fn parse_owner(spec: &str) -> std::io::Result<Owner> {
+    let (user, group) = match spec.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (spec, None),
+    };
+
+    let (uid, primary_gid) = match user.parse::<u32>() {
+        Ok(uid) => (uid, None),
+        Err(_) => {
+            let entry = lookup("/etc/passwd", user)?;
+            (entry.0, entry.1)
+        }
+    };
+
+    let gid = match group {
+        Some(group) => match group.parse::<u32>() {
+            Ok(gid) => Some(gid),
+            Err(_) => Some(lookup("/etc/group", group)?.0),
+        },
+        None => primary_gid,
+    };
+
+    Ok(Owner { uid, gid })
+}
+
+/// Finds `name` in a passwd or group style file and returns its id and, for
+/// passwd entries, the primary group id.
+fn lookup(database: &str, name: &str) -> std::io::Result<(u32, Option<u32>)> {
+    let content = std::fs::read_to_string(database)?;
+    content
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.first() == Some(&name))
+        .and_then(|fields| {
+            let id = fields.get(2)?.parse().ok()?;
+            let group_id = fields.get(3).and_then(|gid| gid.parse().ok());
+            Some((id, group_id))
+        })
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No entry for {} in {}", name, database),
+            )
+        })
+}
+
+/// Picks the permission bits for an extracted file. Overwritten files keep
+/// their existing mode plus any executable bits from the archive, new files
+/// take the archive's mode, and known server binaries are always executable.
+/// Execute bits are only given to classes that can read the file, and
+/// setuid, setgid and sticky bits are never kept, since files may be handed
+/// to another owner by root.
+pub fn target_mode(
+    file_name: &Path,
+    zip_mode: Option<u32>,
+    existing_mode: Option<u32>,
+) -> Option<u32> {
+    let zip_mode = zip_mode.map(|mode| mode & 0o777);
+    let existing_mode = existing_mode.map(|mode| mode & 0o777);
+
+    let mode = match (existing_mode, zip_mode) {
+        (Some(existing), Some(zip)) => Some(existing | (zip & readable_execute_bits(existing))),
+        (Some(existing), None) => Some(existing),
+        (None, zip) => zip,
+    };
+
+    let is_executable = file_name
+        .to_str()
+        .is_some_and(|name| EXECUTABLES.contains(&name));
+    if is_executable {
+        let mode = mode.unwrap_or(0o644);
+        Some(mode | readable_execute_bits(mode))
+    } else {
+        mode
+    }
+}
+
+/// The execute bits of the classes `mode` lets read.
+fn readable_execute_bits(mode: u32) -> u32 {
+    (mode & 0o444) >> 2
+}
+
+/// The permission bits of an existing file, if it exists.
+pub fn existing_mode(path: &Path) -> Option<u32> {
+    let metadata = std::fs::metadata(path).ok()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn set_owner(path: &Path, owner: &Owner) -> std::io::Result<()> {
+    std::os::unix::fs::chown(path, Some(owner.uid), owner.gid)
+}
+
+#[cfg(not(unix))]
+pub fn set_owner(_path: &Path, _owner: &Owner) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Changing file ownership is only supported on unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_mode_new_file_uses_zip_mode() {
+        let result = target_mode(Path::new("behavior_packs/a.json"), Some(0o100600), None);
+
+        assert_eq!(result, Some(0o600));
+    }
+
+    #[test]
+    fn test_target_mode_new_file_without_zip_mode() {
+        let result = target_mode(Path::new("server.properties"), None, None);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_target_mode_keeps_existing_mode() {
+        let result = target_mode(Path::new("server.properties"), Some(0o644), Some(0o100640));
+
+        assert_eq!(result, Some(0o640));
+    }
+
+    #[test]
+    fn test_target_mode_adds_executable_bits_from_zip() {
+        assert_eq!(
+            target_mode(Path::new("run.sh"), Some(0o755), Some(0o640)),
+            Some(0o750)
+        );
+        assert_eq!(
+            target_mode(Path::new("run.sh"), Some(0o755), Some(0o600)),
+            Some(0o700)
+        );
+    }
+
+    #[test]
+    fn test_target_mode_drops_special_bits() {
+        assert_eq!(
+            target_mode(Path::new("run.sh"), Some(0o104755), None),
+            Some(0o755)
+        );
+        assert_eq!(
+            target_mode(Path::new("run.sh"), Some(0o6755), Some(0o2640)),
+            Some(0o750)
+        );
+    }
+
+    #[test]
+    fn test_target_mode_server_binary_always_executable() {
+        assert_eq!(
+            target_mode(Path::new("bedrock_server"), None, None),
+            Some(0o755)
+        );
+        assert_eq!(
+            target_mode(Path::new("bedrock_server"), Some(0o644), None),
+            Some(0o755)
+        );
+        assert_eq!(
+            target_mode(Path::new("bedrock_server"), None, Some(0o700)),
+            Some(0o700)
+        );
+    }
+
+    #[test]
+    fn test_parse_owner_numeric() {
+        assert_eq!(
+            parse_owner("1000:1001").unwrap(),
+            Owner {
+                uid: 1000,
+                gid: Some(1001)
+            }
+        );
+        assert_eq!(
+            parse_owner("1000").unwrap(),
+            Owner {
+                uid: 1000,
+                gid: None
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_owner_by_name() {
+        let result = parse_owner("root:root").unwrap();
+
+        assert_eq!(
+            result,
+            Owner {
+                uid: 0,
+                gid: Some(0)
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_owner_primary_group() {
+        let result = parse_owner("root").unwrap();
+
+        assert_eq!(
+            result,
+            Owner {
+                uid: 0,
+                gid: Some(0)
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_owner_unknown_user() {
+        let result = parse_owner("no-such-user-12345");
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
+}
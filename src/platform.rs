@@ -0,0 +1,160 @@
+use crate::error::{Error, Result};
+use crate::manifest;
+use crate::release::DownloadType;
+use std::fmt;
+use std::path::Path;
+
+/// What a server's files run on. Download types of the same platform differ
+/// only in their release channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Windows,
+    Linux,
+    Java,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 3] = [Platform::Windows, Platform::Linux, Platform::Java];
+
+    pub fn of(download_type: &DownloadType) -> Platform {
+        match download_type {
+            DownloadType::Windows | DownloadType::PreviewWindows => Platform::Windows,
+            DownloadType::Linux | DownloadType::PreviewLinux => Platform::Linux,
+            DownloadType::ServerJar => Platform::Java,
+        }
+    }
+
+    /// The file only this platform's server has.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Platform::Windows => "bedrock_server.exe",
+            Platform::Linux => "bedrock_server",
+            Platform::Java => "server.jar",
+        }
+    }
+
+    /// The stable download type of the platform.
+    pub fn stable(&self) -> DownloadType {
+        match self {
+            Platform::Windows => DownloadType::Windows,
+            Platform::Linux => DownloadType::Linux,
+            Platform::Java => DownloadType::ServerJar,
+        }
+    }
+
+    /// The preview download type of the platform. Java servers have none.
+    pub fn preview(&self) -> Option<DownloadType> {
+        match self {
+            Platform::Windows => Some(DownloadType::PreviewWindows),
+            Platform::Linux => Some(DownloadType::PreviewLinux),
+            Platform::Java => None,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Windows => write!(f, "Windows"),
+            Platform::Linux => write!(f, "Linux"),
+            Platform::Java => write!(f, "Java"),
+        }
+    }
+}
+
+/// The platform of the server installed at `server_path`, read from which
+/// server binary it has. `None` when it has none, an error when it has several.
+pub fn installed_platform(server_path: &Path) -> Result<Option<Platform>> {
+    let found: Vec<Platform> = Platform::ALL
+        .into_iter()
+        .filter(|platform| server_path.join(platform.marker()).is_file())
+        .collect();
+    match found.as_slice() {
+        [] => Ok(None),
+        [platform] => Ok(Some(*platform)),
+        _ => Err(Error::Other(format!(
+            "{} has the server binaries of several platforms ({})",
+            server_path.display(),
+            found
+                .iter()
+                .map(|platform| platform.marker())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// The download type of the server installed at `server_path`: the one its
+/// manifest records, else the stable channel of the platform its binary is
+/// for. `None` for an empty directory.
+pub fn detect(server_path: &Path) -> Result<Option<DownloadType>> {
+    let platform = installed_platform(server_path)?;
+    let recorded = manifest::read_manifest(server_path)?
+        .and_then(|manifest| DownloadType::from_links_name(&manifest.download_type));
+    Ok(match (recorded, platform) {
+        // The binary wins over a manifest left behind by another platform.
+        (Some(recorded), Some(platform)) if Platform::of(&recorded) != platform => {
+            Some(platform.stable())
+        }
+        (Some(recorded), _) => Some(recorded),
+        (None, platform) => platform.map(|platform| platform.stable()),
+    })
+}
+
+/// Fails when `requested` is for another platform than the server installed
+/// at `server_path`, rather than mixing two platforms' files.
+pub fn check(server_path: &Path, requested: &DownloadType) -> Result<()> {
+    match installed_platform(server_path)? {
+        Some(installed) if installed != Platform::of(requested) => Err(Error::Other(format!(
+            "{} has a {} server, refusing to install {} over it",
+            server_path.display(),
+            installed,
+            requested
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::Manifest;
+    use crate::release::Release;
+
+    #[test]
+    fn test_detect() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let server_path = temp_dir.path();
+        assert_eq!(detect(server_path).unwrap(), None);
+
+        std::fs::write(server_path.join("bedrock_server"), "").unwrap();
+        assert_eq!(detect(server_path).unwrap(), Some(DownloadType::Linux));
+
+        let release = Release {
+            download_type: DownloadType::PreviewLinux,
+            download_url: "https://example.com/bedrock-server-1.21.100.20.zip".to_owned(),
+        };
+        let manifest = Manifest::new(&release, chrono::Utc::now(), Default::default());
+        manifest::write_manifest(server_path, &manifest).unwrap();
+        assert_eq!(
+            detect(server_path).unwrap(),
+            Some(DownloadType::PreviewLinux)
+        );
+
+        std::fs::write(server_path.join("bedrock_server.exe"), "").unwrap();
+        assert!(detect(server_path).is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let server_path = temp_dir.path();
+        check(server_path, &DownloadType::Windows).unwrap();
+
+        std::fs::write(server_path.join("bedrock_server"), "").unwrap();
+
+        check(server_path, &DownloadType::Linux).unwrap();
+        check(server_path, &DownloadType::PreviewLinux).unwrap();
+        assert!(check(server_path, &DownloadType::Windows).is_err());
+    }
+}
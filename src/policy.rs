@@ -0,0 +1,120 @@
+use crate::release::DownloadType;
+use crate::version::{Change, Version};
+use serde::Deserialize;
+
+/// Which updates are applied without asking. The default applies every
+/// update, as bedrock-up always has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UpdatePolicy {
+    /// The largest change applied automatically. Anything larger is held for
+    /// approval.
+    pub auto_apply: Change,
+
+    /// Apply preview releases automatically. Held for approval when false.
+    pub auto_apply_previews: bool,
+}
+
+impl Default for UpdatePolicy {
+    fn default() -> UpdatePolicy {
+        UpdatePolicy {
+            auto_apply: Change::Major,
+            auto_apply_previews: true,
+        }
+    }
+}
+
+/// What the policy says about an available update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Apply,
+    /// Needs approval, for the given reason.
+    Hold(String),
+}
+
+impl UpdatePolicy {
+    /// Judges the update from `installed` to `available`. A server with no
+    /// known version is always updated, since there is nothing to protect.
+    pub fn evaluate(
+        &self,
+        download_type: &DownloadType,
+        installed: Option<&Version>,
+        available: Option<&Version>,
+    ) -> Verdict {
+        if download_type.is_preview() && !self.auto_apply_previews {
+            return Verdict::Hold("preview releases are never applied automatically".to_owned());
+        }
+
+        let Some(installed) = installed else {
+            return Verdict::Apply;
+        };
+        let change = match available {
+            Some(available) => installed.change_to(available),
+            // Without a version there is no telling how large the change is.
+            None => Change::Major,
+        };
+        if change <= self.auto_apply {
+            Verdict::Apply
+        } else {
+            Verdict::Hold(format!(
+                "a {} update, only {} updates are applied automatically",
+                change, self.auto_apply
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let policy = UpdatePolicy {
+            auto_apply: Change::Patch,
+            auto_apply_previews: false,
+        };
+        let installed = version("1.21.92.1");
+
+        let evaluate = |download_type, available: &str| {
+            policy.evaluate(&download_type, Some(&installed), Some(&version(available)))
+        };
+        assert_eq!(evaluate(DownloadType::Linux, "1.21.92.2"), Verdict::Apply);
+        assert_eq!(evaluate(DownloadType::Linux, "1.21.93.1"), Verdict::Apply);
+        assert_eq!(
+            evaluate(DownloadType::Linux, "1.22.0.3"),
+            Verdict::Hold(
+                "a minor update, only patch updates are applied automatically".to_owned()
+            )
+        );
+        assert!(matches!(
+            evaluate(DownloadType::PreviewLinux, "1.21.92.2"),
+            Verdict::Hold(_)
+        ));
+        assert!(matches!(
+            policy.evaluate(&DownloadType::Linux, Some(&installed), None),
+            Verdict::Hold(_)
+        ));
+        assert_eq!(
+            policy.evaluate(&DownloadType::Linux, None, Some(&installed)),
+            Verdict::Apply
+        );
+    }
+
+    #[test]
+    fn test_default_applies_everything() {
+        let policy = UpdatePolicy::default();
+
+        let result = policy.evaluate(
+            &DownloadType::PreviewLinux,
+            Some(&version("1.21.92.1")),
+            Some(&version("2.0.0.1")),
+        );
+
+        assert_eq!(result, Verdict::Apply);
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const SERVER_PROPERTIES: &str = "server.properties";
+
+/// Parses `key=value` lines, skipping blank lines and `#` comments.
+pub fn parse_properties(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
+
+/// Reads the server's `server.properties`.
+pub fn read_server_properties(server_path: &Path) -> std::io::Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(server_path.join(SERVER_PROPERTIES))?;
+    Ok(parse_properties(&content))
+}
+
+/// Sets `changes` in `key=value` content, keeping every other line as it is.
+/// Keys not in the content yet are appended.
+pub fn set_properties(content: &str, changes: &[(&str, String)]) -> String {
+    let mut missing: Vec<&(&str, String)> = changes.iter().collect();
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let key = line.split_once('=').map(|(key, _)| key.trim());
+            match changes.iter().find(|(name, _)| Some(*name) == key) {
+                Some((name, value)) if !line.trim_start().starts_with('#') => {
+                    missing.retain(|(other, _)| other != name);
+                    format!("{}={}", name, value)
+                }
+                _ => line.to_owned(),
+            }
+        })
+        .collect();
+    lines.extend(
+        missing
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value)),
+    );
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+/// Sets `changes` in the server's `server.properties`, creating it when missing.
+pub fn set_server_properties(
+    server_path: &Path,
+    changes: &[(&str, String)],
+) -> std::io::Result<()> {
+    let path = server_path.join(SERVER_PROPERTIES);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    std::fs::write(path, set_properties(&content, changes))
+}
+
+/// The IPv4 port the server listens on.
+pub fn server_port(server_path: &Path) -> u16 {
+    read_server_properties(server_path)
+        .ok()
+        .and_then(|properties| properties.get("server-port")?.parse().ok())
+        .unwrap_or(crate::raknet::DEFAULT_PORT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_properties() {
+        let result = parse_properties(
+            "# Comment\nserver-name=Dedicated Server\n\nserver-port = 19140\nlevel-name=\n",
+        );
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result["server-name"], "Dedicated Server");
+        assert_eq!(result["server-port"], "19140");
+        assert_eq!(result["level-name"], "");
+    }
+
+    #[test]
+    fn test_set_properties() {
+        let result = set_properties(
+            "# level-name=ignored\nserver-port = 19132\nlevel-name=Bedrock level\n",
+            &[
+                ("level-name", "Bedrock level Preview".to_owned()),
+                ("server-port", "19134".to_owned()),
+                ("server-portv6", "19135".to_owned()),
+            ],
+        );
+
+        assert_eq!(
+            result,
+            "# level-name=ignored\nserver-port=19134\nlevel-name=Bedrock level Preview\nserver-portv6=19135\n"
+        );
+    }
+
+    #[test]
+    fn test_server_port() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(server_port(temp_dir.path()), crate::raknet::DEFAULT_PORT);
+
+        std::fs::write(
+            temp_dir.path().join(SERVER_PROPERTIES),
+            "server-port=19140\n",
+        )
+        .unwrap();
+        assert_eq!(server_port(temp_dir.path()), 19140);
+    }
+}
@@ -0,0 +1,201 @@
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Port Bedrock servers listen on when `server.properties` doesn't say.
+pub const DEFAULT_PORT: u16 = 19132;
+
+const UNCONNECTED_PING: u8 = 0x01;
+const UNCONNECTED_PONG: u8 = 0x1c;
+/// Marks RakNet offline messages.
+const MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+/// Packet id, time, server guid and magic come before the status string.
+const PONG_HEADER_LEN: usize = 1 + 8 + 8 + MAGIC.len();
+
+/// What a server reports in the server list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerStatus {
+    /// `MCPE` for Bedrock, `MCEE` for Education Edition.
+    pub edition: String,
+    pub motd: String,
+    pub protocol: u32,
+    pub version: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub server_id: String,
+    pub level_name: Option<String>,
+    pub game_mode: Option<String>,
+    pub port: Option<u16>,
+    pub port_v6: Option<u16>,
+}
+
+/// Asks the server at `addr` for its status with a RakNet unconnected ping,
+/// the same query the in-game server list makes.
+pub fn ping(addr: impl ToSocketAddrs, timeout: Duration) -> std::io::Result<ServerStatus> {
+    let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "No address to ping")
+    })?;
+    let local: SocketAddr = if addr.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(addr)?;
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default();
+    socket.send(&ping_packet(time, std::process::id().into()))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0u8; 1500];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let len = socket.recv(&mut buf)?;
+        if buf[0] == UNCONNECTED_PONG {
+            return parse_pong(&buf[..len]);
+        }
+    }
+}
+
+fn ping_packet(time: u64, client_guid: u64) -> Vec<u8> {
+    let mut packet = vec![UNCONNECTED_PING];
+    packet.extend_from_slice(&time.to_be_bytes());
+    packet.extend_from_slice(&MAGIC);
+    packet.extend_from_slice(&client_guid.to_be_bytes());
+    packet
+}
+
+/// Reads the status string out of an unconnected pong packet.
+pub fn parse_pong(packet: &[u8]) -> std::io::Result<ServerStatus> {
+    let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    if packet.first() != Some(&UNCONNECTED_PONG) {
+        return Err(invalid("Not an unconnected pong"));
+    }
+    if packet.get(17..PONG_HEADER_LEN) != Some(&MAGIC[..]) {
+        return Err(invalid("Missing RakNet magic"));
+    }
+    let len = packet
+        .get(PONG_HEADER_LEN..PONG_HEADER_LEN + 2)
+        .map(|len| u16::from_be_bytes([len[0], len[1]]) as usize)
+        .ok_or_else(|| invalid("Truncated pong"))?;
+    let status = packet
+        .get(PONG_HEADER_LEN + 2..PONG_HEADER_LEN + 2 + len)
+        .ok_or_else(|| invalid("Truncated pong"))?;
+
+    parse_status(&String::from_utf8_lossy(status)).ok_or_else(|| invalid("Malformed status"))
+}
+
+/// Parses `MCPE;motd;protocol;version;players;max;id;level;mode;mode id;port;port6;`.
+fn parse_status(status: &str) -> Option<ServerStatus> {
+    let fields: Vec<&str> = status.split(';').collect();
+    let field = |index: usize| {
+        fields
+            .get(index)
+            .filter(|field| !field.is_empty())
+            .map(|field| field.to_string())
+    };
+
+    Some(ServerStatus {
+        edition: field(0)?,
+        motd: field(1).unwrap_or_default(),
+        protocol: fields.get(2)?.parse().ok()?,
+        version: field(3)?,
+        players: fields.get(4)?.parse().ok()?,
+        max_players: fields.get(5)?.parse().ok()?,
+        server_id: field(6).unwrap_or_default(),
+        level_name: field(7),
+        game_mode: field(8),
+        port: field(10).and_then(|port| port.parse().ok()),
+        port_v6: field(11).and_then(|port| port.parse().ok()),
+    })
+}
+
+/// The unconnected pong a server answering with `status` sends, for fake
+/// servers in tests.
+#[cfg(test)]
+pub(crate) fn pong_packet(status: &str) -> Vec<u8> {
+    let mut packet = vec![UNCONNECTED_PONG];
+    packet.extend_from_slice(&1u64.to_be_bytes());
+    packet.extend_from_slice(&2u64.to_be_bytes());
+    packet.extend_from_slice(&MAGIC);
+    packet.extend_from_slice(&(status.len() as u16).to_be_bytes());
+    packet.extend_from_slice(status.as_bytes());
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = "MCPE;Dedicated Server;818;1.21.92;2;10;13253860892328930865;Bedrock level;Survival;1;19132;19133;";
+
+    #[test]
+    fn test_parse_pong() {
+        let result = parse_pong(&pong_packet(STATUS)).unwrap();
+
+        assert_eq!(
+            result,
+            ServerStatus {
+                edition: "MCPE".to_owned(),
+                motd: "Dedicated Server".to_owned(),
+                protocol: 818,
+                version: "1.21.92".to_owned(),
+                players: 2,
+                max_players: 10,
+                server_id: "13253860892328930865".to_owned(),
+                level_name: Some("Bedrock level".to_owned()),
+                game_mode: Some("Survival".to_owned()),
+                port: Some(19132),
+                port_v6: Some(19133),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_pong_invalid() {
+        assert!(parse_pong(&[]).is_err());
+        assert!(parse_pong(&pong_packet("MCPE;motd")).is_err());
+
+        let mut truncated = pong_packet(STATUS);
+        truncated.truncate(40);
+        assert!(parse_pong(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_ping() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            let (len, client) = server.recv_from(&mut buf).unwrap();
+            assert_eq!(buf[0], UNCONNECTED_PING);
+            assert_eq!(&buf[9..25], &MAGIC);
+            assert_eq!(len, 33);
+            server.send_to(&pong_packet(STATUS), client).unwrap();
+        });
+
+        let result = ping(addr, Duration::from_secs(5)).unwrap();
+
+        assert_eq!(result.players, 2);
+        assert_eq!(result.version, "1.21.92");
+    }
+
+    #[test]
+    fn test_ping_no_answer() {
+        // Bound but never answering.
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let result = ping(server.local_addr().unwrap(), Duration::from_millis(200));
+
+        assert!(result.is_err());
+    }
+}
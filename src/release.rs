@@ -0,0 +1,1216 @@
+use crate::error::{Error, Result};
+use crate::http::{HttpClient, Validators};
+use clap::ValueEnum;
+use reqwest::{StatusCode, Url};
+use std::fmt;
+
+pub const DEFAULT_LINKS_URL: &str =
+    "https://net-secondary.web.minecraft-services.net/api/v1.0/download/links";
+pub const DEFAULT_DOWNLOAD_PAGE_URL: &str =
+    "https://www.minecraft.net/en-us/download/server/bedrock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DownloadType {
+    Windows,
+    Linux,
+    PreviewWindows,
+    PreviewLinux,
+    ServerJar,
+}
+
+impl DownloadType {
+    /// The download type the links API calls `name`, e.g. `serverBedrockLinux`.
+    pub fn from_links_name(name: &str) -> Option<DownloadType> {
+        DownloadType::value_variants()
+            .iter()
+            .find(|download_type| download_type.to_string() == name)
+            .copied()
+    }
+
+    pub fn is_preview(&self) -> bool {
+        matches!(
+            self,
+            DownloadType::PreviewWindows | DownloadType::PreviewLinux
+        )
+    }
+}
+
+impl fmt::Display for DownloadType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadType::Windows => write!(f, "serverBedrockWindows"),
+            DownloadType::Linux => write!(f, "serverBedrockLinux"),
+            DownloadType::PreviewWindows => {
+                write!(f, "serverBedrockPreviewWindows")
+            }
+            DownloadType::PreviewLinux => write!(f, "serverBedrockPreviewLinux"),
+            DownloadType::ServerJar => write!(f, "serverJar"),
+        }
+    }
+}
+
+/// A place the download links can be read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinksEndpoint {
+    /// A links API returning the `result.links` JSON document.
+    Api(String),
+    /// An HTML download page scraped for server download links.
+    DownloadPage(String),
+}
+
+impl LinksEndpoint {
+    pub fn url(&self) -> &str {
+        match self {
+            LinksEndpoint::Api(url) | LinksEndpoint::DownloadPage(url) => url,
+        }
+    }
+}
+
+/// Builds the ordered fallback chain of endpoints: the given links APIs, then
+/// the built-in API and download page unless fallback is disabled.
+pub fn links_endpoints(
+    links_urls: &[String],
+    download_page_url: Option<&str>,
+    no_fallback: bool,
+) -> Vec<LinksEndpoint> {
+    let mut endpoints: Vec<LinksEndpoint> = links_urls
+        .iter()
+        .map(|url| LinksEndpoint::Api(url.clone()))
+        .collect();
+
+    if !no_fallback || endpoints.is_empty() {
+        endpoints.push(LinksEndpoint::Api(DEFAULT_LINKS_URL.to_owned()));
+    }
+
+    match download_page_url {
+        Some(url) => endpoints.push(LinksEndpoint::DownloadPage(url.to_owned())),
+        None if !no_fallback => {
+            endpoints.push(LinksEndpoint::DownloadPage(
+                DEFAULT_DOWNLOAD_PAGE_URL.to_owned(),
+            ));
+        }
+        None => {}
+    }
+
+    endpoints.dedup();
+    endpoints
+}
+
+/// A links document listing the current download of every download type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseIndex {
+    /// Where the index was read from.
+    pub source: String,
+    pub json: serde_json::Value,
+    /// How to ask `source` whether the index changed since it was read.
+    pub validators: Option<Validators>,
+}
+
+impl ReleaseIndex {
+    /// Fetches the index from a single endpoint.
+    pub fn fetch(client: &HttpClient, endpoint: &LinksEndpoint) -> Result<ReleaseIndex> {
+        let (json, validators) = match endpoint {
+            LinksEndpoint::Api(url) => get_json_from_web_with_url(client, url)?,
+            LinksEndpoint::DownloadPage(url) => (get_json_from_download_page(client, url)?, None),
+        };
+        Ok(ReleaseIndex {
+            source: endpoint.url().to_owned(),
+            json,
+            validators,
+        })
+    }
+
+    /// Like [`ReleaseIndex::fetch`], but returns `None` when the endpoint
+    /// answers 304 Not Modified to the validators `cached` was read with.
+    pub fn fetch_if_changed(
+        client: &HttpClient,
+        endpoint: &LinksEndpoint,
+        cached: &ReleaseIndex,
+    ) -> Result<Option<ReleaseIndex>> {
+        if let (LinksEndpoint::Api(url), Some(validators)) = (endpoint, &cached.validators)
+            && cached.source == *url
+        {
+            let resp = client.get_if_changed(url, validators)?;
+            if resp.status() == StatusCode::NOT_MODIFIED {
+                return Ok(None);
+            }
+            let (json, validators) = read_json_response(resp)?;
+            return Ok(Some(ReleaseIndex {
+                source: url.clone(),
+                json,
+                validators,
+            }));
+        }
+        ReleaseIndex::fetch(client, endpoint).map(Some)
+    }
+
+    /// Walks the endpoints in order and returns the index and release from the
+    /// first one that has a download for `download_type`.
+    pub fn fetch_first(
+        client: &HttpClient,
+        endpoints: &[LinksEndpoint],
+        download_type: &DownloadType,
+    ) -> Result<(ReleaseIndex, Release)> {
+        first_found(endpoints, download_type, |endpoint| {
+            let index = ReleaseIndex::fetch(client, endpoint)?;
+            let release = index.resolve(download_type).map_err(Error::Schema)?;
+            Ok((index, release))
+        })
+    }
+
+    /// Like [`ReleaseIndex::fetch_first`], but returns `None` when the
+    /// endpoint `cached` was read from reports it unchanged.
+    pub fn fetch_first_if_changed(
+        client: &HttpClient,
+        endpoints: &[LinksEndpoint],
+        download_type: &DownloadType,
+        cached: &ReleaseIndex,
+    ) -> Result<Option<(ReleaseIndex, Release)>> {
+        first_found(endpoints, download_type, |endpoint| {
+            let Some(index) = ReleaseIndex::fetch_if_changed(client, endpoint, cached)? else {
+                return Ok(None);
+            };
+            let release = index.resolve(download_type).map_err(Error::Schema)?;
+            Ok(Some((index, release)))
+        })
+    }
+
+    /// Finds the release for `download_type` in this index. A relative
+    /// download url is resolved against `source` when that is a url, as in
+    /// the `links.json` of a mirror.
+    pub fn resolve(
+        &self,
+        download_type: &DownloadType,
+    ) -> std::result::Result<Release, LinksSchemaError> {
+        let mut download_url = get_download_url_from_json(&self.json, download_type)?;
+        if let Ok(base) = Url::parse(&self.source) {
+            download_url = base
+                .join(&download_url)
+                .map_err(|_| LinksSchemaError::InvalidDownloadUrl(download_type.to_string()))?
+                .to_string();
+        }
+        Ok(Release {
+            download_type: *download_type,
+            download_url,
+        })
+    }
+}
+
+/// One downloadable server build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub download_type: DownloadType,
+    pub download_url: String,
+}
+
+impl Release {
+    /// The version in the zip's name, `1.21.92.1` for
+    /// `bedrock-server-1.21.92.1.zip`.
+    pub fn version(&self) -> Option<&str> {
+        version_from_url(&self.download_url)
+    }
+}
+
+/// Reads the version out of a download URL or zip file name.
+pub fn version_from_url(download_url: &str) -> Option<&str> {
+    download_url
+        .rsplit('/')
+        .next()?
+        .strip_prefix("bedrock-server-")?
+        .strip_suffix(".zip")
+}
+
+/// Returns the first result `fetch` produces for an endpoint, collecting the
+/// failures of the ones before it.
+fn first_found<T>(
+    endpoints: &[LinksEndpoint],
+    download_type: &DownloadType,
+    mut fetch: impl FnMut(&LinksEndpoint) -> Result<T>,
+) -> Result<T> {
+    let mut failures = Vec::new();
+    for endpoint in endpoints {
+        match fetch(endpoint) {
+            Ok(found) => return Ok(found),
+            Err(e) => failures.push((endpoint.url().to_owned(), e)),
+        }
+    }
+
+    Err(Error::NoRelease {
+        download_type: *download_type,
+        failures,
+    })
+}
+
+fn get_json_from_web_with_url(
+    client: &HttpClient,
+    url: &str,
+) -> Result<(serde_json::Value, Option<Validators>)> {
+    read_json_response(client.get(url)?)
+}
+
+fn read_json_response(
+    resp: reqwest::blocking::Response,
+) -> Result<(serde_json::Value, Option<Validators>)> {
+    let resp = resp.error_for_status()?;
+    let validators = Validators::from_response(&resp);
+    Ok((resp.json()?, validators))
+}
+
+fn get_json_from_download_page(client: &HttpClient, url: &str) -> Result<serde_json::Value> {
+    let html = client.get(url)?.error_for_status()?.text()?;
+    Ok(scrape_download_links(&html))
+}
+
+/// Path segment the download page uses for each download type.
+fn download_page_segment(download_type: &DownloadType) -> Option<&'static str> {
+    match download_type {
+        DownloadType::Windows => Some("/bin-win/"),
+        DownloadType::Linux => Some("/bin-linux/"),
+        DownloadType::PreviewWindows => Some("/bin-win-preview/"),
+        DownloadType::PreviewLinux => Some("/bin-linux-preview/"),
+        DownloadType::ServerJar => None,
+    }
+}
+
+/// Pulls the server zip links out of the download page html and shapes them
+/// like a links API response so the rest of the update treats them the same.
+fn scrape_download_links(html: &str) -> serde_json::Value {
+    let mut links: Vec<serde_json::Value> = Vec::new();
+
+    for (start, _) in html.match_indices("https://") {
+        let url: String = html[start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | '<' | '>'))
+            .collect();
+        if !url.ends_with(".zip") {
+            continue;
+        }
+
+        let download_type = DownloadType::value_variants().iter().find(|download_type| {
+            download_page_segment(download_type).is_some_and(|segment| url.contains(segment))
+        });
+        let Some(download_type) = download_type else {
+            continue;
+        };
+
+        let download_type = download_type.to_string();
+        if links
+            .iter()
+            .any(|link| link["downloadType"] == download_type.as_str())
+        {
+            continue;
+        }
+        links.push(serde_json::json!({
+            "downloadType": download_type,
+            "downloadUrl": url,
+        }));
+    }
+
+    serde_json::json!({ "result": { "links": links } })
+}
+
+/// The part of the links schema a response did not satisfy.
+#[derive(Debug, PartialEq)]
+pub enum LinksSchemaError {
+    NotAnObject,
+    MissingLinks,
+    LinksNotArray,
+    DownloadTypeNotFound(String),
+    MissingDownloadUrl(String),
+    DownloadUrlNotString(String),
+    InvalidDownloadUrl(String),
+}
+
+impl std::fmt::Display for LinksSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinksSchemaError::NotAnObject => write!(f, "expected a JSON object"),
+            LinksSchemaError::MissingLinks => {
+                write!(f, "expected a `result.links` or `links` field")
+            }
+            LinksSchemaError::LinksNotArray => write!(f, "expected `links` to be an array"),
+            LinksSchemaError::DownloadTypeNotFound(download_type) => {
+                write!(f, "no link has `downloadType` \"{}\"", download_type)
+            }
+            LinksSchemaError::MissingDownloadUrl(download_type) => {
+                write!(f, "link \"{}\" has no `downloadUrl` field", download_type)
+            }
+            LinksSchemaError::DownloadUrlNotString(download_type) => {
+                write!(
+                    f,
+                    "link \"{}\" has a non-string `downloadUrl`",
+                    download_type
+                )
+            }
+            LinksSchemaError::InvalidDownloadUrl(download_type) => {
+                write!(f, "link \"{}\" has an invalid `downloadUrl`", download_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinksSchemaError {}
+
+/// Finds the download url for `download_type`. Accepts the links array either
+/// under `result` or at the top level, and skips unusable duplicate entries.
+pub fn get_download_url_from_json(
+    json: &serde_json::Value,
+    download_type: &DownloadType,
+) -> std::result::Result<String, LinksSchemaError> {
+    let json = json.as_object().ok_or(LinksSchemaError::NotAnObject)?;
+    let links = json
+        .get("result")
+        .and_then(|result| result.get("links"))
+        .or_else(|| json.get("links"))
+        .ok_or(LinksSchemaError::MissingLinks)?
+        .as_array()
+        .ok_or(LinksSchemaError::LinksNotArray)?;
+
+    let download_type = download_type.to_string();
+    let mut error = LinksSchemaError::DownloadTypeNotFound(download_type.clone());
+    for item in links {
+        if item.get("downloadType").and_then(|t| t.as_str()) != Some(download_type.as_str()) {
+            continue;
+        }
+
+        match item.get("downloadUrl") {
+            Some(serde_json::Value::String(url)) => return Ok(url.trim().to_owned()),
+            Some(_) => error = LinksSchemaError::DownloadUrlNotString(download_type.clone()),
+            None => error = LinksSchemaError::MissingDownloadUrl(download_type.clone()),
+        }
+    }
+
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_client;
+    use mockito::Server;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_json_from_web_success() {
+        // Create a mock server
+        let mut server = Server::new();
+        let mock_response = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "bedrock-server",
+                        "downloadUrl": "https://example.com/bedrock-server.zip"
+                    }
+                ]
+            }
+        });
+
+        let mock = server
+            .mock("GET", "/api/v1.0/download/links")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create();
+
+        // Test the function
+        let (result, _) = get_json_from_web_with_url(
+            &test_client(),
+            &format!("{}/api/v1.0/download/links", server.url()),
+        )
+        .unwrap();
+
+        // Verify the mock was called
+        mock.assert();
+
+        // Verify the response
+        assert_eq!(result, mock_response);
+    }
+
+    #[test]
+    fn test_get_json_from_web_invalid_json() {
+        // Create a mock server that returns invalid JSON
+        let mut server = Server::new();
+
+        let mock = server
+            .mock("GET", "/api/v1.0/download/links")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("invalid json")
+            .create();
+
+        // Test the function
+        let result = get_json_from_web_with_url(
+            &test_client(),
+            &format!("{}/api/v1.0/download/links", server.url()),
+        );
+
+        // Verify the mock was called
+        mock.assert();
+
+        // Verify invalid JSON is an error
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_json_from_web_server_error() {
+        // Create a mock server that returns a server error
+        let mut server = Server::new();
+
+        let mock = server
+            .mock("GET", "/api/v1.0/download/links")
+            .with_status(500)
+            .with_body("Internal Server Error")
+            .create();
+
+        // Test the function
+        let result = get_json_from_web_with_url(
+            &test_client(),
+            &format!("{}/api/v1.0/download/links", server.url()),
+        );
+
+        // Verify the mock was called
+        mock.assert();
+
+        // Verify a server error is an error
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_json_from_web_connection_error() {
+        // Test with an invalid URL to simulate connection error
+        let result =
+            get_json_from_web_with_url(&test_client(), "http://non-existent-domain-12345.com/api");
+
+        // Verify a connection error is an error
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_json_from_web_empty_response() {
+        // Create a mock server that returns empty JSON
+        let mut server = Server::new();
+
+        let mock = server
+            .mock("GET", "/api/v1.0/download/links")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{}")
+            .create();
+
+        // Test the function
+        let (result, _) = get_json_from_web_with_url(
+            &test_client(),
+            &format!("{}/api/v1.0/download/links", server.url()),
+        )
+        .unwrap();
+
+        // Verify the mock was called
+        mock.assert();
+
+        // Verify the response is empty JSON object
+        assert_eq!(result, json!({}));
+    }
+
+    #[test]
+    fn test_get_json_from_web_complex_response() {
+        // Test with a more complex JSON response
+        let mut server = Server::new();
+        let mock_response = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "bedrock-server",
+                        "downloadUrl": "https://example.com/bedrock-server-1.20.0.zip",
+                        "version": "1.20.0"
+                    },
+                    {
+                        "downloadType": "bedrock-server-preview",
+                        "downloadUrl": "https://example.com/bedrock-server-preview-1.21.0.zip",
+                        "version": "1.21.0"
+                    }
+                ],
+                "metadata": {
+                    "lastUpdated": "2025-07-04T12:00:00Z"
+                }
+            }
+        });
+
+        let mock = server
+            .mock("GET", "/api/v1.0/download/links")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create();
+
+        // Test the function
+        let (result, _) = get_json_from_web_with_url(
+            &test_client(),
+            &format!("{}/api/v1.0/download/links", server.url()),
+        )
+        .unwrap();
+
+        // Verify the mock was called
+        mock.assert();
+
+        // Verify the response matches expected structure
+        assert_eq!(result, mock_response);
+
+        // Verify specific nested values
+        assert_eq!(
+            result["result"]["links"][0]["downloadType"],
+            "bedrock-server"
+        );
+        assert_eq!(result["result"]["links"][1]["version"], "1.21.0");
+        assert_eq!(
+            result["result"]["metadata"]["lastUpdated"],
+            "2025-07-04T12:00:00Z"
+        );
+    }
+
+    // Tests for get_download_url_from_json function
+    #[test]
+    fn test_get_download_url_from_json_success_windows() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
+                    },
+                    {
+                        "downloadType": "serverBedrockLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-windows.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_success_linux() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
+                    },
+                    {
+                        "downloadType": "serverBedrockLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Linux);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-linux.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_success_preview_windows() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockPreviewWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-preview-windows.zip"
+                    },
+                    {
+                        "downloadType": "serverBedrockPreviewLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-preview-linux.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::PreviewWindows);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-preview-windows.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_success_preview_linux() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockPreviewLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-preview-linux.zip"
+                    },
+                    {
+                        "downloadType": "serverJar",
+                        "downloadUrl": "https://example.com/bedrock-server.jar"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::PreviewLinux);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-preview-linux.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_success_server_jar() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
+                    },
+                    {
+                        "downloadType": "serverJar",
+                        "downloadUrl": "https://example.com/bedrock-server.jar"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::ServerJar);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server.jar".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_not_found() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
+                    },
+                    {
+                        "downloadType": "serverBedrockLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::ServerJar);
+
+        assert_eq!(
+            result,
+            Err(LinksSchemaError::DownloadTypeNotFound(
+                "serverJar".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_missing_result() {
+        let json_data = json!({
+            "error": "No data available"
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(result, Err(LinksSchemaError::MissingLinks));
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_missing_links() {
+        let json_data = json!({
+            "result": {
+                "message": "No links available"
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(result, Err(LinksSchemaError::MissingLinks));
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_links_not_array() {
+        let json_data = json!({
+            "result": {
+                "links": "not an array"
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(result, Err(LinksSchemaError::LinksNotArray));
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_empty_links_array() {
+        let json_data = json!({
+            "result": {
+                "links": []
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(
+            result,
+            Err(LinksSchemaError::DownloadTypeNotFound(
+                "serverBedrockWindows".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_missing_download_type() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadUrl": "https://example.com/bedrock-server.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(
+            result,
+            Err(LinksSchemaError::DownloadTypeNotFound(
+                "serverBedrockWindows".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_missing_download_url() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(
+            result,
+            Err(LinksSchemaError::MissingDownloadUrl(
+                "serverBedrockWindows".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_download_url_not_string() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": 123
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(
+            result,
+            Err(LinksSchemaError::DownloadUrlNotString(
+                "serverBedrockWindows".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_multiple_matches_returns_first() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows-1.zip"
+                    },
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows-2.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-windows-1.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_complex_structure() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockWindows",
+                        "downloadUrl": "https://example.com/bedrock-server-windows.zip",
+                        "version": "1.20.0",
+                        "metadata": {
+                            "size": 123456789,
+                            "checksum": "abc123def456"
+                        }
+                    },
+                    {
+                        "downloadType": "serverBedrockLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-linux.zip",
+                        "version": "1.20.0",
+                        "metadata": {
+                            "size": 987654321,
+                            "checksum": "xyz789uvw012"
+                        }
+                    }
+                ],
+                "lastUpdated": "2025-07-04T12:00:00Z"
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Linux);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-linux.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_null_json() {
+        let json_data = serde_json::Value::Null;
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        assert_eq!(result, Err(LinksSchemaError::NotAnObject));
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_case_sensitive() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverbedrockwindows",  // lowercase
+                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+
+        // Should not match because the case differs
+        assert_eq!(
+            result,
+            Err(LinksSchemaError::DownloadTypeNotFound(
+                "serverBedrockWindows".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_top_level_links() {
+        let json_data = json!({
+            "links": [
+                {
+                    "downloadType": "serverBedrockLinux",
+                    "downloadUrl": " https://example.com/bedrock-server-linux.zip\n"
+                }
+            ]
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Linux);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-linux.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_url_from_json_skips_unusable_duplicate() {
+        let json_data = json!({
+            "result": {
+                "links": [
+                    {
+                        "downloadType": "serverBedrockLinux"
+                    },
+                    {
+                        "downloadType": "serverBedrockLinux",
+                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
+                    }
+                ]
+            }
+        });
+
+        let result = get_download_url_from_json(&json_data, &DownloadType::Linux);
+
+        assert_eq!(
+            result,
+            Ok("https://example.com/bedrock-server-linux.zip".to_string())
+        );
+    }
+
+    // Tests for scrape_download_links function
+    #[test]
+    fn test_scrape_download_links() {
+        let html = r#"
+            <a href="https://www.minecraft.net/bedrockdedicatedserver/bin-win/bedrock-server-1.21.92.1.zip">Windows</a>
+            <a href="https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-1.21.92.1.zip">Linux</a>
+            <a href='https://www.minecraft.net/bedrockdedicatedserver/bin-win-preview/bedrock-server-1.21.100.20.zip'>Preview</a>
+            <a href="https://www.minecraft.net/bedrockdedicatedserver/bin-linux-preview/bedrock-server-1.21.100.20.zip">Preview</a>
+            <a href="https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-1.21.92.1.zip">Duplicate</a>
+            <a href="https://www.minecraft.net/en-us/terms">Terms</a>
+        "#;
+
+        let result = scrape_download_links(html);
+
+        assert_eq!(result["result"]["links"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            get_download_url_from_json(&result, &DownloadType::Windows),
+            Ok(
+                "https://www.minecraft.net/bedrockdedicatedserver/bin-win/bedrock-server-1.21.92.1.zip"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            get_download_url_from_json(&result, &DownloadType::PreviewLinux),
+            Ok("https://www.minecraft.net/bedrockdedicatedserver/bin-linux-preview/bedrock-server-1.21.100.20.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_scrape_download_links_no_links() {
+        let result = scrape_download_links("<html><body>Nothing here</body></html>");
+
+        assert_eq!(result, json!({ "result": { "links": [] } }));
+    }
+
+    // Tests for links_endpoints function
+    #[test]
+    fn test_links_endpoints_defaults() {
+        let result = links_endpoints(&[], None, false);
+
+        assert_eq!(
+            result,
+            vec![
+                LinksEndpoint::Api(DEFAULT_LINKS_URL.to_owned()),
+                LinksEndpoint::DownloadPage(DEFAULT_DOWNLOAD_PAGE_URL.to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_endpoints_custom_first() {
+        let links_urls = vec![
+            "https://a.example.com/links".to_owned(),
+            "https://b.example.com/links".to_owned(),
+        ];
+
+        let result = links_endpoints(&links_urls, Some("https://example.com/download"), false);
+
+        assert_eq!(
+            result,
+            vec![
+                LinksEndpoint::Api("https://a.example.com/links".to_owned()),
+                LinksEndpoint::Api("https://b.example.com/links".to_owned()),
+                LinksEndpoint::Api(DEFAULT_LINKS_URL.to_owned()),
+                LinksEndpoint::DownloadPage("https://example.com/download".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_endpoints_no_fallback() {
+        let links_urls = vec!["https://example.com/links".to_owned()];
+
+        let result = links_endpoints(&links_urls, None, true);
+
+        assert_eq!(
+            result,
+            vec![LinksEndpoint::Api("https://example.com/links".to_owned())]
+        );
+    }
+
+    // Tests for ReleaseIndex::fetch_first
+    #[test]
+    fn test_fetch_first_falls_back_to_next_endpoint() {
+        let mut server = Server::new();
+        let broken_mock = server
+            .mock("GET", "/broken")
+            .with_status(500)
+            .with_body("Internal Server Error")
+            .create();
+        let wrong_schema_mock = server
+            .mock("GET", "/wrong-schema")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "downloads": [] }).to_string())
+            .create();
+        let page_mock = server
+            .mock("GET", "/download")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(
+                r#"<a href="https://example.com/bin-linux/bedrock-server-1.21.92.1.zip">Linux</a>"#,
+            )
+            .create();
+
+        let endpoints = vec![
+            LinksEndpoint::Api(format!("{}/broken", server.url())),
+            LinksEndpoint::Api(format!("{}/wrong-schema", server.url())),
+            LinksEndpoint::DownloadPage(format!("{}/download", server.url())),
+        ];
+        let result = ReleaseIndex::fetch_first(&test_client(), &endpoints, &DownloadType::Linux);
+
+        broken_mock.assert();
+        wrong_schema_mock.assert();
+        page_mock.assert();
+        let (index, release) = result.unwrap();
+        assert_eq!(index.source, format!("{}/download", server.url()));
+        assert_eq!(
+            release,
+            Release {
+                download_type: DownloadType::Linux,
+                download_url: "https://example.com/bin-linux/bedrock-server-1.21.92.1.zip"
+                    .to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_fetch_first_all_endpoints_fail() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/download")
+            .with_status(404)
+            .with_body("Not Found")
+            .create();
+
+        let endpoints = vec![LinksEndpoint::DownloadPage(format!(
+            "{}/download",
+            server.url()
+        ))];
+        let result = ReleaseIndex::fetch_first(&test_client(), &endpoints, &DownloadType::Linux);
+
+        mock.assert();
+        match result {
+            Err(Error::NoRelease {
+                download_type,
+                failures,
+            }) => {
+                assert_eq!(download_type, DownloadType::Linux);
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].0, format!("{}/download", server.url()));
+            }
+            other => panic!("expected NoRelease, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fetch_first_if_changed_not_modified() {
+        let mut server = Server::new();
+        let links = json!({ "result": { "links": [{
+            "downloadType": "serverBedrockLinux",
+            "downloadUrl": "https://example.com/bin-linux/bedrock-server-1.21.92.1.zip"
+        }] } });
+        let first_mock = server
+            .mock("GET", "/links")
+            .with_header("etag", "\"v1\"")
+            .with_body(links.to_string())
+            .create();
+        let endpoints = vec![LinksEndpoint::Api(format!("{}/links", server.url()))];
+
+        let (cached, _) =
+            ReleaseIndex::fetch_first(&test_client(), &endpoints, &DownloadType::Linux).unwrap();
+        first_mock.assert();
+        assert_eq!(
+            cached.validators.as_ref().and_then(|v| v.etag.as_deref()),
+            Some("\"v1\"")
+        );
+
+        let unchanged_mock = server
+            .mock("GET", "/links")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create();
+        let result = ReleaseIndex::fetch_first_if_changed(
+            &test_client(),
+            &endpoints,
+            &DownloadType::Linux,
+            &cached,
+        );
+
+        unchanged_mock.assert();
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_release_index_resolve() {
+        let index = ReleaseIndex {
+            source: "cache".to_owned(),
+            json: json!({ "links": [{
+                "downloadType": "serverBedrockLinux",
+                "downloadUrl": "https://example.com/bedrock-server-linux.zip"
+            }] }),
+            validators: None,
+        };
+
+        assert_eq!(
+            index.resolve(&DownloadType::Linux).unwrap().download_url,
+            "https://example.com/bedrock-server-linux.zip"
+        );
+        assert_eq!(
+            index.resolve(&DownloadType::Windows),
+            Err(LinksSchemaError::DownloadTypeNotFound(
+                "serverBedrockWindows".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_version_from_url() {
+        assert_eq!(
+            version_from_url(
+                "https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-1.21.92.1.zip"
+            ),
+            Some("1.21.92.1")
+        );
+        assert_eq!(
+            version_from_url("bedrock-server-1.21.100.6.zip"),
+            Some("1.21.100.6")
+        );
+        assert_eq!(
+            version_from_url("https://example.com/bedrock-server-linux"),
+            None
+        );
+    }
+}
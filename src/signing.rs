@@ -0,0 +1,211 @@
+use crate::error::{Error, Result};
+use crate::release::DownloadType;
+use crate::verify::{self, FileHashes};
+use clap::ValueEnum;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The hashes of every zip a mirror serves, next to its `links.json`.
+pub const RELEASES_FILE: &str = "releases.json";
+/// The detached ed25519 signature of [`RELEASES_FILE`], hex encoded.
+pub const SIGNATURE_FILE: &str = "releases.json.sig";
+
+/// The SHA-256 of every zip in a mirror, keyed by its path relative to the
+/// mirror, e.g. `serverBedrockLinux/bedrock-server-1.21.92.1.zip`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseHashes {
+    pub files: FileHashes,
+}
+
+pub fn generate_key() -> SigningKey {
+    SigningKey::generate(&mut rand_core::OsRng)
+}
+
+/// Writes the secret key, hex encoded, readable only by its owner. An existing
+/// key is never overwritten.
+pub fn write_secret_key(path: &Path, key: &SigningKey) -> Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    writeln!(file, "{}", to_hex(&key.to_bytes()))?;
+    Ok(())
+}
+
+pub fn read_secret_key(path: &Path) -> Result<SigningKey> {
+    let content = std::fs::read_to_string(path)?;
+    let bytes = from_hex(content.trim())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| Error::Other(format!("{} is not a secret key", path.display())))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+pub fn encode_public_key(key: &VerifyingKey) -> String {
+    to_hex(key.as_bytes())
+}
+
+/// Parses a hex encoded public key, as printed by `mirror keygen`.
+pub fn parse_public_key(hex: &str) -> Result<VerifyingKey> {
+    from_hex(hex.trim())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| Error::Other(format!("invalid public key {}", hex)))
+}
+
+/// Hashes every zip in the mirror directory and writes [`RELEASES_FILE`]
+/// with its signature.
+pub fn sign_dir(dir: &Path, key: &SigningKey) -> Result<ReleaseHashes> {
+    let mut hashes = ReleaseHashes::default();
+    for download_type in DownloadType::value_variants() {
+        let entries = match std::fs::read_dir(dir.join(download_type.to_string())) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if !file_name.ends_with(".zip") || !entry.file_type()?.is_file() {
+                continue;
+            }
+            let hash = verify::hash_reader(std::fs::File::open(entry.path())?)?;
+            hashes
+                .files
+                .insert(format!("{}/{}", download_type, file_name), hash);
+        }
+    }
+
+    let manifest = serde_json::to_vec_pretty(&hashes)?;
+    let signature = key.sign(&manifest);
+    write_atomically(&dir.join(RELEASES_FILE), &manifest)?;
+    write_atomically(
+        &dir.join(SIGNATURE_FILE),
+        to_hex(&signature.to_bytes()).as_bytes(),
+    )?;
+    Ok(hashes)
+}
+
+/// Checks `signature` over the raw `manifest` before parsing it.
+pub fn verify_signed(
+    manifest: &[u8],
+    signature: &str,
+    key: &VerifyingKey,
+) -> Result<ReleaseHashes> {
+    let signature = from_hex(signature.trim())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| Error::Signature(format!("{} is malformed", SIGNATURE_FILE)))?;
+    key.verify(manifest, &signature).map_err(|_| {
+        Error::Signature(format!(
+            "{} is not signed by the trusted key",
+            RELEASES_FILE
+        ))
+    })?;
+    Ok(serde_json::from_slice(manifest)?)
+}
+
+/// Checks the downloaded zip against the signed hash of `relative`.
+pub fn check_zip(hashes: &ReleaseHashes, relative: &str, zip_path: &Path) -> Result<()> {
+    let expected = hashes
+        .files
+        .get(relative)
+        .ok_or_else(|| Error::Signature(format!("{} is not in {}", relative, RELEASES_FILE)))?;
+    let actual = verify::hash_reader(std::fs::File::open(zip_path)?)?;
+    if actual != *expected {
+        return Err(Error::Signature(format!(
+            "{} does not match its signed hash",
+            relative
+        )));
+    }
+    Ok(())
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let linux_dir = temp_dir.path().join("serverBedrockLinux");
+        std::fs::create_dir(&linux_dir).unwrap();
+        let zip_path = linux_dir.join("bedrock-server-1.21.92.1.zip");
+        std::fs::write(&zip_path, "abc").unwrap();
+        std::fs::write(linux_dir.join("bedrock-server-1.21.93.1.zip.part"), "").unwrap();
+        let key = generate_key();
+        let relative = "serverBedrockLinux/bedrock-server-1.21.92.1.zip";
+
+        let hashes = sign_dir(temp_dir.path(), &key).unwrap();
+
+        assert_eq!(
+            hashes.files,
+            FileHashes::from([(
+                relative.to_owned(),
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_owned()
+            )])
+        );
+        let manifest = std::fs::read(temp_dir.path().join(RELEASES_FILE)).unwrap();
+        let signature = std::fs::read_to_string(temp_dir.path().join(SIGNATURE_FILE)).unwrap();
+        let public_key = parse_public_key(&encode_public_key(&key.verifying_key())).unwrap();
+        let verified = verify_signed(&manifest, &signature, &public_key).unwrap();
+        assert_eq!(verified, hashes);
+        check_zip(&verified, relative, &zip_path).unwrap();
+
+        std::fs::write(&zip_path, "tampered").unwrap();
+        assert!(matches!(
+            check_zip(&verified, relative, &zip_path),
+            Err(Error::Signature(_))
+        ));
+        let mut tampered = manifest.clone();
+        tampered[0] = b' ';
+        assert!(matches!(
+            verify_signed(&tampered, &signature, &public_key),
+            Err(Error::Signature(_))
+        ));
+        let other_key = generate_key().verifying_key();
+        assert!(matches!(
+            verify_signed(&manifest, &signature, &other_key),
+            Err(Error::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn test_secret_key_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("mirror.key");
+        let key = generate_key();
+
+        write_secret_key(&path, &key).unwrap();
+
+        assert_eq!(read_secret_key(&path).unwrap(), key);
+        assert!(write_secret_key(&path, &generate_key()).is_err());
+        assert!(parse_public_key("not hex").is_err());
+    }
+}
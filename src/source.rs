@@ -0,0 +1,657 @@
+use crate::error::{Error, Result};
+use crate::fingerprint::{KNOWN_VERSIONS_FILE, KnownVersions};
+use crate::http::HttpClient;
+use crate::release::{DownloadType, LinksEndpoint, Release, ReleaseIndex};
+use crate::signing::{self, ReleaseHashes};
+use crate::updater;
+use crate::version::Version;
+use clap::ValueEnum;
+use ed25519_dalek::VerifyingKey;
+use reqwest::Url;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Where a server's releases come from.
+pub trait ReleaseSource {
+    /// Names the source in messages.
+    fn describe(&self) -> String;
+
+    /// The newest release for `download_type`, with the index it was read from
+    /// so it can be cached.
+    fn latest(&self, download_type: &DownloadType) -> Result<(ReleaseIndex, Release)>;
+
+    /// Like [`ReleaseSource::latest`], but returns `None` when the source can
+    /// tell the index is unchanged since `cached` was read from it.
+    fn latest_if_changed(
+        &self,
+        download_type: &DownloadType,
+        _cached: &ReleaseIndex,
+    ) -> Result<Option<(ReleaseIndex, Release)>> {
+        self.latest(download_type).map(Some)
+    }
+
+    /// Puts the release's zip in a temporary file the caller may delete.
+    fn fetch(&self, release: &Release) -> Result<PathBuf>;
+
+    /// The server binaries of the releases the source knows about, to identify
+    /// servers installed without bedrock-up. Releases whose version `known`
+    /// already has may be left out. Empty when it can't tell.
+    fn known_versions(&self, _known: &KnownVersions) -> Result<KnownVersions> {
+        Ok(KnownVersions::default())
+    }
+}
+
+/// The official links API, falling back along a chain of endpoints.
+pub struct OfficialSource {
+    client: HttpClient,
+    endpoints: Vec<LinksEndpoint>,
+}
+
+impl OfficialSource {
+    pub fn new(client: HttpClient, endpoints: Vec<LinksEndpoint>) -> OfficialSource {
+        OfficialSource { client, endpoints }
+    }
+}
+
+impl ReleaseSource for OfficialSource {
+    fn describe(&self) -> String {
+        "the official links API".to_owned()
+    }
+
+    fn latest(&self, download_type: &DownloadType) -> Result<(ReleaseIndex, Release)> {
+        ReleaseIndex::fetch_first(&self.client, &self.endpoints, download_type)
+    }
+
+    fn latest_if_changed(
+        &self,
+        download_type: &DownloadType,
+        cached: &ReleaseIndex,
+    ) -> Result<Option<(ReleaseIndex, Release)>> {
+        ReleaseIndex::fetch_first_if_changed(&self.client, &self.endpoints, download_type, cached)
+    }
+
+    fn fetch(&self, release: &Release) -> Result<PathBuf> {
+        updater::fetch_update_zip(&self.client, &release.download_url)
+    }
+}
+
+/// A JSON index in the links API format served from any URL. Download urls
+/// may be relative to the index.
+pub struct IndexSource {
+    client: HttpClient,
+    url: Url,
+}
+
+impl IndexSource {
+    pub fn new(client: HttpClient, url: &str) -> Result<IndexSource> {
+        let url = Url::parse(url)
+            .map_err(|e| Error::Other(format!("invalid index url {}: {}", url, e)))?;
+        Ok(IndexSource { client, url })
+    }
+
+    fn endpoint(&self) -> LinksEndpoint {
+        LinksEndpoint::Api(self.url.to_string())
+    }
+}
+
+impl ReleaseSource for IndexSource {
+    fn describe(&self) -> String {
+        format!("the release index at {}", self.url)
+    }
+
+    fn latest(&self, download_type: &DownloadType) -> Result<(ReleaseIndex, Release)> {
+        let index = ReleaseIndex::fetch(&self.client, &self.endpoint())?;
+        let release = index.resolve(download_type).map_err(Error::Schema)?;
+        Ok((index, release))
+    }
+
+    fn latest_if_changed(
+        &self,
+        download_type: &DownloadType,
+        cached: &ReleaseIndex,
+    ) -> Result<Option<(ReleaseIndex, Release)>> {
+        let Some(index) = ReleaseIndex::fetch_if_changed(&self.client, &self.endpoint(), cached)?
+        else {
+            return Ok(None);
+        };
+        let release = index.resolve(download_type).map_err(Error::Schema)?;
+        Ok(Some((index, release)))
+    }
+
+    fn fetch(&self, release: &Release) -> Result<PathBuf> {
+        updater::fetch_update_zip(&self.client, &release.download_url)
+    }
+}
+
+/// A fleet mirror serving a rewritten links index at `<url>/links.json` next
+/// to the zips it lists.
+pub struct MirrorSource {
+    index: IndexSource,
+    base: Url,
+    /// When set, every zip must match the mirror's signed release hashes.
+    public_key: Option<VerifyingKey>,
+}
+
+/// Where a mirror serves its links index, relative to its base url.
+pub const MIRROR_INDEX_PATH: &str = "links.json";
+
+impl MirrorSource {
+    pub fn new(client: HttpClient, url: &str) -> Result<MirrorSource> {
+        let base = if url.ends_with('/') {
+            url.to_owned()
+        } else {
+            format!("{}/", url)
+        };
+        let index = IndexSource::new(client, &format!("{}{}", base, MIRROR_INDEX_PATH))?;
+        let base = index
+            .url
+            .join(".")
+            .map_err(|e| Error::Other(format!("invalid mirror url {}: {}", url, e)))?;
+        Ok(MirrorSource {
+            index,
+            base,
+            public_key: None,
+        })
+    }
+
+    /// Only accepts zips listed in the mirror's release hashes signed by `key`.
+    pub fn with_public_key(mut self, key: VerifyingKey) -> MirrorSource {
+        self.public_key = Some(key);
+        self
+    }
+
+    fn signed_hashes(&self, key: &VerifyingKey) -> Result<ReleaseHashes> {
+        let get = |name: &str| -> Result<Vec<u8>> {
+            let url = self
+                .base
+                .join(name)
+                .map_err(|e| Error::Other(e.to_string()))?;
+            let resp = self.index.client.get(url.as_str())?.error_for_status()?;
+            Ok(resp.bytes()?.to_vec())
+        };
+        let manifest = get(signing::RELEASES_FILE)?;
+        let signature = String::from_utf8_lossy(&get(signing::SIGNATURE_FILE)?).into_owned();
+        signing::verify_signed(&manifest, &signature, key)
+    }
+}
+
+impl ReleaseSource for MirrorSource {
+    fn describe(&self) -> String {
+        format!("the mirror at {}", self.index.url)
+    }
+
+    fn latest(&self, download_type: &DownloadType) -> Result<(ReleaseIndex, Release)> {
+        self.index.latest(download_type)
+    }
+
+    fn latest_if_changed(
+        &self,
+        download_type: &DownloadType,
+        cached: &ReleaseIndex,
+    ) -> Result<Option<(ReleaseIndex, Release)>> {
+        self.index.latest_if_changed(download_type, cached)
+    }
+
+    fn fetch(&self, release: &Release) -> Result<PathBuf> {
+        let Some(key) = &self.public_key else {
+            return self.index.fetch(release);
+        };
+        let relative = release
+            .download_url
+            .strip_prefix(self.base.as_str())
+            .ok_or_else(|| {
+                Error::Signature(format!(
+                    "{} is not served by the mirror",
+                    release.download_url
+                ))
+            })?;
+        let hashes = self.signed_hashes(key)?;
+        let zip_path = self.index.fetch(release)?;
+        if let Err(e) = signing::check_zip(&hashes, relative, &zip_path) {
+            std::fs::remove_file(&zip_path)?;
+            return Err(e);
+        }
+        Ok(zip_path)
+    }
+
+    fn known_versions(&self, _known: &KnownVersions) -> Result<KnownVersions> {
+        // The index isn't covered by the signature, so a signed mirror's is
+        // not trusted to name the installed version.
+        if self.public_key.is_some() {
+            return Ok(KnownVersions::default());
+        }
+        let url = self
+            .base
+            .join(KNOWN_VERSIONS_FILE)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        let resp = self.index.client.get(url.as_str())?;
+        // Mirrors synced before the index existed don't serve it.
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(KnownVersions::default());
+        }
+        Ok(serde_json::from_slice(&resp.error_for_status()?.bytes()?)?)
+    }
+}
+
+/// A local directory of server zips, one subdirectory per download type named
+/// like the links API does (`serverBedrockLinux/bedrock-server-1.21.92.1.zip`).
+/// The highest version wins.
+pub struct DirectorySource {
+    path: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(path: &str) -> DirectorySource {
+        DirectorySource {
+            path: PathBuf::from(shellexpand::tilde(path).to_string()),
+        }
+    }
+
+    fn newest_zip(&self, download_type: &DownloadType) -> Result<Option<PathBuf>> {
+        let dir = self.path.join(download_type.to_string());
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut newest: Option<(Version, PathBuf)> = None;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(version) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(zip_version)
+            else {
+                continue;
+            };
+            if newest.as_ref().is_none_or(|(newest, _)| version > *newest) {
+                newest = Some((version, path));
+            }
+        }
+        Ok(newest.map(|(_, path)| path))
+    }
+}
+
+impl ReleaseSource for DirectorySource {
+    fn describe(&self) -> String {
+        format!("the directory {}", self.path.display())
+    }
+
+    fn latest(&self, download_type: &DownloadType) -> Result<(ReleaseIndex, Release)> {
+        // Index every download type so a shared cache stays usable for all.
+        let mut links = Vec::new();
+        for candidate in DownloadType::value_variants() {
+            if let Some(zip) = self.newest_zip(candidate)? {
+                links.push(serde_json::json!({
+                    "downloadType": candidate.to_string(),
+                    "downloadUrl": file_url(&zip)?,
+                }));
+            }
+        }
+
+        let index = ReleaseIndex {
+            source: self.path.display().to_string(),
+            json: serde_json::json!({ "result": { "links": links } }),
+            validators: None,
+        };
+        let release = index.resolve(download_type).map_err(Error::Schema)?;
+        Ok((index, release))
+    }
+
+    fn fetch(&self, release: &Release) -> Result<PathBuf> {
+        let source = Url::parse(&release.download_url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(|| Error::Other(format!("not a file url: {}", release.download_url)))?;
+        let file_name = source.file_name().unwrap_or_default();
+        // Copy so the caller can delete the zip without emptying the directory.
+        let file_path = std::env::temp_dir().join(file_name);
+        std::fs::copy(&source, &file_path)?;
+        Ok(file_path)
+    }
+
+    fn known_versions(&self, known: &KnownVersions) -> Result<KnownVersions> {
+        let mut found = KnownVersions::default();
+        for download_type in DownloadType::value_variants() {
+            let entries = match std::fs::read_dir(self.path.join(download_type.to_string())) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for entry in entries {
+                let path = entry?.path();
+                let Some(version) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(crate::release::version_from_url)
+                else {
+                    continue;
+                };
+                // Hashing every zip on every run is slow in a large directory.
+                if !known.has_version(version) {
+                    found.record_zip(&path, version)?;
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+fn file_url(path: &Path) -> Result<String> {
+    let path = std::path::absolute(path)?;
+    Url::from_file_path(&path)
+        .map(|url| url.to_string())
+        .map_err(|_| Error::Other(format!("cannot make a url of {}", path.display())))
+}
+
+/// The version in a `bedrock-server-<version>.zip` file name, as numbers.
+fn zip_version(file_name: &str) -> Option<Version> {
+    crate::release::version_from_url(file_name)?.parse().ok()
+}
+
+/// Which release source a server uses, as written in the config file or given
+/// on the command line as `official`, `dir:<path>`, `index:<url>` or
+/// `mirror:<url>`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SourceConfig {
+    #[default]
+    Official,
+    Directory {
+        path: String,
+    },
+    Index {
+        url: String,
+    },
+    Mirror {
+        url: String,
+        /// Hex encoded ed25519 key the mirror's release hashes must be signed
+        /// with, as printed by `mirror keygen`.
+        #[serde(default, rename = "publicKey")]
+        public_key: Option<String>,
+    },
+}
+
+impl std::str::FromStr for SourceConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        match spec.split_once(':') {
+            _ if spec == "official" => Ok(SourceConfig::Official),
+            Some(("dir", path)) => Ok(SourceConfig::Directory {
+                path: path.to_owned(),
+            }),
+            Some(("index", url)) => Ok(SourceConfig::Index {
+                url: url.to_owned(),
+            }),
+            Some(("mirror", url)) => Ok(SourceConfig::Mirror {
+                url: url.to_owned(),
+                public_key: None,
+            }),
+            _ => Err(format!(
+                "unknown source {}, expected official, dir:<path>, index:<url> or mirror:<url>",
+                spec
+            )),
+        }
+    }
+}
+
+impl SourceConfig {
+    /// Builds the source. `endpoints` is the fallback chain the official
+    /// source walks.
+    pub fn build(
+        &self,
+        client: HttpClient,
+        endpoints: Vec<LinksEndpoint>,
+    ) -> Result<Box<dyn ReleaseSource>> {
+        Ok(match self {
+            SourceConfig::Official => Box::new(OfficialSource::new(client, endpoints)),
+            SourceConfig::Directory { path } => Box::new(DirectorySource::new(path)),
+            SourceConfig::Index { url } => Box::new(IndexSource::new(client, url)?),
+            SourceConfig::Mirror { url, public_key } => {
+                let source = MirrorSource::new(client, url)?;
+                match public_key {
+                    Some(key) => Box::new(source.with_public_key(signing::parse_public_key(key)?)),
+                    None => Box::new(source),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_client, write_zip};
+    use crate::verify::FileHashes;
+    use mockito::Server;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_directory_source_picks_highest_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let linux_dir = temp_dir.path().join("serverBedrockLinux");
+        std::fs::create_dir_all(&linux_dir).unwrap();
+        for name in [
+            "bedrock-server-1.21.9.1.zip",
+            "bedrock-server-1.21.92.1.zip",
+            "bedrock-server-1.21.10.0.zip",
+            "notes.txt",
+        ] {
+            std::fs::write(linux_dir.join(name), name).unwrap();
+        }
+        let source = DirectorySource::new(temp_dir.path().to_str().unwrap());
+
+        let (index, release) = source.latest(&DownloadType::Linux).unwrap();
+
+        assert!(release.download_url.starts_with("file://"));
+        assert!(
+            release
+                .download_url
+                .ends_with("/serverBedrockLinux/bedrock-server-1.21.92.1.zip")
+        );
+        assert_eq!(index.resolve(&DownloadType::Linux).unwrap(), release);
+
+        let zip_path = source.fetch(&release).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&zip_path).unwrap(),
+            "bedrock-server-1.21.92.1.zip"
+        );
+        std::fs::remove_file(zip_path).unwrap();
+        // The directory keeps its copy
+        assert!(linux_dir.join("bedrock-server-1.21.92.1.zip").exists());
+    }
+
+    #[test]
+    fn test_directory_source_missing_download_type() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = DirectorySource::new(temp_dir.path().to_str().unwrap());
+
+        let result = source.latest(&DownloadType::Windows);
+
+        assert!(matches!(
+            result,
+            Err(Error::Schema(
+                crate::release::LinksSchemaError::DownloadTypeNotFound(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_directory_source_hashes_unknown_versions_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let linux_dir = temp_dir.path().join("serverBedrockLinux");
+        std::fs::create_dir_all(&linux_dir).unwrap();
+        write_zip(
+            &linux_dir.join("bedrock-server-1.21.92.1.zip"),
+            &[("bedrock_server", "new binary")],
+        );
+        // Not a zip, so reading it fails unless it is skipped.
+        std::fs::write(linux_dir.join("bedrock-server-1.21.90.3.zip"), "old").unwrap();
+        let source = DirectorySource::new(temp_dir.path().to_str().unwrap());
+        let server_path = temp_dir.path().join("server");
+        std::fs::create_dir(&server_path).unwrap();
+        std::fs::write(server_path.join("bedrock_server"), "new binary").unwrap();
+
+        assert!(source.known_versions(&KnownVersions::default()).is_err());
+
+        let mut known = KnownVersions::default();
+        let files = FileHashes::from([(
+            "bedrock_server".to_owned(),
+            crate::verify::hash_reader(&b"old binary"[..]).unwrap(),
+        )]);
+        known.record_files(&files, "1.21.90.3");
+        let found = source.known_versions(&known).unwrap();
+
+        assert_eq!(
+            found.identify(&server_path).unwrap().as_deref(),
+            Some("1.21.92.1")
+        );
+        assert!(!found.has_version("1.21.90.3"));
+    }
+
+    #[test]
+    fn test_index_source_resolves_relative_urls() {
+        let mut server = Server::new();
+        let index_mock = server
+            .mock("GET", "/releases/index.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "links": [{
+                    "downloadType": "serverBedrockLinux",
+                    "downloadUrl": "linux/bedrock-server-1.21.92.1.zip"
+                }] })
+                .to_string(),
+            )
+            .create();
+        let zip_mock = server
+            .mock("GET", "/releases/linux/bedrock-server-1.21.92.1.zip")
+            .with_status(200)
+            .with_body("zip")
+            .create();
+        let source = IndexSource::new(
+            test_client(),
+            &format!("{}/releases/index.json", server.url()),
+        )
+        .unwrap();
+
+        let (_, release) = source.latest(&DownloadType::Linux).unwrap();
+        let zip_path = source.fetch(&release).unwrap();
+
+        index_mock.assert();
+        zip_mock.assert();
+        assert_eq!(
+            release.download_url,
+            format!(
+                "{}/releases/linux/bedrock-server-1.21.92.1.zip",
+                server.url()
+            )
+        );
+        assert_eq!(std::fs::read_to_string(&zip_path).unwrap(), "zip");
+        std::fs::remove_file(zip_path).unwrap();
+    }
+
+    #[test]
+    fn test_official_source_resolves_mirror_links_json() {
+        let mut server = Server::new();
+        let links_mock = server
+            .mock("GET", "/mirror/links.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "result": { "links": [{
+                    "downloadType": "serverBedrockLinux",
+                    "downloadUrl": "linux/bedrock-server-1.21.92.1.zip"
+                }] } })
+                .to_string(),
+            )
+            .create();
+        let zip_mock = server
+            .mock("GET", "/mirror/linux/bedrock-server-1.21.92.1.zip")
+            .with_status(200)
+            .with_body("zip")
+            .create();
+        let source = OfficialSource::new(
+            test_client(),
+            vec![LinksEndpoint::Api(format!(
+                "{}/mirror/links.json",
+                server.url()
+            ))],
+        );
+
+        let (index, release) = source.latest(&DownloadType::Linux).unwrap();
+        let zip_path = source.fetch(&release).unwrap();
+
+        links_mock.assert();
+        zip_mock.assert();
+        assert_eq!(
+            release.download_url,
+            format!("{}/mirror/linux/bedrock-server-1.21.92.1.zip", server.url())
+        );
+        assert_eq!(index.resolve(&DownloadType::Linux).unwrap(), release);
+        assert_eq!(std::fs::read_to_string(&zip_path).unwrap(), "zip");
+        std::fs::remove_file(zip_path).unwrap();
+    }
+
+    #[test]
+    fn test_mirror_source_reads_links_json() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/fleet/links.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "result": { "links": [{
+                    "downloadType": "serverBedrockWindows",
+                    "downloadUrl": "/fleet/zips/bedrock-server-1.21.92.1.zip"
+                }] } })
+                .to_string(),
+            )
+            .create();
+        let source = MirrorSource::new(test_client(), &format!("{}/fleet", server.url())).unwrap();
+
+        let (_, release) = source.latest(&DownloadType::Windows).unwrap();
+
+        mock.assert();
+        assert_eq!(
+            release.download_url,
+            format!("{}/fleet/zips/bedrock-server-1.21.92.1.zip", server.url())
+        );
+    }
+
+    #[test]
+    fn test_source_config_from_str() {
+        assert_eq!("official".parse(), Ok(SourceConfig::Official));
+        assert_eq!(
+            "dir:/srv/zips".parse(),
+            Ok(SourceConfig::Directory {
+                path: "/srv/zips".to_owned()
+            })
+        );
+        assert_eq!(
+            "mirror:http://mirror.lan:8080".parse(),
+            Ok(SourceConfig::Mirror {
+                url: "http://mirror.lan:8080".to_owned(),
+                public_key: None,
+            })
+        );
+        assert!("ftp:example.com".parse::<SourceConfig>().is_err());
+    }
+
+    #[test]
+    fn test_source_config_deserialize() {
+        let result: SourceConfig =
+            serde_json::from_value(json!({ "type": "index", "url": "https://example.com/i.json" }))
+                .unwrap();
+
+        assert_eq!(
+            result,
+            SourceConfig::Index {
+                url: "https://example.com/i.json".to_owned()
+            }
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::error::{Error, Result};
+use crate::http::Validators;
+use crate::release::{DownloadType, Release, ReleaseIndex};
+use crate::updater::{self, UpdatePlan};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Where a staged release is kept, inside the server directory so applying it
+/// only has to rename files.
+pub const STAGE_DIR: &str = ".bedrock-up-staged";
+const STAGED_FILES: &str = "files";
+/// Written last, a stage without it was interrupted and is not used.
+const STAGED_INFO: &str = "staged.json";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StagedInfo {
+    download_type: String,
+    download_url: String,
+    source: String,
+    index: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    validators: Option<Validators>,
+    staged_at: DateTime<Utc>,
+}
+
+/// A release downloaded and extracted ahead of time, ready to be applied.
+#[derive(Debug)]
+pub struct Staged {
+    pub dir: PathBuf,
+    pub release: Release,
+    /// The index the release was found in, saved to the cache once applied.
+    pub index: ReleaseIndex,
+    pub staged_at: DateTime<Utc>,
+}
+
+impl Staged {
+    /// Plans moving the staged files into the server, keeping existing
+    /// excluded files.
+    pub fn plan(&self, server_path: &str, exclude: &[String]) -> Result<UpdatePlan> {
+        updater::plan_staged(server_path, &self.dir.join(STAGED_FILES), exclude)
+    }
+
+    /// Deletes the staging area.
+    pub fn discard(&self) -> Result<()> {
+        discard(&self.dir)
+    }
+}
+
+/// Extracts `zip_path` into the staging area of the server at `server_path`,
+/// replacing anything staged before. Every entry's checksum is verified while
+/// extracting, so a corrupt download fails here rather than during `apply`.
+pub fn stage(
+    server_path: &Path,
+    zip_path: &Path,
+    release: &Release,
+    index: &ReleaseIndex,
+) -> Result<Staged> {
+    let dir = server_path.join(STAGE_DIR);
+    discard(&dir)?;
+    let files = dir.join(STAGED_FILES);
+    std::fs::create_dir_all(&files)?;
+
+    let extracted = updater::plan_update(&files.to_string_lossy(), zip_path, &[])
+        .and_then(|plan| updater::apply_update(&plan, None));
+    if let Err(e) = extracted {
+        discard(&dir)?;
+        return Err(e);
+    }
+
+    let staged = Staged {
+        dir,
+        release: release.clone(),
+        index: index.clone(),
+        staged_at: Utc::now(),
+    };
+    let info = StagedInfo {
+        download_type: release.download_type.to_string(),
+        download_url: release.download_url.clone(),
+        source: index.source.clone(),
+        index: index.json.clone(),
+        validators: index.validators.clone(),
+        staged_at: staged.staged_at,
+    };
+    std::fs::write(
+        staged.dir.join(STAGED_INFO),
+        serde_json::to_vec_pretty(&info)?,
+    )?;
+    Ok(staged)
+}
+
+/// The release staged for the server at `server_path`, if there is one.
+pub fn read_staged(server_path: &Path) -> Result<Option<Staged>> {
+    let dir = server_path.join(STAGE_DIR);
+    let info: StagedInfo = match std::fs::read(dir.join(STAGED_INFO)) {
+        Ok(content) => serde_json::from_slice(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let download_type = DownloadType::from_links_name(&info.download_type).ok_or_else(|| {
+        Error::Other(format!(
+            "unknown download type {} in the staged release",
+            info.download_type
+        ))
+    })?;
+
+    Ok(Some(Staged {
+        dir,
+        release: Release {
+            download_type,
+            download_url: info.download_url,
+        },
+        index: ReleaseIndex {
+            source: info.source,
+            json: info.index,
+            validators: info.validators,
+        },
+        staged_at: info.staged_at,
+    }))
+}
+
+fn discard(dir: &Path) -> Result<()> {
+    match std::fs::remove_dir_all(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::create_test_zip;
+
+    #[test]
+    fn test_stage_then_apply() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir(&server_dir).unwrap();
+        std::fs::write(server_dir.join("server.properties"), "custom").unwrap();
+        std::fs::write(server_dir.join("bedrock_server"), "old").unwrap();
+        let zip_path = create_test_zip(
+            temp_dir.path(),
+            &[
+                ("bedrock_server", "new"),
+                ("server.properties", "default"),
+                ("behavior_packs/vanilla/manifest.json", "{}"),
+            ],
+        );
+        let release = Release {
+            download_type: DownloadType::Linux,
+            download_url: "https://example.com/bedrock-server-1.21.92.1.zip".to_owned(),
+        };
+        let index = ReleaseIndex {
+            source: "https://example.com/links".to_owned(),
+            json: serde_json::json!({ "result": { "links": [] } }),
+            validators: None,
+        };
+
+        stage(&server_dir, &zip_path, &release, &index).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("bedrock_server")).unwrap(),
+            "old"
+        );
+
+        let staged = read_staged(&server_dir).unwrap().unwrap();
+        assert_eq!(staged.release, release);
+        assert_eq!(staged.index.json, index.json);
+
+        let server_path = server_dir.to_str().unwrap();
+        let plan = staged
+            .plan(server_path, &["server.properties".to_owned()])
+            .unwrap();
+        updater::apply_update(&plan, None).unwrap();
+        staged.discard().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("bedrock_server")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("server.properties")).unwrap(),
+            "custom"
+        );
+        assert!(
+            server_dir
+                .join("behavior_packs/vanilla/manifest.json")
+                .exists()
+        );
+        assert!(!server_dir.join(STAGE_DIR).exists());
+        assert!(read_staged(&server_dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stage_corrupt_zip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("bedrock-server-1.21.92.1.zip");
+        std::fs::write(&zip_path, "not a zip").unwrap();
+        let release = Release {
+            download_type: DownloadType::Linux,
+            download_url: "https://example.com/bedrock-server-1.21.92.1.zip".to_owned(),
+        };
+        let index = ReleaseIndex {
+            source: "cache".to_owned(),
+            json: serde_json::Value::Null,
+            validators: None,
+        };
+
+        let result = stage(temp_dir.path(), &zip_path, &release, &index);
+
+        assert!(result.is_err());
+        assert!(read_staged(temp_dir.path()).unwrap().is_none());
+    }
+}
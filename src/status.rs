@@ -0,0 +1,176 @@
+use crate::fingerprint::KnownVersions;
+use crate::manifest::{self, Manifest};
+use crate::raknet::{self, ServerStatus};
+use crate::{Release, properties};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The state of one server directory and the server running from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerReport {
+    pub server_path: PathBuf,
+    /// The installed version, from the manifest, else the known version of the
+    /// server binary, else the update cache.
+    pub installed_version: Option<String>,
+    /// When the last update was applied, only known from the manifest.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// What the server answered to a status ping, `None` when it isn't running.
+    pub running: Option<ServerStatus>,
+    pub disk_usage: u64,
+}
+
+impl ServerReport {
+    /// Inspects the server at `server_path`. When the server has no manifest
+    /// yet, its binary is looked up in `known`, and else `cached`, the release
+    /// the update cache records, is assumed.
+    pub fn collect(
+        server_path: &Path,
+        known: &KnownVersions,
+        cached: Option<&Release>,
+    ) -> std::io::Result<ServerReport> {
+        let manifest = manifest::read_manifest(server_path)?;
+        let installed_version = match &manifest {
+            Some(manifest) => manifest.version.clone(),
+            None => known
+                .identify(server_path)
+                .ok()
+                .flatten()
+                .or_else(|| cached.and_then(|release| release.version().map(str::to_owned))),
+        };
+        let port = properties::server_port(server_path);
+
+        Ok(ServerReport {
+            server_path: server_path.to_path_buf(),
+            installed_version,
+            updated_at: manifest.map(|Manifest { installed_at, .. }| installed_at),
+            running: raknet::ping(("127.0.0.1", port), PING_TIMEOUT).ok(),
+            disk_usage: disk_usage(server_path)?,
+        })
+    }
+
+    /// Whether the running server is older than the installed files, which
+    /// happens until it is restarted after an update.
+    pub fn restart_pending(&self) -> bool {
+        match (&self.installed_version, &self.running) {
+            (Some(installed), Some(running)) => !same_version(installed, &running.version),
+            _ => false,
+        }
+    }
+}
+
+/// Servers report three parts of the version, `1.21.92` for the
+/// `1.21.92.1` build, so only the parts both have are compared.
+fn same_version(installed: &str, running: &str) -> bool {
+    installed == running
+        || installed
+            .strip_prefix(running)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Bytes taken up by the files under `path`. Symlinks are not followed.
+pub fn disk_usage(path: &Path) -> std::io::Result<u64> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Formats a byte count for people, e.g. `1.5 GiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::release::DownloadType;
+
+    fn status(version: &str) -> ServerStatus {
+        ServerStatus {
+            edition: "MCPE".to_owned(),
+            motd: "Dedicated Server".to_owned(),
+            protocol: 818,
+            version: version.to_owned(),
+            players: 0,
+            max_players: 10,
+            server_id: "1".to_owned(),
+            level_name: None,
+            game_mode: None,
+            port: None,
+            port_v6: None,
+        }
+    }
+
+    #[test]
+    fn test_restart_pending() {
+        let mut report = ServerReport {
+            server_path: PathBuf::from("/srv/bedrock"),
+            installed_version: Some("1.21.92.1".to_owned()),
+            updated_at: None,
+            running: Some(status("1.21.92")),
+            disk_usage: 0,
+        };
+        assert!(!report.restart_pending());
+
+        report.running = Some(status("1.21.9"));
+        assert!(report.restart_pending());
+
+        report.running = None;
+        assert!(!report.restart_pending());
+    }
+
+    #[test]
+    fn test_collect_without_manifest() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("bedrock_server"), [0; 100]).unwrap();
+        std::fs::create_dir(temp_dir.path().join("worlds")).unwrap();
+        std::fs::write(temp_dir.path().join("worlds").join("level.dat"), [0; 24]).unwrap();
+        let cached = Release {
+            download_type: DownloadType::Linux,
+            download_url: "https://example.com/bedrock-server-1.21.92.1.zip".to_owned(),
+        };
+
+        let report =
+            ServerReport::collect(temp_dir.path(), &KnownVersions::default(), Some(&cached))
+                .unwrap();
+
+        assert_eq!(report.installed_version.as_deref(), Some("1.21.92.1"));
+        assert_eq!(report.updated_at, None);
+        assert_eq!(report.disk_usage, 124);
+
+        let mut known = KnownVersions::default();
+        let binary = crate::verify::hash_reader(&[0; 100][..]).unwrap();
+        known.record_files(
+            &crate::verify::FileHashes::from([("bedrock_server".to_owned(), binary)]),
+            "1.21.84.1",
+        );
+        let report = ServerReport::collect(temp_dir.path(), &known, Some(&cached)).unwrap();
+        assert_eq!(report.installed_version.as_deref(), Some("1.21.84.1"));
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a server gets to shut down after `stop` before it is killed.
+pub const DEFAULT_STOP_TIMEOUT_SECS: u64 = 30;
+
+/// Sends console commands to whichever server process is currently running.
+/// Clones share the same server, so every console input can hold one.
+#[derive(Clone, Default)]
+pub struct ConsoleInput(Arc<Mutex<Option<ChildStdin>>>);
+
+impl ConsoleInput {
+    pub fn send(&self, command: &str) -> std::io::Result<()> {
+        let mut stdin = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let stdin = stdin.as_mut().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "the server is not running",
+            )
+        })?;
+        writeln!(stdin, "{}", command)?;
+        stdin.flush()
+    }
+
+    fn connect(&self, stdin: Option<ChildStdin>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = stdin;
+    }
+}
+
+/// What waiting for console output turned up.
+#[derive(Debug, PartialEq)]
+pub enum Output {
+    Line(String),
+    Timeout,
+    /// The server closed its output, which it does when it exits.
+    Closed,
+}
+
+/// A running server whose console output is read line by line.
+pub struct ServerProcess {
+    child: Child,
+    input: ConsoleInput,
+    output: Receiver<String>,
+}
+
+impl ServerProcess {
+    /// Starts the server and connects `input` to its console.
+    pub fn spawn(mut command: Command, input: ConsoleInput) -> std::io::Result<ServerProcess> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (sender, output) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, sender);
+        }
+        input.connect(child.stdin.take());
+
+        Ok(ServerProcess {
+            child,
+            input,
+            output,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Waits up to `timeout` for the next line of console output.
+    pub fn next_line(&self, timeout: Duration) -> Output {
+        match self.output.recv_timeout(timeout) {
+            Ok(line) => Output::Line(line),
+            Err(RecvTimeoutError::Timeout) => Output::Timeout,
+            Err(RecvTimeoutError::Disconnected) => Output::Closed,
+        }
+    }
+
+    pub fn send(&self, command: &str) -> std::io::Result<()> {
+        self.input.send(command)
+    }
+
+    /// Waits for the server to exit.
+    pub fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        let status = self.child.wait()?;
+        self.input.connect(None);
+        Ok(status)
+    }
+
+    pub fn kill(&mut self) -> std::io::Result<ExitStatus> {
+        self.child.kill()?;
+        self.wait()
+    }
+
+    /// Asks the server to shut down with `stop`, and kills it when it doesn't
+    /// within `timeout`.
+    pub fn stop(&mut self, timeout: Duration) -> std::io::Result<ExitStatus> {
+        // The server may already be gone, in which case there is nothing to stop.
+        let _ = self.send("stop");
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self.child.try_wait()?.is_some() {
+                return self.wait();
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        self.kill()
+    }
+}
+
+fn forward_lines(stream: impl Read + Send + 'static, sender: Sender<String>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Exponential delay between restarts of a server that keeps crashing.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Backoff {
+        Backoff {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// The delay before the next restart. Each call doubles the one after.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Starts over from the initial delay, once the server is up again.
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(5), Duration::from_secs(12));
+
+        assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(10));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(12));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(12));
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_console_input_without_server() {
+        let input = ConsoleInput::default();
+
+        let result = input.send("list");
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::NotConnected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_server_process_forwards_console() {
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            r#"echo ready; read cmd; echo "got $cmd"; read cmd; echo oops >&2; read cmd; exit 0"#,
+        ]);
+        let input = ConsoleInput::default();
+        let mut server = ServerProcess::spawn(command, input.clone()).unwrap();
+        let timeout = Duration::from_secs(10);
+
+        assert_eq!(server.next_line(timeout), Output::Line("ready".to_owned()));
+        input.send("list").unwrap();
+        assert_eq!(
+            server.next_line(timeout),
+            Output::Line("got list".to_owned())
+        );
+        server.send("save hold").unwrap();
+        assert_eq!(server.next_line(timeout), Output::Line("oops".to_owned()));
+
+        let status = server.stop(timeout).unwrap();
+        assert!(status.success());
+        assert_eq!(server.next_line(timeout), Output::Closed);
+        assert!(input.send("list").is_err());
+    }
+}
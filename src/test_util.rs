@@ -0,0 +1,52 @@
+//! Fixtures shared by the unit tests.
+
+use crate::http::{HttpClient, HttpSettings};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A client that gives up on the first failure, so error tests are quick.
+pub fn test_client() -> HttpClient {
+    HttpClient::new(&HttpSettings {
+        retries: Some(0),
+        ..HttpSettings::default()
+    })
+    .unwrap()
+}
+
+/// Writes a zip of `files` to `path`.
+pub fn write_zip(path: &Path, files: &[(&str, impl AsRef<[u8]>)]) {
+    let entries: Vec<(&str, &[u8], Option<u32>)> = files
+        .iter()
+        .map(|(name, content)| (*name, content.as_ref(), None))
+        .collect();
+    write_zip_with_modes(path, &entries);
+}
+
+/// Writes a zip of `entries` to `path`, with the unix mode of those that
+/// have one.
+pub fn write_zip_with_modes(path: &Path, entries: &[(&str, &[u8], Option<u32>)]) {
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    for (name, content, mode) in entries {
+        let mut options = zip::write::SimpleFileOptions::default();
+        if let Some(mode) = mode {
+            options = options.unix_permissions(*mode);
+        }
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+/// Writes a release zip of `files` into `dir`, named like the 1.21.92.1 one.
+pub fn create_test_zip(dir: &Path, files: &[(&str, impl AsRef<[u8]>)]) -> PathBuf {
+    let zip_path = dir.join("bedrock-server-1.21.92.1.zip");
+    write_zip(&zip_path, files);
+    zip_path
+}
+
+/// Like [`create_test_zip`], with the unix mode of the entries that have one.
+pub fn create_test_zip_with_modes(dir: &Path, entries: &[(&str, &[u8], Option<u32>)]) -> PathBuf {
+    let zip_path = dir.join("bedrock-server-1.21.92.1.zip");
+    write_zip_with_modes(&zip_path, entries);
+    zip_path
+}
@@ -1,337 +1,433 @@
-use crate::args::{DownloadType, UpdateArgs};
-
-pub fn update(args: UpdateArgs) {
-    let web_json = get_json_from_web();
-    if web_json.is_null() {
-        return;
-    }
-
-    let cache_json = get_json_from_cache(&args.cache_path);
-    let web_download_url = get_download_url_from_json(&web_json, &args.download_type).unwrap();
-    let cache_download_url =
-        get_download_url_from_json(&cache_json, &args.download_type).unwrap_or("0.0.0".to_owned());
-
-    println!("Current version in cache: {}", cache_download_url);
-    println!("Version available on the web: {}", web_download_url);
+use crate::error::Result;
+use crate::http::{HttpClient, Validators};
+use crate::lock;
+use crate::pack;
+use crate::permissions::{self, Owner};
+use crate::release::ReleaseIndex;
+use crate::verify::FileHashes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// How long to wait for another process writing the same cache file.
+const CACHE_LOCK_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Reads the links JSON saved by the last applied update, or `Null` when there
+/// is no readable cache.
+pub fn get_json_from_cache(cache_path: &str) -> serde_json::Value {
+    let cache_path = shellexpand::tilde(cache_path).to_string();
 
-    if !args.force && web_download_url == cache_download_url {
-        println!(
-            "You are already on the latest version: {}",
-            cache_download_url
-        );
-        return;
-    }
+    std::fs::File::open(&cache_path)
+        .ok()
+        .and_then(|file| serde_json::from_reader(std::io::BufReader::new(file)).ok())
+        .unwrap_or(serde_json::Value::Null)
+}
 
-    println!("New version available: {}", web_download_url);
-    let Some(zip_path) = fetch_update_zip(&web_download_url) else {
-        return;
-    };
+/// Downloads the update zip into the temp directory and returns its path.
+pub fn fetch_update_zip(client: &HttpClient, download_url: &str) -> Result<PathBuf> {
+    let mut resp = client.get(download_url)?.error_for_status()?;
 
-    apply_update(args.server_path, &zip_path, args.exclude);
-    std::fs::remove_file(zip_path).unwrap();
+    let file_name = download_url.split('/').next_back().unwrap_or("update.zip");
+    let file_path = std::env::temp_dir().join(file_name);
 
-    if let Err(e) = update_cache(web_json, &args.cache_path) {
-        eprintln!("Failed to update cache: {}", e);
-    }
-    println!("Update applied successfully.");
+    let mut file = std::fs::File::create(&file_path)?;
+    // Streaming the body applies the read timeout to each read rather than to
+    // the whole multi-hundred-MB download.
+    resp.copy_to(&mut file)?;
+    Ok(file_path)
 }
 
-fn get_json_from_web() -> serde_json::Value {
-    get_json_from_web_with_url(
-        "https://net-secondary.web.minecraft-services.net/api/v1.0/download/links",
-    )
+/// What applying an update does with one archive entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    CreateDir,
+    Create,
+    Overwrite,
+    /// An excluded file that already exists, or a file of an installed pack,
+    /// which is left alone.
+    Skip,
 }
 
-fn get_json_from_web_with_url(url: &str) -> serde_json::Value {
-    println!("Fetching links from the web...");
-
-    reqwest::blocking::get(url)
-        .and_then(|resp| resp.json::<serde_json::Value>())
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to fetch or parse JSON from {}: {}", url, e);
-            serde_json::Value::Null
-        })
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedFile {
+    /// Path relative to the server directory.
+    pub path: PathBuf,
+    pub action: FileAction,
+    index: usize,
 }
 
-fn get_json_from_cache(cache_path: &str) -> serde_json::Value {
-    println!("Reading cache from: {}", cache_path);
-    let cache_path = shellexpand::tilde(cache_path).to_string();
+/// Where the files of an update come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateSource {
+    Zip(PathBuf),
+    /// A release extracted ahead of time. Its files are moved into place.
+    Staged(PathBuf),
+}
 
-    std::fs::File::open(&cache_path)
-        .and_then(|file| {
-            let reader = std::io::BufReader::new(file);
-            serde_json::from_reader(reader).map_err(|_| {
-                eprintln!("Failed to read or parse cache file: {}", cache_path);
-                std::io::Error::new(std::io::ErrorKind::InvalidData, "Parse error")
-            })
-        })
-        .unwrap_or(serde_json::Value::Null)
+/// The changes an update makes to a server directory, worked out before
+/// anything is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatePlan {
+    pub server_path: PathBuf,
+    pub source: UpdateSource,
+    pub files: Vec<PlannedFile>,
 }
 
-fn get_download_url_from_json(
-    json: &serde_json::Value,
-    download_type: &DownloadType,
-) -> Option<String> {
-    json.get("result")?
-        .get("links")?
-        .as_array()?
-        .iter()
-        .find_map(|item| {
-            if item.get("downloadType") == Some(&download_type.to_string().into()) {
-                item.get("downloadUrl")?.as_str().map(|s| s.to_string())
-            } else {
-                None
-            }
-        })
+impl UpdatePlan {
+    pub fn files_with(&self, action: FileAction) -> impl Iterator<Item = &PlannedFile> {
+        self.files.iter().filter(move |file| file.action == action)
+    }
 }
 
-fn fetch_update_zip(download_url: &str) -> Option<std::path::PathBuf> {
-    let resp = reqwest::blocking::get(download_url).ok()?;
-    if !resp.status().is_success() {
-        eprintln!("Failed to download update: {}", resp.status());
-        return None;
+/// Plans extracting `zip_path` into `server_path`. Excluded files are only
+/// written when they don't exist yet, and the packs installed with
+/// [`pack::install`] are never written.
+pub fn plan_update(server_path: &str, zip_path: &Path, exclude: &[String]) -> Result<UpdatePlan> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(zip_path)?)?;
+    let server_path = PathBuf::from(shellexpand::tilde(server_path).to_string());
+    let packs = pack::installed_dirs(&server_path)?;
+
+    let mut files = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+
+        let action = file_action(&server_path, &path, file.is_dir(), exclude, &packs);
+        files.push(PlannedFile {
+            path,
+            action,
+            index,
+        });
     }
 
-    let file_name = download_url.split('/').last().unwrap_or("update.zip");
-    let file_path = std::env::temp_dir().join(file_name);
+    Ok(UpdatePlan {
+        server_path,
+        source: UpdateSource::Zip(zip_path.to_path_buf()),
+        files,
+    })
+}
 
-    match std::fs::File::create(&file_path) {
-        Ok(mut file) => {
-            if let Ok(bytes) = resp.bytes() {
-                if std::io::copy(&mut bytes.as_ref(), &mut file).is_ok() {
-                    println!("Downloaded update to: {}", file_path.display());
-                    Some(file_path)
-                } else {
-                    eprintln!("Failed to write downloaded file");
-                    None
-                }
-            } else {
-                eprintln!("Failed to read response bytes");
-                None
+/// Plans moving the release extracted in `staged_dir` into `server_path`,
+/// the same way [`plan_update`] plans extracting a zip.
+pub fn plan_staged(server_path: &str, staged_dir: &Path, exclude: &[String]) -> Result<UpdatePlan> {
+    let server_path = PathBuf::from(shellexpand::tilde(server_path).to_string());
+    let packs = pack::installed_dirs(&server_path)?;
+
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = std::fs::read_dir(staged_dir.join(&dir))?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+
+        for name in entries {
+            let path = dir.join(name);
+            let is_dir = staged_dir.join(&path).is_dir();
+            let action = file_action(&server_path, &path, is_dir, exclude, &packs);
+            files.push(PlannedFile {
+                path: path.clone(),
+                action,
+                index: files.len(),
+            });
+            if is_dir {
+                dirs.push(path);
             }
         }
-        Err(e) => {
-            eprintln!("Failed to create file: {}", e);
-            None
-        }
     }
-}
+    // Directories come before the files in them, as they do in the zips.
+    files.sort_by(|a, b| a.path.cmp(&b.path));
 
-fn apply_update(server_path: String, zip_path: &std::path::Path, exclude: Vec<String>) {
-    println!("Applying update from: {}", zip_path.display());
-    println!("Excluded files: {:?}", exclude);
+    Ok(UpdatePlan {
+        server_path,
+        source: UpdateSource::Staged(staged_dir.to_path_buf()),
+        files,
+    })
+}
 
-    let mut archive = zip::ZipArchive::new(std::fs::File::open(zip_path).unwrap()).unwrap();
-    let server_path = std::path::PathBuf::from(shellexpand::tilde(&server_path).to_string());
-    let exclude_set: std::collections::HashSet<_> = exclude.into_iter().collect();
+fn file_action(
+    server_path: &Path,
+    path: &Path,
+    is_dir: bool,
+    exclude: &[String],
+    packs: &[PathBuf],
+) -> FileAction {
+    if packs.iter().any(|dir| path.starts_with(dir)) {
+        return FileAction::Skip;
+    }
+    let exists = std::fs::metadata(server_path.join(path)).is_ok();
+    let excluded = exclude
+        .iter()
+        .any(|name| Some(name.as_str()) == path.to_str());
+    if excluded && exists {
+        FileAction::Skip
+    } else if is_dir {
+        FileAction::CreateDir
+    } else if exists {
+        FileAction::Overwrite
+    } else {
+        FileAction::Create
+    }
+}
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).unwrap();
-        let out_path = match file.enclosed_name() {
-            Some(path) => path,
-            None => continue,
-        };
+/// Writes a planned update, keeping file modes and handing files to `owner`.
+pub fn apply_update(plan: &UpdatePlan, owner: Option<&Owner>) -> Result<()> {
+    enum Files<'a> {
+        Zip(zip::ZipArchive<std::fs::File>),
+        Staged(&'a Path),
+    }
+    let mut source = match &plan.source {
+        UpdateSource::Zip(zip_path) => {
+            Files::Zip(zip::ZipArchive::new(std::fs::File::open(zip_path)?)?)
+        }
+        UpdateSource::Staged(staged_dir) => Files::Staged(staged_dir),
+    };
 
-        let should_exclude = exclude_set.contains(out_path.to_str().unwrap_or(""));
+    for planned in &plan.files {
+        let out_path = plan.server_path.join(&planned.path);
+        match planned.action {
+            FileAction::Skip => continue,
+            FileAction::CreateDir => std::fs::create_dir_all(&out_path)?,
+            FileAction::Create | FileAction::Overwrite => {
+                if let Some(parent) = out_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
 
-        let out_path = server_path.join(out_path);
+                let existing_mode = permissions::existing_mode(&out_path);
+                let new_mode = match &mut source {
+                    Files::Zip(archive) => {
+                        let mut file = archive.by_index(planned.index)?;
+                        let mut outfile = std::fs::File::create(&out_path)?;
+                        std::io::copy(&mut file, &mut outfile)?;
+                        file.unix_mode()
+                    }
+                    Files::Staged(staged_dir) => {
+                        let staged = staged_dir.join(&planned.path);
+                        let mode = permissions::existing_mode(&staged);
+                        // The staging area is inside the server directory, so
+                        // this is a rename on the same filesystem.
+                        std::fs::rename(staged, &out_path)?;
+                        mode
+                    }
+                };
 
-        let should_exclude = should_exclude && std::fs::metadata(&out_path).is_ok();
-        if should_exclude {
-            println!("Skipping excluded file: {}", out_path.display());
-            continue;
-        }
-        if file.is_dir() {
-            std::fs::create_dir_all(&out_path).unwrap();
-        } else {
-            if let Some(parent) = out_path.parent() {
-                std::fs::create_dir_all(parent).unwrap();
+                if let Some(mode) = permissions::target_mode(&planned.path, new_mode, existing_mode)
+                {
+                    permissions::set_mode(&out_path, mode)?;
+                }
             }
+        }
 
-            let mut outfile = std::fs::File::create(&out_path).unwrap();
-            std::io::copy(&mut file, &mut outfile).unwrap();
+        if let Some(owner) = owner {
+            permissions::set_owner(&out_path, owner)?;
         }
     }
-}
 
-fn update_cache(web_json: serde_json::Value, cache_path: &str) -> std::io::Result<()> {
-    let cache_path = shellexpand::tilde(cache_path).to_string();
-    if let Some(parent) = std::path::Path::new(&cache_path).parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-    let file = std::fs::File::create(cache_path)?;
-    serde_json::to_writer(file, &web_json)?;
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mockito::Server;
-    use serde_json::json;
+/// Where a snapshot is kept, inside the server directory.
+pub const SNAPSHOT_DIR: &str = ".bedrock-up-snapshot";
+
+/// Copies of the files an update overwrites and a list of those it creates,
+/// so the update can be rolled back.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub dir: PathBuf,
+    server_path: PathBuf,
+    overwritten: Vec<PathBuf>,
+    created: BTreeSet<PathBuf>,
+}
 
-    #[test]
-    fn test_get_json_from_web_success() {
-        // Create a mock server
-        let mut server = Server::new();
-        let mock_response = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "bedrock-server",
-                        "downloadUrl": "https://example.com/bedrock-server.zip"
+/// Snapshots what applying `plan` would change. Call before [`apply_update`].
+pub fn snapshot(plan: &UpdatePlan) -> Result<Snapshot> {
+    let dir = plan.server_path.join(SNAPSHOT_DIR);
+    // A snapshot left by an interrupted run no longer matches the server.
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+
+    let mut overwritten = Vec::new();
+    let mut created = BTreeSet::new();
+    for file in &plan.files {
+        match file.action {
+            FileAction::Overwrite => {
+                let copy = dir.join(&file.path);
+                if let Some(parent) = copy.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(plan.server_path.join(&file.path), &copy)?;
+                overwritten.push(file.path.clone());
+            }
+            FileAction::Create | FileAction::CreateDir => {
+                // Remember the topmost missing directory as well, zips don't
+                // always list directories.
+                for path in file.path.ancestors() {
+                    if path.as_os_str().is_empty() || plan.server_path.join(path).exists() {
+                        break;
                     }
-                ]
+                    created.insert(path.to_path_buf());
+                }
             }
-        });
-
-        let mock = server
-            .mock("GET", "/api/v1.0/download/links")
-            .with_status(200)
-            .with_header("content-type", "application/json")
-            .with_body(mock_response.to_string())
-            .create();
-
-        // Test the function
-        let result =
-            get_json_from_web_with_url(&format!("{}/api/v1.0/download/links", server.url()));
-
-        // Verify the mock was called
-        mock.assert();
-
-        // Verify the response
-        assert_eq!(result, mock_response);
+            FileAction::Skip => {}
+        }
     }
 
-    #[test]
-    fn test_get_json_from_web_invalid_json() {
-        // Create a mock server that returns invalid JSON
-        let mut server = Server::new();
-
-        let mock = server
-            .mock("GET", "/api/v1.0/download/links")
-            .with_status(200)
-            .with_header("content-type", "application/json")
-            .with_body("invalid json")
-            .create();
-
-        // Test the function
-        let result =
-            get_json_from_web_with_url(&format!("{}/api/v1.0/download/links", server.url()));
+    Ok(Snapshot {
+        dir,
+        server_path: plan.server_path.clone(),
+        overwritten,
+        created,
+    })
+}
 
-        // Verify the mock was called
-        mock.assert();
+impl Snapshot {
+    /// Puts the server back the way it was when the snapshot was taken.
+    pub fn restore(&self) -> Result<()> {
+        for path in &self.created {
+            let path = self.server_path.join(path);
+            let removed = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            match removed {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
 
-        // Verify the response is null for invalid JSON
-        assert_eq!(result, serde_json::Value::Null);
+        for path in &self.overwritten {
+            std::fs::copy(self.dir.join(path), self.server_path.join(path))?;
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_get_json_from_web_server_error() {
-        // Create a mock server that returns a server error
-        let mut server = Server::new();
-
-        let mock = server
-            .mock("GET", "/api/v1.0/download/links")
-            .with_status(500)
-            .with_body("Internal Server Error")
-            .create();
-
-        // Test the function
-        let result =
-            get_json_from_web_with_url(&format!("{}/api/v1.0/download/links", server.url()));
-
-        // Verify the mock was called
-        mock.assert();
-
-        // Verify the response is null for server error
-        assert_eq!(result, serde_json::Value::Null);
+    /// Deletes the snapshot.
+    pub fn discard(self) -> Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
     }
+}
 
-    #[test]
-    fn test_get_json_from_web_connection_error() {
-        // Test with an invalid URL to simulate connection error
-        let result = get_json_from_web_with_url("http://non-existent-domain-12345.com/api");
+/// Files of the installed release that the release `plan` installs doesn't
+/// have, such as the old platform's binary and libraries after switching
+/// download types. Excluded files are kept.
+pub fn leftovers(plan: &UpdatePlan, installed: &FileHashes, exclude: &[String]) -> Vec<PathBuf> {
+    let incoming: BTreeSet<&Path> = plan.files.iter().map(|file| file.path.as_path()).collect();
+    installed
+        .keys()
+        .filter(|path| !exclude.contains(path))
+        .map(PathBuf::from)
+        .filter(|path| !incoming.contains(path.as_path()))
+        .collect()
+}
 
-        // Verify the response is null for connection error
-        assert_eq!(result, serde_json::Value::Null);
+/// Removes the `leftovers` from the server, then the directories they leave
+/// empty.
+pub fn remove_leftovers(server_path: &Path, leftovers: &[PathBuf]) -> Result<()> {
+    for path in leftovers {
+        match std::fs::remove_file(server_path.join(path)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let mut dir = path.parent();
+        while let Some(parent) = dir.filter(|parent| !parent.as_os_str().is_empty()) {
+            let full = server_path.join(parent);
+            let empty = std::fs::read_dir(&full).is_ok_and(|mut entries| entries.next().is_none());
+            if !empty {
+                break;
+            }
+            std::fs::remove_dir(&full)?;
+            dir = parent.parent();
+        }
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_get_json_from_web_empty_response() {
-        // Create a mock server that returns empty JSON
-        let mut server = Server::new();
-
-        let mock = server
-            .mock("GET", "/api/v1.0/download/links")
-            .with_status(200)
-            .with_header("content-type", "application/json")
-            .with_body("{}")
-            .create();
-
-        // Test the function
-        let result =
-            get_json_from_web_with_url(&format!("{}/api/v1.0/download/links", server.url()));
-
-        // Verify the mock was called
-        mock.assert();
+/// Saves the links JSON of the applied update so the next run can tell whether
+/// anything changed.
+pub fn update_cache(web_json: serde_json::Value, cache_path: &str) -> std::io::Result<()> {
+    let _cache_lock = lock::acquire(&lock::cache_lock_path(cache_path), CACHE_LOCK_WAIT)
+        .map_err(std::io::Error::other)?;
 
-        // Verify the response is empty JSON object
-        assert_eq!(result, json!({}));
+    let cache_path = shellexpand::tilde(cache_path).to_string();
+    if let Some(parent) = std::path::Path::new(&cache_path).parent() {
+        std::fs::create_dir_all(parent)?;
     }
 
-    #[test]
-    fn test_get_json_from_web_complex_response() {
-        // Test with a more complex JSON response
-        let mut server = Server::new();
-        let mock_response = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "bedrock-server",
-                        "downloadUrl": "https://example.com/bedrock-server-1.20.0.zip",
-                        "version": "1.20.0"
-                    },
-                    {
-                        "downloadType": "bedrock-server-preview",
-                        "downloadUrl": "https://example.com/bedrock-server-preview-1.21.0.zip",
-                        "version": "1.21.0"
-                    }
-                ],
-                "metadata": {
-                    "lastUpdated": "2025-07-04T12:00:00Z"
-                }
-            }
-        });
-
-        let mock = server
-            .mock("GET", "/api/v1.0/download/links")
-            .with_status(200)
-            .with_header("content-type", "application/json")
-            .with_body(mock_response.to_string())
-            .create();
+    // Write next to the cache and rename over it so readers never see a
+    // partially written file.
+    let temp_path = format!("{}.tmp", cache_path);
+    let file = std::fs::File::create(&temp_path)?;
+    serde_json::to_writer(file, &web_json)?;
+    std::fs::rename(temp_path, cache_path)?;
+    Ok(())
+}
 
-        // Test the function
-        let result =
-            get_json_from_web_with_url(&format!("{}/api/v1.0/download/links", server.url()));
+/// Key of the cached links JSON under which the validators of the response
+/// are kept, so the next check can be a conditional request.
+const CACHE_VALIDATORS_KEY: &str = "_validators";
 
-        // Verify the mock was called
-        mock.assert();
+#[derive(Serialize, Deserialize)]
+struct CachedValidators {
+    /// The url the validators belong to.
+    source: String,
+    #[serde(flatten)]
+    validators: Validators,
+}
 
-        // Verify the response matches expected structure
-        assert_eq!(result, mock_response);
+/// Reads the cache as an index. Its source is the url the cached links were
+/// fetched from when the response's validators were saved with them.
+pub fn read_cache(cache_path: &str) -> ReleaseIndex {
+    let mut json = get_json_from_cache(cache_path);
+    let cached = json
+        .as_object_mut()
+        .and_then(|object| object.remove(CACHE_VALIDATORS_KEY))
+        .and_then(|value| serde_json::from_value::<CachedValidators>(value).ok());
+    match cached {
+        Some(cached) => ReleaseIndex {
+            source: cached.source,
+            json,
+            validators: Some(cached.validators),
+        },
+        None => ReleaseIndex {
+            source: cache_path.to_owned(),
+            json,
+            validators: None,
+        },
+    }
+}
 
-        // Verify specific nested values
-        assert_eq!(
-            result["result"]["links"][0]["downloadType"],
-            "bedrock-server"
-        );
-        assert_eq!(result["result"]["links"][1]["version"], "1.21.0");
-        assert_eq!(
-            result["result"]["metadata"]["lastUpdated"],
-            "2025-07-04T12:00:00Z"
+/// Saves the index to the cache along with its validators.
+pub fn write_cache(index: &ReleaseIndex, cache_path: &str) -> std::io::Result<()> {
+    let mut json = index.json.clone();
+    if let (Some(object), Some(validators)) = (json.as_object_mut(), &index.validators) {
+        let cached = CachedValidators {
+            source: index.source.clone(),
+            validators: validators.clone(),
+        };
+        object.insert(
+            CACHE_VALIDATORS_KEY.to_owned(),
+            serde_json::to_value(cached)?,
         );
     }
+    update_cache(json, cache_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{create_test_zip_with_modes, test_client};
+    use mockito::Server;
+    use serde_json::json;
 
     // Tests for get_json_from_cache function
     #[test]
@@ -352,7 +448,7 @@ mod tests {
             }
         });
 
-        write!(temp_file, "{}", test_json.to_string()).unwrap();
+        write!(temp_file, "{}", test_json).unwrap();
         temp_file.flush().unwrap();
 
         // Test the function
@@ -363,497 +459,103 @@ mod tests {
     }
 
     #[test]
-    fn test_get_json_from_cache_file_not_found() {
-        // Test with a non-existent file path
-        let result = get_json_from_cache("/non/existent/file.json");
-
-        // Verify the result is null when file doesn't exist
-        assert_eq!(result, serde_json::Value::Null);
-    }
-
-    #[test]
-    fn test_get_json_from_cache_invalid_json() {
-        use std::io::Write;
-        use tempfile::NamedTempFile;
-
-        // Create a temporary file with invalid JSON
-        let mut temp_file = NamedTempFile::new().unwrap();
-        write!(temp_file, "invalid json content").unwrap();
-        temp_file.flush().unwrap();
-
-        // Test the function
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result is null for invalid JSON
-        assert_eq!(result, serde_json::Value::Null);
-    }
-
-    #[test]
-    fn test_get_json_from_cache_empty_file() {
-        use tempfile::NamedTempFile;
-
-        // Create an empty temporary file
-        let temp_file = NamedTempFile::new().unwrap();
-
-        // Test the function
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result is null for empty file
-        assert_eq!(result, serde_json::Value::Null);
-    }
-
-    #[test]
-    fn test_get_json_from_cache_empty_json_object() {
-        use std::io::Write;
-        use tempfile::NamedTempFile;
-
-        // Create a temporary file with empty JSON object
-        let mut temp_file = NamedTempFile::new().unwrap();
-        write!(temp_file, "{{}}").unwrap();
-        temp_file.flush().unwrap();
-
-        // Test the function
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result is empty JSON object
-        assert_eq!(result, json!({}));
-    }
-
-    #[test]
-    fn test_get_json_from_cache_complex_json() {
-        use std::io::Write;
-        use tempfile::NamedTempFile;
-
-        // Create a temporary file with complex JSON
-        let mut temp_file = NamedTempFile::new().unwrap();
-        let complex_json = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "bedrock-server",
-                        "downloadUrl": "https://example.com/bedrock-server-1.20.0.zip",
-                        "version": "1.20.0",
-                        "metadata": {
-                            "size": 123456789,
-                            "checksum": "abc123def456"
-                        }
-                    },
-                    {
-                        "downloadType": "bedrock-server-preview",
-                        "downloadUrl": "https://example.com/bedrock-server-preview-1.21.0.zip",
-                        "version": "1.21.0",
-                        "metadata": {
-                            "size": 987654321,
-                            "checksum": "xyz789uvw012"
-                        }
-                    }
-                ],
-                "lastUpdated": "2025-07-04T12:00:00Z",
-                "totalCount": 2
-            },
-            "status": "success"
-        });
-
-        write!(temp_file, "{}", complex_json.to_string()).unwrap();
-        temp_file.flush().unwrap();
-
-        // Test the function
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result matches the complex JSON
-        assert_eq!(result, complex_json);
-
-        // Verify specific nested values
-        assert_eq!(result["result"]["links"][0]["version"], "1.20.0");
-        assert_eq!(result["result"]["links"][1]["metadata"]["size"], 987654321);
-        assert_eq!(result["result"]["totalCount"], 2);
-        assert_eq!(result["status"], "success");
-    }
-
-    #[test]
-    fn test_get_json_from_cache_with_tilde_expansion() {
-        use std::io::Write;
-        use tempfile::NamedTempFile;
-
-        // Create a temporary file with valid JSON
-        let mut temp_file = NamedTempFile::new().unwrap();
-        let test_json = json!({
-            "cached_data": {
-                "version": "1.0.0",
-                "timestamp": "2025-07-04T12:00:00Z"
-            }
-        });
-
-        write!(temp_file, "{}", test_json.to_string()).unwrap();
-        temp_file.flush().unwrap();
-
-        // Test the function with absolute path (no tilde expansion needed)
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result
-        assert_eq!(result, test_json);
-    }
-
-    #[test]
-    fn test_get_json_from_cache_partial_json() {
-        use std::io::Write;
-        use tempfile::NamedTempFile;
-
-        // Create a temporary file with truncated/partial JSON
-        let mut temp_file = NamedTempFile::new().unwrap();
-        write!(temp_file, "{{\"result\": {{\"links\": [").unwrap();
-        temp_file.flush().unwrap();
-
-        // Test the function
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result is null for partial/invalid JSON
-        assert_eq!(result, serde_json::Value::Null);
-    }
-
-    #[test]
-    fn test_get_json_from_cache_json_array() {
-        use std::io::Write;
-        use tempfile::NamedTempFile;
-
-        // Create a temporary file with JSON array
-        let mut temp_file = NamedTempFile::new().unwrap();
-        let json_array = json!([
-            {
-                "name": "item1",
-                "value": 123
-            },
-            {
-                "name": "item2",
-                "value": 456
-            }
-        ]);
-
-        write!(temp_file, "{}", json_array.to_string()).unwrap();
-        temp_file.flush().unwrap();
-
-        // Test the function
-        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
-
-        // Verify the result matches the JSON array
-        assert_eq!(result, json_array);
-        assert!(result.is_array());
-        assert_eq!(result.as_array().unwrap().len(), 2);
-    }
-
-    // Tests for get_download_url_from_json function
-    #[test]
-    fn test_get_download_url_from_json_success_windows() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
-                    },
-                    {
-                        "downloadType": "serverBedrockLinux",
-                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
-
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server-windows.zip".to_string())
-        );
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_success_linux() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
-                    },
-                    {
-                        "downloadType": "serverBedrockLinux",
-                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::Linux);
-
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server-linux.zip".to_string())
-        );
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_success_preview_windows() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockPreviewWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-preview-windows.zip"
-                    },
-                    {
-                        "downloadType": "serverBedrockPreviewLinux",
-                        "downloadUrl": "https://example.com/bedrock-server-preview-linux.zip"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::PreviewWindows);
-
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server-preview-windows.zip".to_string())
-        );
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_success_preview_linux() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockPreviewLinux",
-                        "downloadUrl": "https://example.com/bedrock-server-preview-linux.zip"
-                    },
-                    {
-                        "downloadType": "serverJar",
-                        "downloadUrl": "https://example.com/bedrock-server.jar"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::PreviewLinux);
-
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server-preview-linux.zip".to_string())
-        );
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_success_server_jar() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
-                    },
-                    {
-                        "downloadType": "serverJar",
-                        "downloadUrl": "https://example.com/bedrock-server.jar"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::ServerJar);
-
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server.jar".to_string())
-        );
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_not_found() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
-                    },
-                    {
-                        "downloadType": "serverBedrockLinux",
-                        "downloadUrl": "https://example.com/bedrock-server-linux.zip"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::ServerJar);
-
-        assert_eq!(result, None);
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_missing_result() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "error": "No data available"
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
-
-        assert_eq!(result, None);
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_missing_links() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "message": "No links available"
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
-
-        assert_eq!(result, None);
-    }
-
-    #[test]
-    fn test_get_download_url_from_json_links_not_array() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": "not an array"
-            }
-        });
+    fn test_cache_round_trip_with_validators() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("links.json");
+        let cache_path = cache_path.to_str().unwrap();
+        let index = ReleaseIndex {
+            source: "https://example.com/links".to_owned(),
+            json: json!({ "result": { "links": [] } }),
+            validators: Some(Validators {
+                etag: Some("\"v1\"".to_owned()),
+                last_modified: None,
+            }),
+        };
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+        write_cache(&index, cache_path).unwrap();
 
-        assert_eq!(result, None);
+        assert_eq!(read_cache(cache_path), index);
     }
 
     #[test]
-    fn test_get_download_url_from_json_empty_links_array() {
-        use crate::args::DownloadType;
+    fn test_read_cache_without_validators() {
+        let result = read_cache("/non/existent/file.json");
 
-        let json_data = json!({
-            "result": {
-                "links": []
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
-
-        assert_eq!(result, None);
+        assert_eq!(result.source, "/non/existent/file.json");
+        assert_eq!(result.json, serde_json::Value::Null);
+        assert_eq!(result.validators, None);
     }
 
     #[test]
-    fn test_get_download_url_from_json_missing_download_type() {
-        use crate::args::DownloadType;
-
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadUrl": "https://example.com/bedrock-server.zip"
-                    }
-                ]
-            }
-        });
-
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+    fn test_get_json_from_cache_file_not_found() {
+        // Test with a non-existent file path
+        let result = get_json_from_cache("/non/existent/file.json");
 
-        assert_eq!(result, None);
+        // Verify the result is null when file doesn't exist
+        assert_eq!(result, serde_json::Value::Null);
     }
 
     #[test]
-    fn test_get_download_url_from_json_missing_download_url() {
-        use crate::args::DownloadType;
+    fn test_get_json_from_cache_invalid_json() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
 
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows"
-                    }
-                ]
-            }
-        });
+        // Create a temporary file with invalid JSON
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "invalid json content").unwrap();
+        temp_file.flush().unwrap();
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+        // Test the function
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
 
-        assert_eq!(result, None);
+        // Verify the result is null for invalid JSON
+        assert_eq!(result, serde_json::Value::Null);
     }
 
     #[test]
-    fn test_get_download_url_from_json_download_url_not_string() {
-        use crate::args::DownloadType;
+    fn test_get_json_from_cache_empty_file() {
+        use tempfile::NamedTempFile;
 
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": 123
-                    }
-                ]
-            }
-        });
+        // Create an empty temporary file
+        let temp_file = NamedTempFile::new().unwrap();
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+        // Test the function
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
 
-        assert_eq!(result, None);
+        // Verify the result is null for empty file
+        assert_eq!(result, serde_json::Value::Null);
     }
 
     #[test]
-    fn test_get_download_url_from_json_multiple_matches_returns_first() {
-        use crate::args::DownloadType;
+    fn test_get_json_from_cache_empty_json_object() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
 
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows-1.zip"
-                    },
-                    {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows-2.zip"
-                    }
-                ]
-            }
-        });
+        // Create a temporary file with empty JSON object
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{{}}").unwrap();
+        temp_file.flush().unwrap();
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+        // Test the function
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
 
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server-windows-1.zip".to_string())
-        );
+        // Verify the result is empty JSON object
+        assert_eq!(result, json!({}));
     }
 
     #[test]
-    fn test_get_download_url_from_json_complex_structure() {
-        use crate::args::DownloadType;
+    fn test_get_json_from_cache_complex_json() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
 
-        let json_data = json!({
+        // Create a temporary file with complex JSON
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let complex_json = json!({
             "result": {
                 "links": [
                     {
-                        "downloadType": "serverBedrockWindows",
-                        "downloadUrl": "https://example.com/bedrock-server-windows.zip",
+                        "downloadType": "bedrock-server",
+                        "downloadUrl": "https://example.com/bedrock-server-1.20.0.zip",
                         "version": "1.20.0",
                         "metadata": {
                             "size": 123456789,
@@ -861,59 +563,107 @@ mod tests {
                         }
                     },
                     {
-                        "downloadType": "serverBedrockLinux",
-                        "downloadUrl": "https://example.com/bedrock-server-linux.zip",
-                        "version": "1.20.0",
+                        "downloadType": "bedrock-server-preview",
+                        "downloadUrl": "https://example.com/bedrock-server-preview-1.21.0.zip",
+                        "version": "1.21.0",
                         "metadata": {
                             "size": 987654321,
                             "checksum": "xyz789uvw012"
                         }
                     }
                 ],
-                "lastUpdated": "2025-07-04T12:00:00Z"
+                "lastUpdated": "2025-07-04T12:00:00Z",
+                "totalCount": 2
+            },
+            "status": "success"
+        });
+
+        write!(temp_file, "{}", complex_json).unwrap();
+        temp_file.flush().unwrap();
+
+        // Test the function
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
+
+        // Verify the result matches the complex JSON
+        assert_eq!(result, complex_json);
+
+        // Verify specific nested values
+        assert_eq!(result["result"]["links"][0]["version"], "1.20.0");
+        assert_eq!(result["result"]["links"][1]["metadata"]["size"], 987654321);
+        assert_eq!(result["result"]["totalCount"], 2);
+        assert_eq!(result["status"], "success");
+    }
+
+    #[test]
+    fn test_get_json_from_cache_with_tilde_expansion() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        // Create a temporary file with valid JSON
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let test_json = json!({
+            "cached_data": {
+                "version": "1.0.0",
+                "timestamp": "2025-07-04T12:00:00Z"
             }
         });
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Linux);
+        write!(temp_file, "{}", test_json).unwrap();
+        temp_file.flush().unwrap();
 
-        assert_eq!(
-            result,
-            Some("https://example.com/bedrock-server-linux.zip".to_string())
-        );
+        // Test the function with absolute path (no tilde expansion needed)
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
+
+        // Verify the result
+        assert_eq!(result, test_json);
     }
 
     #[test]
-    fn test_get_download_url_from_json_null_json() {
-        use crate::args::DownloadType;
+    fn test_get_json_from_cache_partial_json() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
 
-        let json_data = serde_json::Value::Null;
+        // Create a temporary file with truncated/partial JSON
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{{\"result\": {{\"links\": [").unwrap();
+        temp_file.flush().unwrap();
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+        // Test the function
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
 
-        assert_eq!(result, None);
+        // Verify the result is null for partial/invalid JSON
+        assert_eq!(result, serde_json::Value::Null);
     }
 
     #[test]
-    fn test_get_download_url_from_json_case_sensitive() {
-        use crate::args::DownloadType;
+    fn test_get_json_from_cache_json_array() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
 
-        let json_data = json!({
-            "result": {
-                "links": [
-                    {
-                        "downloadType": "serverbedrockwindows",  // lowercase
-                        "downloadUrl": "https://example.com/bedrock-server-windows.zip"
-                    }
-                ]
+        // Create a temporary file with JSON array
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let json_array = json!([
+            {
+                "name": "item1",
+                "value": 123
+            },
+            {
+                "name": "item2",
+                "value": 456
             }
-        });
+        ]);
 
-        let result = get_download_url_from_json(&json_data, &DownloadType::Windows);
+        write!(temp_file, "{}", json_array).unwrap();
+        temp_file.flush().unwrap();
 
-        // Should return None because the case doesn't match
-        assert_eq!(result, None);
-    }
+        // Test the function
+        let result = get_json_from_cache(temp_file.path().to_str().unwrap());
 
+        // Verify the result matches the JSON array
+        assert_eq!(result, json_array);
+        assert!(result.is_array());
+        assert_eq!(result.as_array().unwrap().len(), 2);
+    }
     // Tests for fetch_update_zip function
     #[test]
     fn test_fetch_update_zip_success() {
@@ -928,10 +678,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/bedrock-server-success.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -956,10 +706,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/bedrock-server.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_none());
+        assert!(result.is_err());
     }
 
     #[test]
@@ -973,18 +723,18 @@ mod tests {
             .create();
 
         let download_url = format!("{}/nonexistent.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_none());
+        assert!(result.is_err());
     }
 
     #[test]
     fn test_fetch_update_zip_connection_error() {
         let download_url = "http://non-existent-domain-12345.com/bedrock-server.zip";
-        let result = fetch_update_zip(download_url);
+        let result = fetch_update_zip(&test_client(), download_url);
 
-        assert!(result.is_none());
+        assert!(result.is_err());
     }
 
     #[test]
@@ -1003,10 +753,10 @@ mod tests {
             "{}/path/to/minecraft-bedrock-server-1.20.0.zip",
             server.url()
         );
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1033,10 +783,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/no-extension", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1063,10 +813,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/download", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1088,10 +838,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/empty.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1118,10 +868,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/large.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1148,10 +898,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/test.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
 
@@ -1176,10 +926,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/bedrock-server-query.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1210,11 +960,11 @@ mod tests {
             .create();
 
         let download_url = format!("{}/redirect", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
         final_mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1236,10 +986,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/bedrock-server-v1.20.0-beta.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1264,10 +1014,10 @@ mod tests {
             .create();
 
         let download_url = format!("{}/server.zip", server.url());
-        let result = fetch_update_zip(&download_url);
+        let result = fetch_update_zip(&test_client(), &download_url);
 
         mock.assert();
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
         let file_path = result.unwrap();
         assert!(file_path.exists());
@@ -1280,6 +1030,259 @@ mod tests {
         std::fs::remove_file(file_path).unwrap();
     }
 
+    // Tests for apply_update function
+    #[test]
+    fn test_apply_update_extracts_files() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("bedrock_server", b"binary", None),
+                ("behavior_packs/vanilla/manifest.json", b"{}", None),
+            ],
+        );
+
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &[]).unwrap();
+        apply_update(&plan, None).unwrap();
+
+        assert_eq!(
+            std::fs::read(server_dir.join("bedrock_server")).unwrap(),
+            b"binary"
+        );
+        assert!(
+            server_dir
+                .join("behavior_packs/vanilla/manifest.json")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_apply_update_skips_existing_excluded_files() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir_all(&server_dir).unwrap();
+        std::fs::write(server_dir.join("server.properties"), "custom").unwrap();
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("server.properties", b"default", None),
+                ("allowlist.json", b"[]", None),
+            ],
+        );
+
+        let exclude = vec!["server.properties".to_owned(), "allowlist.json".to_owned()];
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &exclude).unwrap();
+        apply_update(&plan, None).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("server.properties")).unwrap(),
+            "custom"
+        );
+        // Excluded files are still created when missing
+        assert!(server_dir.join("allowlist.json").exists());
+    }
+
+    #[test]
+    fn test_plan_update_actions() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir_all(&server_dir).unwrap();
+        std::fs::write(server_dir.join("server.properties"), "custom").unwrap();
+        std::fs::write(server_dir.join("bedrock_server"), "old").unwrap();
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("server.properties", b"default", None),
+                ("bedrock_server", b"new", None),
+                ("allowlist.json", b"[]", None),
+            ],
+        );
+
+        let exclude = vec!["server.properties".to_owned(), "allowlist.json".to_owned()];
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &exclude).unwrap();
+
+        let actions: Vec<_> = plan
+            .files
+            .iter()
+            .map(|file| (file.path.to_str().unwrap(), file.action))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("server.properties", FileAction::Skip),
+                ("bedrock_server", FileAction::Overwrite),
+                ("allowlist.json", FileAction::Create),
+            ]
+        );
+        // Planning writes nothing
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("bedrock_server")).unwrap(),
+            "old"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_update_file_modes() {
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir_all(&server_dir).unwrap();
+        std::fs::write(server_dir.join("bedrock_server_how_to.html"), "old").unwrap();
+        std::fs::set_permissions(
+            server_dir.join("bedrock_server_how_to.html"),
+            std::fs::Permissions::from_mode(0o600),
+        )
+        .unwrap();
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("bedrock_server", b"binary", Some(0o644)),
+                ("bedrock_server_how_to.html", b"new", Some(0o644)),
+                ("libCrypto.so", b"lib", Some(0o750)),
+            ],
+        );
+
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &[]).unwrap();
+        apply_update(&plan, None).unwrap();
+
+        let mode = |name: &str| {
+            std::fs::metadata(server_dir.join(name))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+        assert_eq!(mode("bedrock_server"), 0o755);
+        assert_eq!(mode("bedrock_server_how_to.html"), 0o600);
+        assert_eq!(mode("libCrypto.so"), 0o750);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir_all(server_dir.join("worlds")).unwrap();
+        std::fs::write(server_dir.join("bedrock_server"), "old").unwrap();
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("bedrock_server", b"new", None),
+                ("behavior_packs/vanilla/manifest.json", b"{}", None),
+            ],
+        );
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &[]).unwrap();
+
+        let snapshot = snapshot(&plan).unwrap();
+        apply_update(&plan, None).unwrap();
+        snapshot.restore().unwrap();
+        snapshot.discard().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("bedrock_server")).unwrap(),
+            "old"
+        );
+        assert!(!server_dir.join("behavior_packs").exists());
+        assert!(!server_dir.join(SNAPSHOT_DIR).exists());
+        assert!(server_dir.join("worlds").exists());
+    }
+
+    #[test]
+    fn test_plan_update_skips_installed_packs() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir_all(&server_dir).unwrap();
+        std::fs::write(
+            server_dir.join(pack::PACKS_FILE),
+            r#"{"packs": [{"uuid": "ab12", "name": "Custom", "version": "1.0.0",
+                "kind": "behavior", "dir": "behavior_packs/custom", "worlds": []}]}"#,
+        )
+        .unwrap();
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("behavior_packs/custom/manifest.json", b"{}", None),
+                ("behavior_packs/vanilla/manifest.json", b"{}", None),
+            ],
+        );
+
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &[]).unwrap();
+
+        let actions: Vec<_> = plan.files.iter().map(|file| file.action).collect();
+        assert_eq!(actions, vec![FileAction::Skip, FileAction::Create]);
+    }
+
+    #[test]
+    fn test_remove_leftovers() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir_all(server_dir.join("worlds/Bedrock level")).unwrap();
+        std::fs::create_dir_all(server_dir.join("Microsoft.Windows")).unwrap();
+        for path in [
+            "bedrock_server.exe",
+            "vcruntime140.dll",
+            "Microsoft.Windows/lib.dll",
+            "server.properties",
+        ] {
+            std::fs::write(server_dir.join(path), "windows").unwrap();
+        }
+        let installed = FileHashes::from(
+            [
+                "bedrock_server.exe",
+                "vcruntime140.dll",
+                "Microsoft.Windows/lib.dll",
+                "server.properties",
+            ]
+            .map(|path| (path.to_owned(), String::new())),
+        );
+        let zip_path = create_test_zip_with_modes(
+            temp_dir.path(),
+            &[
+                ("bedrock_server", b"linux", None),
+                ("server.properties", b"default", None),
+            ],
+        );
+        let exclude = vec!["server.properties".to_owned()];
+        let plan = plan_update(server_dir.to_str().unwrap(), &zip_path, &exclude).unwrap();
+
+        let leftovers = leftovers(&plan, &installed, &exclude);
+        assert_eq!(
+            leftovers,
+            vec![
+                PathBuf::from("Microsoft.Windows/lib.dll"),
+                PathBuf::from("bedrock_server.exe"),
+                PathBuf::from("vcruntime140.dll"),
+            ]
+        );
+
+        apply_update(&plan, None).unwrap();
+        remove_leftovers(&server_dir, &leftovers).unwrap();
+
+        assert!(server_dir.join("bedrock_server").exists());
+        assert!(!server_dir.join("bedrock_server.exe").exists());
+        assert!(!server_dir.join("Microsoft.Windows").exists());
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("server.properties")).unwrap(),
+            "windows"
+        );
+        assert!(server_dir.join("worlds/Bedrock level").exists());
+    }
+
     // Tests for update_cache function
     #[test]
     fn test_update_cache_success() {
@@ -1536,18 +1539,17 @@ mod tests {
 
     #[test]
     fn test_update_cache_invalid_path() {
-        // Test with an invalid path (on Windows, this would be an invalid drive)
-        let invalid_path = if cfg!(windows) {
-            "Z:\\nonexistent\\path\\cache.json"
-        } else {
-            "/root/nonexistent/path/cache.json"
-        };
+        use tempfile::NamedTempFile;
+
+        // Test with a path nested under a regular file, which can never be created
+        let blocking_file = NamedTempFile::new().unwrap();
+        let invalid_path = blocking_file.path().join("nested").join("cache.json");
 
         let test_json = json!({
             "test": "data"
         });
 
-        let result = update_cache(test_json, invalid_path);
+        let result = update_cache(test_json, invalid_path.to_str().unwrap());
 
         // Should return an error
         assert!(result.is_err());
@@ -1698,9 +1700,10 @@ mod tests {
             results.push(handle.join().unwrap());
         }
 
-        // At least one should succeed (the last one to write)
+        // Writers are serialized by the cache lock, so every one succeeds
         let success_count = results.iter().filter(|r| r.is_ok()).count();
-        assert!(success_count > 0);
+        assert_eq!(success_count, 3);
+        assert!(!lock::cache_lock_path(cache_path.to_str().unwrap()).exists());
 
         // The file should exist and contain valid JSON
         assert!(cache_path.exists());
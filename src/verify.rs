@@ -0,0 +1,268 @@
+use crate::error::Result;
+use crate::pack;
+use crate::permissions::Owner;
+use crate::platform::Platform;
+use crate::stage::STAGE_DIR;
+use crate::updater::{self, FileAction, SNAPSHOT_DIR, UpdatePlan};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// SHA-256 of each file of a release, keyed by its `/`-separated path
+/// relative to the server directory.
+pub type FileHashes = BTreeMap<String, String>;
+
+/// Directories that belong to the server rather than the release.
+const SERVER_DATA: &[&str] = &["worlds", STAGE_DIR, SNAPSHOT_DIR];
+
+/// How a server directory differs from the release installed in it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    /// Files the release doesn't have, outside worlds, installed packs and
+    /// excluded files.
+    pub extra: Vec<String>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+
+    /// The files `repair` restores.
+    pub fn damaged(&self) -> impl Iterator<Item = &String> {
+        self.modified.iter().chain(&self.missing)
+    }
+}
+
+pub fn hash_reader(mut reader: impl Read) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+fn key(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_excluded(key: &str, exclude: &[String]) -> bool {
+    exclude.iter().any(|name| name == key)
+}
+
+/// Hashes the given files of the server, skipping excluded ones, which the
+/// admin is expected to change.
+pub fn hash_files<'a>(
+    server_path: &Path,
+    paths: impl IntoIterator<Item = &'a Path>,
+    exclude: &[String],
+) -> std::io::Result<FileHashes> {
+    let mut hashes = FileHashes::new();
+    for path in paths {
+        let key = key(path);
+        if is_excluded(&key, exclude) {
+            continue;
+        }
+        let hash = hash_reader(std::fs::File::open(server_path.join(path))?)?;
+        hashes.insert(key, hash);
+    }
+    Ok(hashes)
+}
+
+/// Hashes the files `plan` wrote, once it was applied.
+pub fn plan_hashes(plan: &UpdatePlan, exclude: &[String]) -> std::io::Result<FileHashes> {
+    let written = plan
+        .files
+        .iter()
+        .filter(|file| matches!(file.action, FileAction::Create | FileAction::Overwrite))
+        .map(|file| file.path.as_path());
+    hash_files(&plan.server_path, written, exclude)
+}
+
+/// Hashes every file in a release zip.
+pub fn zip_hashes(zip_path: &Path) -> Result<FileHashes> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(zip_path)?)?;
+    let mut hashes = FileHashes::new();
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        hashes.insert(key(&path), hash_reader(file)?);
+    }
+    Ok(hashes)
+}
+
+/// Compares the server's files against the release's `expected` hashes.
+/// Excluded files are neither checked nor reported as extra, nor are the
+/// packs installed with [`pack::install`].
+pub fn verify(server_path: &Path, expected: &FileHashes, exclude: &[String]) -> Result<Report> {
+    let packs = pack::installed_dirs(server_path)?;
+    let mut report = Report::default();
+    for (key, hash) in expected {
+        if is_excluded(key, exclude) {
+            continue;
+        }
+        match std::fs::File::open(server_path.join(key)) {
+            Ok(file) => {
+                if hash_reader(file)? != *hash {
+                    report.modified.push(key.clone());
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => report.missing.push(key.clone()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(server_path.join(&dir))? {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            let key = key(&path);
+            let server_data = dir.as_os_str().is_empty()
+                && (SERVER_DATA.contains(&key.as_str()) || key.starts_with(".bedrock-up"));
+            if server_data || is_excluded(&key, exclude) || packs.contains(&path) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if !expected.contains_key(&key) {
+                report.extra.push(key);
+            }
+        }
+    }
+    report.extra.sort();
+    Ok(report)
+}
+
+/// Whether the server binary installed at `server_path` is the one in
+/// `release`, the hashes of a release zip. Identifies the installed version of
+/// a server nothing recorded a manifest for.
+pub fn same_binary(server_path: &Path, release: &FileHashes) -> Result<bool> {
+    for platform in Platform::ALL {
+        let Some(expected) = release.get(platform.marker()) else {
+            continue;
+        };
+        return match std::fs::File::open(server_path.join(platform.marker())) {
+            Ok(file) => Ok(hash_reader(file)? == *expected),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        };
+    }
+    Ok(false)
+}
+
+/// Extracts only `paths` from the release zip into the server.
+pub fn repair(
+    server_path: &str,
+    zip_path: &Path,
+    paths: &BTreeSet<String>,
+    owner: Option<&Owner>,
+) -> Result<()> {
+    let mut plan = updater::plan_update(server_path, zip_path, &[])?;
+    plan.files.retain(|file| paths.contains(&key(&file.path)));
+    updater::apply_update(&plan, owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::create_test_zip;
+
+    #[test]
+    fn test_hash_reader() {
+        assert_eq!(
+            hash_reader(&b"abc"[..]).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_same_binary() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let zip_path = create_test_zip(temp_dir.path(), &[("bedrock_server", "server")]);
+        let release = zip_hashes(&zip_path).unwrap();
+        let server_dir = temp_dir.path().join("server");
+        std::fs::create_dir(&server_dir).unwrap();
+        assert!(!same_binary(&server_dir, &release).unwrap());
+
+        std::fs::write(server_dir.join("bedrock_server"), "server").unwrap();
+        assert!(same_binary(&server_dir, &release).unwrap());
+
+        std::fs::write(server_dir.join("bedrock_server"), "older server").unwrap();
+        assert!(!same_binary(&server_dir, &release).unwrap());
+    }
+
+    #[test]
+    fn test_verify_and_repair() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let zip_path = create_test_zip(
+            temp_dir.path(),
+            &[
+                ("bedrock_server", "server"),
+                ("server.properties", "default"),
+                ("behavior_packs/vanilla/manifest.json", "{}"),
+                ("resource_packs/vanilla/manifest.json", "{}"),
+            ],
+        );
+        let server_dir = temp_dir.path().join("server");
+        let server_path = server_dir.to_str().unwrap();
+        let exclude = vec!["server.properties".to_owned()];
+        let plan = updater::plan_update(server_path, &zip_path, &exclude).unwrap();
+        updater::apply_update(&plan, None).unwrap();
+        let expected = plan_hashes(&plan, &exclude).unwrap();
+        assert_eq!(expected, {
+            let mut from_zip = zip_hashes(&zip_path).unwrap();
+            from_zip.remove("server.properties");
+            from_zip
+        });
+
+        std::fs::write(server_dir.join("server.properties"), "custom").unwrap();
+        std::fs::write(server_dir.join("bedrock_server"), "tampered").unwrap();
+        std::fs::remove_file(server_dir.join("resource_packs/vanilla/manifest.json")).unwrap();
+        std::fs::write(server_dir.join("behavior_packs/vanilla/extra.json"), "{}").unwrap();
+        std::fs::create_dir_all(server_dir.join("worlds/Bedrock level")).unwrap();
+        std::fs::write(server_dir.join("worlds/Bedrock level/level.dat"), "").unwrap();
+        std::fs::write(server_dir.join(".bedrock-up.json"), "{}").unwrap();
+
+        let report = verify(&server_dir, &expected, &exclude).unwrap();
+        assert_eq!(
+            report,
+            Report {
+                modified: vec!["bedrock_server".to_owned()],
+                missing: vec!["resource_packs/vanilla/manifest.json".to_owned()],
+                extra: vec!["behavior_packs/vanilla/extra.json".to_owned()],
+            }
+        );
+
+        let damaged = report.damaged().cloned().collect();
+        repair(server_path, &zip_path, &damaged, None).unwrap();
+
+        let report = verify(&server_dir, &expected, &exclude).unwrap();
+        assert!(report.modified.is_empty());
+        assert!(report.missing.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(server_dir.join("server.properties")).unwrap(),
+            "custom"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// A Bedrock server version like `1.21.92.1`: major, minor, patch and build.
+/// Versions compare part by part, so `1.21.100.6` is newer than `1.21.92.1`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(Vec<u64>);
+
+impl Version {
+    pub fn parts(&self) -> &[u64] {
+        &self.0
+    }
+
+    /// How far apart two versions are, judged by the first part that differs.
+    pub fn change_to(&self, other: &Version) -> Change {
+        let len = self.0.len().max(other.0.len());
+        let part = |version: &Version, index: usize| version.0.get(index).copied().unwrap_or(0);
+        match (0..len).find(|&index| part(self, index) != part(other, index)) {
+            None => Change::None,
+            Some(0) => Change::Major,
+            Some(1) => Change::Minor,
+            Some(2) => Change::Patch,
+            Some(_) => Change::Build,
+        }
+    }
+}
+
+impl From<Vec<u64>> for Version {
+    fn from(parts: Vec<u64>) -> Version {
+        Version(parts)
+    }
+}
+
+impl FromStr for Version {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Version, Self::Err> {
+        s.split('.')
+            .map(str::parse)
+            .collect::<Result<Vec<u64>, _>>()
+            .map(Version)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|part| part.to_string()).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+/// The most significant part of the version an update changes, ordered from
+/// the smallest change to the largest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum Change {
+    /// The same version.
+    None,
+    Build,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::None => write!(f, "none"),
+            Change::Build => write!(f, "build"),
+            Change::Patch => write!(f, "patch"),
+            Change::Minor => write!(f, "minor"),
+            Change::Major => write!(f, "major"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_version_parse_and_order() {
+        assert_eq!(version("1.21.92.1").parts(), &[1, 21, 92, 1]);
+        assert_eq!(version("1.21.92.1").to_string(), "1.21.92.1");
+        assert!(version("1.21.100.6") > version("1.21.92.1"));
+        assert!(version("1.21.92.10") > version("1.21.92.9"));
+        assert!("1.21.x".parse::<Version>().is_err());
+        assert!("".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn test_change_to() {
+        let installed = version("1.21.92.1");
+
+        assert_eq!(installed.change_to(&version("1.21.92.1")), Change::None);
+        assert_eq!(installed.change_to(&version("1.21.92.2")), Change::Build);
+        assert_eq!(installed.change_to(&version("1.21.93.1")), Change::Patch);
+        assert_eq!(installed.change_to(&version("1.22.0.3")), Change::Minor);
+        assert_eq!(installed.change_to(&version("2.0.0.1")), Change::Major);
+        assert_eq!(installed.change_to(&version("1.21.90.3")), Change::Patch);
+        assert_eq!(version("1.21.92").change_to(&installed), Change::Build);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{Error, Result};
+use crate::nbt::{self, Tag};
+use crate::properties;
+use crate::version::Version;
+use std::path::Path;
+
+/// The directory in the server that holds one directory per world.
+pub const WORLDS_DIR: &str = "worlds";
+pub const LEVEL_DAT: &str = "level.dat";
+/// The `level-name` of a server whose `server.properties` doesn't set one.
+pub const DEFAULT_LEVEL_NAME: &str = "Bedrock level";
+/// `level.dat` starts with the storage version and the length of the NBT
+/// that follows, both 32-bit little-endian.
+const LEVEL_DAT_HEADER_LEN: usize = 8;
+
+/// The versions a world's `level.dat` records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct World {
+    /// The world's directory in `worlds`.
+    pub name: String,
+    /// The version of the server that last opened the world.
+    pub last_opened_with: Option<Version>,
+    /// The oldest version that can open the world.
+    pub minimum_compatible_client: Option<Version>,
+}
+
+impl World {
+    /// The oldest server version that can open the world without risking it.
+    pub fn required_version(&self) -> Option<&Version> {
+        self.last_opened_with
+            .iter()
+            .chain(&self.minimum_compatible_client)
+            .max()
+    }
+}
+
+/// The world the server loads, from its `server.properties`.
+pub fn level_name(server_path: &Path) -> String {
+    properties::read_server_properties(server_path)
+        .ok()
+        .and_then(|properties| properties.get("level-name").cloned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_LEVEL_NAME.to_owned())
+}
+
+/// Reads the versions from the `level.dat` of the world in `dir`.
+pub fn read_world(dir: &Path) -> Result<World> {
+    let path = dir.join(LEVEL_DAT);
+    let bytes = std::fs::read(&path)?;
+    let invalid = |message: String| Error::Other(format!("{}: {}", path.display(), message));
+    let nbt = bytes
+        .get(LEVEL_DAT_HEADER_LEN..)
+        .ok_or_else(|| invalid("too short".to_owned()))?;
+    let (_, level) = nbt::read(nbt).map_err(|e| invalid(e.to_string()))?;
+
+    Ok(World {
+        name: dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        last_opened_with: level.get("lastOpenedWithVersion").and_then(version),
+        minimum_compatible_client: level
+            .get("MinimumCompatibleClientVersion")
+            .and_then(version),
+    })
+}
+
+/// The worlds of the server that have a `level.dat`, sorted by name.
+pub fn read_worlds(server_path: &Path) -> Result<Vec<World>> {
+    let entries = match std::fs::read_dir(server_path.join(WORLDS_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut worlds = Vec::new();
+    for entry in entries {
+        let dir = entry?.path();
+        if dir.join(LEVEL_DAT).is_file() {
+            worlds.push(read_world(&dir)?);
+        }
+    }
+    worlds.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(worlds)
+}
+
+/// Fails with [`Error::Downgrade`] when a world of the server was opened by a
+/// newer version than `version`, which an older server can corrupt.
+pub fn check_downgrade(server_path: &Path, version: &Version) -> Result<()> {
+    let newer: Vec<World> = read_worlds(server_path)?
+        .into_iter()
+        .filter(|world| world.required_version().is_some_and(|v| v > version))
+        .collect();
+    if newer.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Downgrade {
+        version: version.clone(),
+        worlds: newer,
+    })
+}
+
+/// Reads a version list like `[1, 21, 92, 1, 0]` as the server version
+/// `1.21.92.1`, the form releases are named in.
+fn version(tag: &Tag) -> Option<Version> {
+    let Tag::List(parts) = tag else {
+        return None;
+    };
+    let mut parts = parts
+        .iter()
+        .map(|part| match part {
+            Tag::Int(part) => u64::try_from(*part).ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<u64>>>()?;
+    parts.resize(4, 0);
+    Some(Version::from(parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_level_dat(dir: &Path, versions: &[(&str, [i32; 5])]) {
+        let mut nbt = vec![10, 0, 0];
+        for (name, version) in versions {
+            nbt.push(9);
+            nbt.extend((name.len() as u16).to_le_bytes());
+            nbt.extend(name.as_bytes());
+            nbt.push(3);
+            nbt.extend(5i32.to_le_bytes());
+            for part in version {
+                nbt.extend(part.to_le_bytes());
+            }
+        }
+        nbt.push(0);
+
+        let mut bytes = 10u32.to_le_bytes().to_vec();
+        bytes.extend((nbt.len() as u32).to_le_bytes());
+        bytes.extend(nbt);
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join(LEVEL_DAT), bytes).unwrap();
+    }
+
+    #[test]
+    fn test_check_downgrade() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let server_path = temp_dir.path();
+        let worlds = server_path.join(WORLDS_DIR);
+        write_level_dat(
+            &worlds.join("Survival"),
+            &[
+                ("lastOpenedWithVersion", [1, 21, 92, 1, 0]),
+                ("MinimumCompatibleClientVersion", [1, 21, 90, 0, 0]),
+            ],
+        );
+        write_level_dat(
+            &worlds.join("Creative"),
+            &[("MinimumCompatibleClientVersion", [1, 21, 80, 0, 0])],
+        );
+        std::fs::create_dir_all(worlds.join("Empty")).unwrap();
+
+        let read = read_worlds(server_path).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].name, "Survival");
+        assert_eq!(
+            read[1].required_version(),
+            Some(&"1.21.92.1".parse().unwrap())
+        );
+
+        check_downgrade(server_path, &"1.21.92.1".parse().unwrap()).unwrap();
+        check_downgrade(server_path, &"1.21.100.6".parse().unwrap()).unwrap();
+        match check_downgrade(server_path, &"1.21.84.1".parse().unwrap()) {
+            Err(Error::Downgrade { worlds, .. }) => {
+                assert_eq!(worlds.len(), 1);
+                assert_eq!(worlds[0].name, "Survival");
+            }
+            other => panic!("expected a downgrade error, got {:?}", other),
+        }
+        match check_downgrade(server_path, &"1.21.70.3".parse().unwrap()) {
+            Err(Error::Downgrade { worlds, .. }) => assert_eq!(worlds.len(), 2),
+            other => panic!("expected a downgrade error, got {:?}", other),
+        }
+
+        std::fs::write(worlds.join("Creative").join(LEVEL_DAT), [0; 12]).unwrap();
+        assert!(matches!(
+            check_downgrade(server_path, &"1.21.92.1".parse().unwrap()),
+            Err(Error::Other(_))
+        ));
+    }
+}